@@ -5,7 +5,12 @@ use clap::Parser;
 use reth::chainspec::EthereumChainSpecParser;
 use reth_node_ethereum::EthereumNode;
 use reth_tracing::tracing::info;
-use searcher_reth_extension::{ exex::SearcherExEx, SearcherExtension, SetupArgs };
+use searcher_reth_extension::{
+    exex::SearcherExEx,
+    output::OutputSocket,
+    SearcherExtension,
+    SetupArgs,
+};
 use searcher_reth_repository::SearcherRepository;
 use searcher_reth_rpc::{ SearcherRpc, SearcherRpcApiServer };
 use tokio::{ net::UnixDatagram, sync::RwLock };
@@ -13,16 +18,23 @@ use tokio::{ net::UnixDatagram, sync::RwLock };
 fn main() -> eyre::Result<()> {
     // database
     reth::cli::Cli::<EthereumChainSpecParser, SetupArgs>::parse().run(|builder, args| async move {
-        let sock = Arc::new(UnixDatagram::unbound()?);
-        let socket_path = args.socket_path.clone();
-        sock.connect(socket_path)?;
+        let chain_id = builder.config().chain.chain.id();
+        let sock = UnixDatagram::unbound()?;
+        sock.connect(&args.socket_path)?;
+        let mut sock = OutputSocket::new(sock, chain_id);
+        if let Some(max_datagram) = args.max_datagram {
+            sock = sock.with_max_datagram(max_datagram);
+        }
+        let sock = Arc::new(sock);
 
         let db_path = builder.config().datadir().db().join("searcher.db");
-        let chain_id = builder.config().chain.chain.id();
         let repository = Arc::new(SearcherRepository::new(db_path.to_str().unwrap()).await?);
-        let extension = Arc::new(RwLock::new(SearcherExtension::new(args).unwrap()));
-        let extension_for_rpc = extension.clone();
-        let extension_for_exex = extension.clone();
+        let extension = SearcherExtension::new(args).unwrap();
+        // the exex follows the configuration through its watch channel, the rpc updates it
+        let config_rx = extension.subscribe();
+        let extension_for_rpc = Arc::new(RwLock::new(extension));
+
+        let repository_for_rpc = repository.clone();
 
         let handle = builder
             .node(EthereumNode::default())
@@ -33,7 +45,7 @@ fn main() -> eyre::Result<()> {
                             ::new()
                             .expect("failed to spawn blocking runtime");
                         rt.block_on(
-                            SearcherRpc::new(chain_id, extension_for_rpc, repository.clone())
+                            SearcherRpc::new(chain_id, extension_for_rpc, repository_for_rpc)
                         )
                     })
                     .join()
@@ -48,7 +60,12 @@ fn main() -> eyre::Result<()> {
             })
             .install_exex("SearcherExEx", {
                 move |ctx| {
-                    let exex = SearcherExEx::exex(ctx, extension_for_exex, sock.clone());
+                    let exex = SearcherExEx::exex(
+                        ctx,
+                        config_rx.clone(),
+                        sock.clone(),
+                        repository.clone()
+                    );
                     info!(target : "reth-exex", info = "SearcherExEx installed successfully");
                     exex
                 }
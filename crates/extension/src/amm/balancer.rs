@@ -0,0 +1,268 @@
+use alloy_primitives::{ address, Address, B256, U256 };
+use alloy_sol_types::{ sol, SolCall, SolValue };
+use eyre::Result;
+
+use super::{ call_word, IERC20Metadata, PoolSource };
+
+sol! {
+    interface IWeightedPool {
+        function getVault() external view returns (address);
+        function getPoolId() external view returns (bytes32);
+        function getNormalizedWeights() external view returns (uint256[]);
+        function getSwapFeePercentage() external view returns (uint256);
+    }
+
+    interface IVault {
+        function getPoolTokens(bytes32 poolId)
+            external
+            view
+            returns (address[] tokens, uint256[] balances, uint256 lastChangeBlock);
+    }
+}
+
+/// The Balancer V2 Vault, at the same address on every chain. It holds the balances of all pools.
+pub const VAULT: Address = address!("BA12222222228d8Ba445958a75a0704d566BF2C8");
+
+const ONE: u64 = 1_000_000_000_000_000_000;
+/// `WeightedMath._MAX_IN_RATIO`, a swap can't take more than 30% of the input balance.
+const MAX_IN_RATIO: u64 = 300_000_000_000_000_000;
+
+/// A Balancer V2 weighted pool.
+///
+/// Only the weight ratios that `FixedPoint.powUp` computes without `LogExpMath.pow` are quoted
+/// natively: 1 (50/50 pools), 2 and 4 (selling the heavy token of 2:1 and 80/20 pools). Any other
+/// pair of tokens, e.g. buying the heavy token of an 80/20 pool, is simulated in the EVM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BalancerWeightedPool {
+    pub address: Address,
+    pub tokens: Vec<Address>,
+    pub balances: Vec<U256>,
+    /// Normalized weights, 18 decimals.
+    pub weights: Vec<U256>,
+    /// `10^(18 - decimals)` per token.
+    pub scaling_factors: Vec<U256>,
+    /// Swap fee percentage, 18 decimals.
+    pub swap_fee: U256,
+}
+
+impl BalancerWeightedPool {
+    /// Reads weights and fee from the pool and the balances from the vault.
+    pub fn load<S: PoolSource + ?Sized>(source: &mut S, address: Address) -> Result<Self> {
+        let vault = call_word(source, address, IWeightedPool::getVaultCall {})?;
+        let vault = Address::from_word(vault.to_be_bytes::<32>().into());
+        let pool_id = B256::from(
+            call_word(source, address, IWeightedPool::getPoolIdCall {})?.to_be_bytes::<32>()
+        );
+
+        let output = source.call(
+            address,
+            IWeightedPool::getNormalizedWeightsCall {}.abi_encode().into()
+        )?;
+        let (weights,) = <(Vec<U256>,)>::abi_decode_params(&output)?;
+
+        let output = source.call(
+            vault,
+            IVault::getPoolTokensCall { poolId: pool_id }.abi_encode().into()
+        )?;
+        let (tokens, balances, _) = <(Vec<Address>, Vec<U256>, U256)>::abi_decode_params(&output)?;
+
+        let scaling_factors = tokens
+            .iter()
+            .map(|token| {
+                let decimals = call_word(source, *token, IERC20Metadata::decimalsCall {})?;
+                Ok(U256::from(10).pow(U256::from(18).saturating_sub(decimals)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            address,
+            tokens,
+            balances,
+            weights,
+            scaling_factors,
+            swap_fee: call_word(source, address, IWeightedPool::getSwapFeePercentageCall {})?,
+        })
+    }
+
+    pub fn reserve_of(&self, token: Address) -> Option<U256> {
+        self.index_of(token).map(|i| self.balances[i])
+    }
+
+    /// Whether `src_token -> dst_token` can be quoted natively: only the weight ratios that
+    /// `FixedPoint.powUp` special-cases are ported, see [`pow_up`].
+    pub fn quotes(&self, src_token: Address, dst_token: Address) -> bool {
+        let (Some(i), Some(j)) = (self.index_of(src_token), self.index_of(dst_token)) else {
+            return false;
+        };
+        div_down(self.weights[i], self.weights[j]).is_some_and(is_special_exponent)
+    }
+
+    /// `onSwap` for a given-in swap: fee, upscaling and `WeightedMath._calcOutGivenIn`.
+    /// `None` if the swap is impossible, or for weight ratios that [`Self::quotes`] rejects.
+    pub fn amount_out(
+        &self,
+        src_token: Address,
+        dst_token: Address,
+        amount_in: U256
+    ) -> Option<U256> {
+        let i = self.index_of(src_token)?;
+        let j = self.index_of(dst_token)?;
+        if i == j || amount_in.is_zero() {
+            return None;
+        }
+
+        let amount_in = amount_in.checked_sub(mul_up(amount_in, self.swap_fee)?)?;
+        let amount_in = amount_in.checked_mul(self.scaling_factors[i])?;
+        let balance_in = self.balances[i].checked_mul(self.scaling_factors[i])?;
+        let balance_out = self.balances[j].checked_mul(self.scaling_factors[j])?;
+
+        if amount_in > mul_down(balance_in, U256::from(MAX_IN_RATIO))? {
+            return None;
+        }
+        let base = div_up(balance_in, balance_in.checked_add(amount_in)?)?;
+        let exponent = div_down(self.weights[i], self.weights[j])?;
+        let power = pow_up(base, exponent)?;
+        let amount_out = mul_down(balance_out, complement(power))?;
+
+        Some(amount_out / self.scaling_factors[j])
+    }
+
+    fn index_of(&self, token: Address) -> Option<usize> {
+        self.tokens.iter().position(|t| *t == token)
+    }
+}
+
+fn mul_down(a: U256, b: U256) -> Option<U256> {
+    Some(a.checked_mul(b)? / U256::from(ONE))
+}
+
+fn mul_up(a: U256, b: U256) -> Option<U256> {
+    let product = a.checked_mul(b)?;
+    if product.is_zero() {
+        return Some(U256::ZERO);
+    }
+    Some((product - U256::from(1)) / U256::from(ONE) + U256::from(1))
+}
+
+fn div_down(a: U256, b: U256) -> Option<U256> {
+    a.checked_mul(U256::from(ONE))?.checked_div(b)
+}
+
+fn div_up(a: U256, b: U256) -> Option<U256> {
+    if b.is_zero() {
+        return None;
+    }
+    if a.is_zero() {
+        return Some(U256::ZERO);
+    }
+    Some((a.checked_mul(U256::from(ONE))? - U256::from(1)) / b + U256::from(1))
+}
+
+fn complement(x: U256) -> U256 {
+    U256::from(ONE).saturating_sub(x)
+}
+
+/// Exponents of `1`, `2` and `4` (18 decimals), which `FixedPoint.powUp` computes with `mulUp`.
+fn is_special_exponent(y: U256) -> bool {
+    let one = U256::from(ONE);
+    y == one || y == one * U256::from(2) || y == one * U256::from(4)
+}
+
+/// `FixedPoint.powUp` for the exponents it special-cases: the weight ratios of 50/50 pools, of
+/// selling the heavy token of an 80/20 pool, and of exact 2:1 weights. Any other exponent goes
+/// through `LogExpMath.pow`, which is not ported: `None`, and the hop is simulated in the EVM.
+fn pow_up(x: U256, y: U256) -> Option<U256> {
+    let one = U256::from(ONE);
+    if y == one {
+        Some(x)
+    } else if y == one * U256::from(2) {
+        mul_up(x, x)
+    } else if y == one * U256::from(4) {
+        let square = mul_up(x, x)?;
+        mul_up(square, square)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{ Address, U256 };
+
+    use super::BalancerWeightedPool;
+
+    const WETH: Address = Address::with_last_byte(1);
+    const USDC: Address = Address::with_last_byte(2);
+    const BAL: Address = Address::with_last_byte(3);
+
+    fn e(decimals: u32) -> U256 {
+        U256::from(10).pow(U256::from(decimals))
+    }
+
+    /// 10k WETH against 30M USDC at 0.3%.
+    fn weth_usdc() -> BalancerWeightedPool {
+        BalancerWeightedPool {
+            address: Address::ZERO,
+            tokens: vec![WETH, USDC],
+            balances: vec![U256::from(10_000) * e(18), U256::from(30_000_000) * e(6)],
+            weights: vec![U256::from(5) * e(17), U256::from(5) * e(17)],
+            scaling_factors: vec![U256::from(1), e(12)],
+            swap_fee: U256::from(3) * e(15),
+        }
+    }
+
+    /// 80/20 BAL/WETH, 10M BAL against 5k WETH at 1%.
+    fn bal_weth() -> BalancerWeightedPool {
+        BalancerWeightedPool {
+            address: Address::ZERO,
+            tokens: vec![BAL, WETH],
+            balances: vec![U256::from(10_000_000) * e(18), U256::from(5_000) * e(18)],
+            weights: vec![U256::from(8) * e(17), U256::from(2) * e(17)],
+            scaling_factors: vec![U256::from(1), U256::from(1)],
+            swap_fee: e(16),
+        }
+    }
+
+    /// `amount` rounds down from `exact`, the out given in of `WeightedMath` solved in high
+    /// precision, by at most 1e-13 of it: `FixedPoint` rounds in favour of the pool.
+    fn assert_rounds_down_from(amount: Option<U256>, exact: &str) {
+        let exact = exact.parse::<U256>().unwrap();
+        let amount = amount.unwrap();
+        assert!(amount <= exact && exact - amount <= exact / e(13), "{amount} vs {exact}");
+    }
+
+    #[test]
+    fn quotes_even_weights() {
+        let pool = weth_usdc();
+        assert!(pool.quotes(WETH, USDC) && pool.quotes(USDC, WETH));
+        assert_rounds_down_from(pool.amount_out(WETH, USDC, U256::from(10) * e(18)), "29880209431");
+        assert_rounds_down_from(
+            pool.amount_out(USDC, WETH, U256::from(50_000) * e(6)),
+            "16589101110321682138"
+        );
+    }
+
+    #[test]
+    fn quotes_selling_the_heavy_token_only() {
+        let pool = bal_weth();
+        assert!(pool.quotes(BAL, WETH));
+        assert_rounds_down_from(
+            pool.amount_out(BAL, WETH, U256::from(1_000) * e(18)),
+            "1979510047013092232"
+        );
+        // an exponent of 1/4 needs `LogExpMath.pow`
+        assert!(!pool.quotes(WETH, BAL));
+        assert_eq!(pool.amount_out(WETH, BAL, e(18)), None);
+    }
+
+    #[test]
+    fn rejects_swaps_over_the_max_in_ratio() {
+        let pool = weth_usdc();
+        // 30% of the balance after the 0.3% fee
+        let max_in = (U256::from(3_000) * e(18) * U256::from(1_000)) / U256::from(997);
+        assert!(pool.amount_out(WETH, USDC, max_in).is_some());
+        assert_eq!(pool.amount_out(WETH, USDC, max_in + e(15)), None);
+        assert_eq!(pool.amount_out(WETH, WETH, e(18)), None);
+        assert_eq!(pool.amount_out(BAL, USDC, e(18)), None);
+    }
+}
@@ -0,0 +1,309 @@
+use alloy_primitives::{ Address, U256 };
+use alloy_sol_types::sol;
+use eyre::Result;
+
+use super::{ call_word, IERC20Metadata, PoolSource };
+
+sol! {
+    interface ICurvePool {
+        function coins(uint256 i) external view returns (address);
+        function balances(uint256 i) external view returns (uint256);
+        function fee() external view returns (uint256);
+        function initial_A() external view returns (uint256);
+        function future_A() external view returns (uint256);
+        function initial_A_time() external view returns (uint256);
+        function future_A_time() external view returns (uint256);
+        function A_precise() external view returns (uint256);
+    }
+}
+
+/// Largest number of coins probed through `coins(i)`.
+pub const MAX_COINS: usize = 8;
+/// Fees are expressed over 1e10.
+pub const FEE_DENOMINATOR: u64 = 10_000_000_000;
+/// Pools exposing `A_precise()` store `A * 100`.
+pub const A_PRECISION: u64 = 100;
+
+const PRECISION: u64 = 1_000_000_000_000_000_000;
+const MAX_ITERATIONS: usize = 255;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CurveStableSwapPool {
+    pub address: Address,
+    pub coins: Vec<Address>,
+    pub balances: Vec<U256>,
+    /// `10^(36 - decimals)`, normalizing every coin to 18 decimals.
+    pub rates: Vec<U256>,
+    /// Amplification at the simulated timestamp, scaled by `a_precision`.
+    pub amp: U256,
+    pub a_precision: U256,
+    pub fee: U256,
+}
+
+impl CurveStableSwapPool {
+    /// Reads coins, balances, fee and the `A` ramp through the pool getters.
+    pub fn load<S: PoolSource + ?Sized>(source: &mut S, address: Address) -> Result<Self> {
+        let mut coins = Vec::new();
+        let mut balances = Vec::new();
+        let mut rates = Vec::new();
+        for i in 0..MAX_COINS {
+            // `coins(i)` reverts past the last coin
+            let Ok(coin) = call_word(source, address, ICurvePool::coinsCall { i: U256::from(i) })
+            else {
+                break;
+            };
+            let coin = Address::from_word(coin.to_be_bytes::<32>().into());
+            let balance = call_word(source, address, ICurvePool::balancesCall {
+                i: U256::from(i),
+            })?;
+            let decimals = call_word(source, coin, IERC20Metadata::decimalsCall {})?;
+
+            coins.push(coin);
+            balances.push(balance);
+            rates.push(U256::from(10).pow(U256::from(36) - decimals.min(U256::from(36))));
+        }
+
+        let a_precision = if call_word(source, address, ICurvePool::A_preciseCall {}).is_ok() {
+            U256::from(A_PRECISION)
+        } else {
+            U256::from(1)
+        };
+        let amp = ramped_a(
+            call_word(source, address, ICurvePool::initial_ACall {})?,
+            call_word(source, address, ICurvePool::future_ACall {})?,
+            call_word(source, address, ICurvePool::initial_A_timeCall {})?,
+            call_word(source, address, ICurvePool::future_A_timeCall {})?,
+            U256::from(source.timestamp())
+        );
+
+        Ok(Self {
+            address,
+            coins,
+            balances,
+            rates,
+            amp,
+            a_precision,
+            fee: call_word(source, address, ICurvePool::feeCall {})?,
+        })
+    }
+
+    pub fn reserve_of(&self, token: Address) -> Option<U256> {
+        self.index_of(token).map(|i| self.balances[i])
+    }
+
+    /// `get_dy(i, j, dx)` of the pool, `None` without a route or if the math overflows.
+    pub fn amount_out(&self, src_token: Address, dst_token: Address, dx: U256) -> Option<U256> {
+        let i = self.index_of(src_token)?;
+        let j = self.index_of(dst_token)?;
+        if i == j || dx.is_zero() {
+            return None;
+        }
+        let precision = U256::from(PRECISION);
+
+        let xp = self.xp()?;
+        let x = xp[i].checked_add(dx.checked_mul(self.rates[i])? / precision)?;
+        let y = self.get_y(i, j, x, &xp)?;
+        let dy = xp[j].checked_sub(y)?.checked_sub(U256::from(1))?.checked_mul(precision)? /
+            self.rates[j];
+        let fee = (self.fee * dy) / U256::from(FEE_DENOMINATOR);
+        dy.checked_sub(fee)
+    }
+
+    fn index_of(&self, token: Address) -> Option<usize> {
+        self.coins.iter().position(|coin| *coin == token)
+    }
+
+    /// Balances normalized to 18 decimals.
+    fn xp(&self) -> Option<Vec<U256>> {
+        self.balances
+            .iter()
+            .zip(&self.rates)
+            .map(|(balance, rate)| balance.checked_mul(*rate).map(|xp| xp / U256::from(PRECISION)))
+            .collect()
+    }
+
+    /// Newton iteration of the StableSwap invariant `D`.
+    fn get_d(&self, xp: &[U256]) -> Option<U256> {
+        let n = U256::from(xp.len());
+        let sum = xp.iter().try_fold(U256::ZERO, |sum, x| sum.checked_add(*x))?;
+        if sum.is_zero() {
+            return Some(U256::ZERO);
+        }
+
+        let ann = self.amp.checked_mul(n)?;
+        let mut d = sum;
+        for _ in 0..MAX_ITERATIONS {
+            let mut d_p = d;
+            for x in xp {
+                d_p = d_p.checked_mul(d)?.checked_div(x.checked_mul(n)?)?;
+            }
+            let d_prev = d;
+            let numerator = ((ann * sum) / self.a_precision)
+                .checked_add(d_p.checked_mul(n)?)?
+                .checked_mul(d)?;
+            let denominator = (ann.checked_sub(self.a_precision)? * d) / self.a_precision +
+                (n + U256::from(1)) * d_p;
+            d = numerator.checked_div(denominator)?;
+            if d.abs_diff(d_prev) <= U256::from(1) {
+                return Some(d);
+            }
+        }
+        None
+    }
+
+    /// Balance of coin `j` keeping `D` constant once coin `i` is at `x`.
+    fn get_y(&self, i: usize, j: usize, x: U256, xp: &[U256]) -> Option<U256> {
+        let n = U256::from(xp.len());
+        let d = self.get_d(xp)?;
+        let ann = self.amp.checked_mul(n)?;
+
+        let mut c = d;
+        let mut sum = U256::ZERO;
+        for (k, balance) in xp.iter().enumerate() {
+            let x_k = if k == i {
+                x
+            } else if k != j {
+                *balance
+            } else {
+                continue;
+            };
+            sum = sum.checked_add(x_k)?;
+            c = c.checked_mul(d)?.checked_div(x_k.checked_mul(n)?)?;
+        }
+        c = c.checked_mul(d)?.checked_mul(self.a_precision)?.checked_div(ann.checked_mul(n)?)?;
+        let b = sum + (d * self.a_precision).checked_div(ann)?;
+
+        let mut y = d;
+        for _ in 0..MAX_ITERATIONS {
+            let y_prev = y;
+            y = y
+                .checked_mul(y)?
+                .checked_add(c)?
+                .checked_div((U256::from(2) * y + b).checked_sub(d)?)?;
+            if y.abs_diff(y_prev) <= U256::from(1) {
+                return Some(y);
+            }
+        }
+        None
+    }
+}
+
+/// `A` ramped linearly between `initial_A` and `future_A`, like the pool's `_A()`.
+fn ramped_a(
+    initial_a: U256,
+    future_a: U256,
+    initial_time: U256,
+    future_time: U256,
+    timestamp: U256
+) -> U256 {
+    if timestamp >= future_time || future_time <= initial_time {
+        return future_a;
+    }
+    let elapsed = timestamp.saturating_sub(initial_time);
+    let duration = future_time - initial_time;
+    if future_a > initial_a {
+        initial_a + ((future_a - initial_a) * elapsed) / duration
+    } else {
+        initial_a - ((initial_a - future_a) * elapsed) / duration
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{ Address, U256 };
+
+    use super::{ ramped_a, CurveStableSwapPool };
+
+    const DAI: Address = Address::with_last_byte(1);
+    const USDC: Address = Address::with_last_byte(2);
+    const USDT: Address = Address::with_last_byte(3);
+
+    fn e(decimals: u32) -> U256 {
+        U256::from(10).pow(U256::from(decimals))
+    }
+
+    /// An imbalanced 3pool: 160M DAI, 170M USDC and 90M USDT, A = 2000 and a 0.01% fee.
+    fn three_pool(amp: u64, a_precision: u64) -> CurveStableSwapPool {
+        CurveStableSwapPool {
+            address: Address::ZERO,
+            coins: vec![DAI, USDC, USDT],
+            balances: vec![
+                U256::from(160_000_000) * e(18),
+                U256::from(170_000_000) * e(6),
+                U256::from(90_000_000) * e(6)
+            ],
+            rates: vec![e(18), e(30), e(30)],
+            amp: U256::from(amp),
+            a_precision: U256::from(a_precision),
+            fee: U256::from(1_000_000),
+        }
+    }
+
+    /// `get_dy` is within a unit of the output coin of `exact`, the StableSwap invariant solved in
+    /// high precision.
+    fn assert_near(amount: Option<U256>, exact: &str) {
+        let exact = exact.parse::<U256>().unwrap();
+        let amount = amount.unwrap();
+        assert!(amount.abs_diff(exact) <= U256::from(1), "{amount} vs {exact}");
+    }
+
+    #[test]
+    fn get_dy_matches_the_invariant() {
+        let pool = three_pool(2000, 1);
+        assert_near(
+            pool.amount_out(USDC, DAI, U256::from(1_000_000) * e(6)),
+            "999868276767823964894622"
+        );
+        assert_near(pool.amount_out(DAI, USDT, e(24)), "999511625950");
+        assert_near(pool.amount_out(USDT, USDC, U256::from(50_000_000) * e(6)), "50002000285572");
+    }
+
+    #[test]
+    fn a_precise_pools_quote_alike() {
+        let (pool, precise) = (three_pool(2000, 1), three_pool(200_000, 100));
+        for (src, dst, dx) in [(USDC, DAI, e(12)), (DAI, USDT, e(24)), (USDT, USDC, e(13))] {
+            assert_eq!(pool.amount_out(src, dst, dx), precise.amount_out(src, dst, dx));
+        }
+    }
+
+    #[test]
+    fn get_d_of_a_balanced_pool_is_its_sum() {
+        let pool = three_pool(2000, 1);
+        let xp = [e(24), e(24), e(24)];
+        assert_eq!(pool.get_d(&xp), Some(U256::from(3) * e(24)));
+        assert_eq!(pool.get_d(&[U256::ZERO; 3]), Some(U256::ZERO));
+    }
+
+    #[test]
+    fn get_y_keeps_d() {
+        let pool = three_pool(2000, 1);
+        let xp = pool.xp().unwrap();
+        let d = pool.get_d(&xp).unwrap();
+        // unchanged balances solve to the same balance
+        assert!(pool.get_y(1, 0, xp[1], &xp).unwrap().abs_diff(xp[0]) <= U256::from(1));
+
+        let mut swapped = xp.clone();
+        swapped[1] += U256::from(5_000_000) * e(18);
+        swapped[0] = pool.get_y(1, 0, swapped[1], &xp).unwrap();
+        assert!(swapped[0] < xp[0]);
+        assert!(pool.get_d(&swapped).unwrap().abs_diff(d) <= U256::from(2));
+    }
+
+    #[test]
+    fn ramps_a_linearly() {
+        let a = |initial: u64, future: u64, timestamp: u64| {
+            ramped_a(
+                U256::from(initial),
+                U256::from(future),
+                U256::from(1_000),
+                U256::from(2_000),
+                U256::from(timestamp)
+            )
+        };
+        assert_eq!(a(100, 200, 1_000), U256::from(100));
+        assert_eq!(a(100, 200, 1_250), U256::from(125));
+        assert_eq!(a(200, 100, 1_250), U256::from(175));
+        assert_eq!(a(100, 200, 2_000), U256::from(200));
+        assert_eq!(a(100, 200, 5_000), U256::from(200));
+    }
+}
@@ -0,0 +1,286 @@
+pub mod balancer;
+pub mod curve;
+pub mod uniswap_v2;
+pub mod uniswap_v3;
+
+use alloy_primitives::{ Address, Bytes, U256 };
+use alloy_sol_types::{ sol, SolCall };
+use eyre::{ eyre, Result };
+use searcher_reth_repository::types::dex_type;
+
+use crate::strategy::path_finding::types::{ Hop, RoutePath, PROFIT_RATIO_PRECISION };
+use balancer::BalancerWeightedPool;
+use curve::CurveStableSwapPool;
+use uniswap_v2::UniswapV2Pool;
+use uniswap_v3::UniswapV3Pool;
+
+sol! {
+    interface IERC20Metadata {
+        function decimals() external view returns (uint8);
+    }
+}
+
+/// Probe size of a native estimate, as a fraction of the first pool's input reserve. Small enough
+/// for price impact to stay well under the ppm profit thresholds.
+pub const PROBE_RESERVE_DIVISOR: u64 = 1_000_000;
+
+/// Probe size of a native estimate on a pool holding `reserve` of the input token: a
+/// [`PROBE_RESERVE_DIVISOR`] share of it, but at least about its square root. Under a reserve of
+/// 1e12 the share would be quoted with a large rounding error (nothing at all under 1e6), and at
+/// the square root the rounding weighs about as much as the price impact.
+pub fn probe_amount(reserve: U256) -> U256 {
+    let square_root = U256::from(1) << (reserve.bit_len() / 2);
+    (reserve / U256::from(PROBE_RESERVE_DIVISOR)).max(square_root)
+}
+
+/// Where native quoting reads pool state from.
+pub trait PoolSource {
+    /// Reads a storage slot of `address`.
+    fn storage(&mut self, address: Address, slot: U256) -> Result<U256>;
+
+    /// Calls a view function of `address`, for values that are not in storage (immutables).
+    fn call(&mut self, address: Address, input: Bytes) -> Result<Bytes>;
+
+    /// Timestamp of the simulated block, for time-dependent pool parameters.
+    fn timestamp(&mut self) -> u64;
+
+    /// Swap fee in bps of the Uniswap V2 pairs deployed by `factory`.
+    fn uniswap_v2_fee_bps(&mut self, _factory: Address) -> u64 {
+        uniswap_v2::FEE_BPS
+    }
+
+    /// Loads the pool behind `hop`. Sources may override it to cache pool states.
+    fn pool(&mut self, hop: &Hop) -> Result<Option<PoolState>> {
+        PoolState::load(self, hop)
+    }
+}
+
+/// State of a pool that can be quoted natively, without an EVM round-trip.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PoolState {
+    UniswapV2(UniswapV2Pool),
+    UniswapV3(UniswapV3Pool),
+    CurveStableSwap(CurveStableSwapPool),
+    BalancerWeighted(BalancerWeightedPool),
+}
+
+impl PoolState {
+    /// Loads the pool behind `hop`, `None` if its dex type has no native quoting.
+    pub fn load<S: PoolSource + ?Sized>(source: &mut S, hop: &Hop) -> Result<Option<Self>> {
+        Ok(match hop.dexType {
+            dex_type::UNISWAP_V2 => Some(Self::UniswapV2(UniswapV2Pool::load(source, hop.dex)?)),
+            dex_type::UNISWAP_V3 => Some(Self::UniswapV3(UniswapV3Pool::load(source, hop)?)),
+            dex_type::CURVE_STABLESWAP =>
+                Some(Self::CurveStableSwap(CurveStableSwapPool::load(source, hop.dex)?)),
+            dex_type::BALANCER_WEIGHTED =>
+                Some(Self::BalancerWeighted(BalancerWeightedPool::load(source, hop.dex)?)),
+            _ => None,
+        })
+    }
+
+    /// Whether `hop` can be quoted natively through this pool, otherwise it's left to the EVM.
+    pub fn quotes(&self, hop: &Hop) -> bool {
+        match self {
+            Self::BalancerWeighted(pool) => pool.quotes(hop.srcToken, hop.dstToken),
+            _ => true,
+        }
+    }
+
+    /// Output of swapping `amount_in` along `hop`, `None` if the swap is impossible.
+    pub fn amount_out<S: PoolSource + ?Sized>(
+        &self,
+        source: &mut S,
+        hop: &Hop,
+        amount_in: U256
+    ) -> Result<Option<U256>> {
+        match self {
+            Self::UniswapV2(pool) => Ok(pool.amount_out(hop.srcToken, amount_in)),
+            Self::UniswapV3(pool) => pool.amount_out(source, hop.srcToken, amount_in),
+            Self::CurveStableSwap(pool) =>
+                Ok(pool.amount_out(hop.srcToken, hop.dstToken, amount_in)),
+            Self::BalancerWeighted(pool) =>
+                Ok(pool.amount_out(hop.srcToken, hop.dstToken, amount_in)),
+        }
+    }
+
+    /// Reserve of `token` available to the pool, used to size probes.
+    pub fn reserve_of<S: PoolSource + ?Sized>(
+        &self,
+        source: &mut S,
+        token: Address
+    ) -> Result<Option<U256>> {
+        match self {
+            Self::UniswapV2(pool) => Ok(pool.reserve_of(token)),
+            Self::UniswapV3(pool) => pool.reserve_of(source, token),
+            Self::CurveStableSwap(pool) => Ok(pool.reserve_of(token)),
+            Self::BalancerWeighted(pool) => Ok(pool.reserve_of(token)),
+        }
+    }
+}
+
+/// Loads the pool behind `hop` if it can quote the hop natively, `Ok(None)` leaves it to the EVM.
+pub fn native_pool<S: PoolSource + ?Sized>(source: &mut S, hop: &Hop) -> Result<Option<PoolState>> {
+    Ok(source.pool(hop)?.filter(|pool| pool.quotes(hop)))
+}
+
+/// Natively quotes a whole route. `Ok(None)` if a hop can't be quoted natively.
+pub fn quote_route<S: PoolSource + ?Sized>(
+    source: &mut S,
+    route_path: &RoutePath,
+    amount_in: U256
+) -> Result<Option<U256>> {
+    let mut amount = amount_in;
+    for hop in &route_path.hops {
+        let Some(pool) = native_pool(source, hop)? else {
+            return Ok(None);
+        };
+        amount = pool.amount_out(source, hop, amount)?.unwrap_or_default();
+    }
+    Ok(Some(amount))
+}
+
+/// Estimates the ppm profit ratio of a route for a probe sized on its first pool.
+/// `Ok(None)` if the route can't be estimated natively and must go to the EVM.
+pub fn estimate_profit_ratio<S: PoolSource + ?Sized>(
+    source: &mut S,
+    route_path: &RoutePath
+) -> Result<Option<u64>> {
+    let Some(first_hop) = route_path.hops.first() else {
+        return Ok(None);
+    };
+    let Some(first_pool) = source.pool(first_hop)? else {
+        return Ok(None);
+    };
+    let reserve = first_pool.reserve_of(source, first_hop.srcToken)?.unwrap_or_default();
+    let amount_in = probe_amount(reserve);
+
+    let amount_out = quote_route(source, route_path, amount_in)?;
+    Ok(amount_out.map(|amount_out| ratio_of(amount_in, amount_out)))
+}
+
+/// Natural log of the marginal rate of `hop` (amount out per amount in, after fees), quoted for a
+/// probe sized on the pool's input reserve. `Ok(None)` if the hop can't be quoted natively.
+pub fn log_spot_rate<S: PoolSource + ?Sized>(source: &mut S, hop: &Hop) -> Result<Option<f64>> {
+    let Some(pool) = native_pool(source, hop)? else {
+        return Ok(None);
+    };
+    let reserve = pool.reserve_of(source, hop.srcToken)?.unwrap_or_default();
+    let amount_in = probe_amount(reserve);
+
+    Ok(match pool.amount_out(source, hop, amount_in)? {
+        Some(amount_out) if !amount_out.is_zero() => Some(ln(amount_out) - ln(amount_in)),
+        _ => None,
+    })
+}
+
+/// Natural log of a non-zero `U256`, from its top 64 bits.
+fn ln(value: U256) -> f64 {
+    let bits = value.bit_len();
+    if bits <= 64 {
+        return (value.to::<u64>() as f64).ln();
+    }
+    let shift = bits - 64;
+    ((value >> shift).to::<u64>() as f64).ln() + (shift as f64) * std::f64::consts::LN_2
+}
+
+/// Calls a getter returning a single word.
+pub(crate) fn call_word<S: PoolSource + ?Sized, C: SolCall>(
+    source: &mut S,
+    address: Address,
+    call: C
+) -> Result<U256> {
+    let output = source.call(address, call.abi_encode().into())?;
+    if output.len() < 32 {
+        return Err(eyre!("unexpected return data length {} from {address}", output.len()));
+    }
+    Ok(U256::from_be_slice(&output[..32]))
+}
+
+/// ppm profit ratio of receiving `amount_out` for `amount_in`, 0 on a loss.
+pub fn ratio_of(amount_in: U256, amount_out: U256) -> u64 {
+    if amount_in.is_zero() || amount_out <= amount_in {
+        return 0;
+    }
+    let ratio = ((amount_out - amount_in) * U256::from(PROFIT_RATIO_PRECISION)) / amount_in;
+    ratio.try_into().unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use alloy_primitives::{ Address, Bytes, U256 };
+    use eyre::{ eyre, Result };
+    use searcher_reth_repository::types::dex_type;
+
+    use crate::strategy::path_finding::types::{ Hop, RoutePath };
+
+    use super::{
+        estimate_profit_ratio,
+        probe_amount,
+        uniswap_v2::{ RESERVES_SLOT, TOKEN0_SLOT, TOKEN1_SLOT },
+        PoolSource,
+    };
+
+    const TOKEN_A: Address = Address::with_last_byte(0xaa);
+    const TOKEN_B: Address = Address::with_last_byte(0xbb);
+
+    /// Uniswap V2 pairs in storage.
+    #[derive(Default)]
+    struct Pairs {
+        slots: HashMap<(Address, U256), U256>,
+    }
+
+    impl Pairs {
+        fn add(&mut self, pair: Address, reserve_a: u64, reserve_b: u64) {
+            let word = |token: Address| U256::from_be_slice(token.as_slice());
+            let packed = U256::from(reserve_a) | (U256::from(reserve_b) << 112);
+            self.slots.insert((pair, U256::from(TOKEN0_SLOT)), word(TOKEN_A));
+            self.slots.insert((pair, U256::from(TOKEN1_SLOT)), word(TOKEN_B));
+            self.slots.insert((pair, U256::from(RESERVES_SLOT)), packed);
+        }
+    }
+
+    impl PoolSource for Pairs {
+        fn storage(&mut self, address: Address, slot: U256) -> Result<U256> {
+            Ok(self.slots.get(&(address, slot)).copied().unwrap_or_default())
+        }
+
+        fn call(&mut self, address: Address, _input: Bytes) -> Result<Bytes> {
+            Err(eyre!("no code at {address}"))
+        }
+
+        fn timestamp(&mut self) -> u64 {
+            0
+        }
+    }
+
+    fn hop(pair: Address, src_token: Address, dst_token: Address) -> Hop {
+        Hop { dexType: dex_type::UNISWAP_V2, dex: pair, srcToken: src_token, dstToken: dst_token }
+    }
+
+    #[test]
+    fn probes_at_least_about_the_square_root_of_the_reserve() {
+        let probe = |reserve: u64| probe_amount(U256::from(reserve));
+        assert_eq!(probe(0), U256::from(1));
+        assert_eq!(probe(999_999), U256::from(1 << 10));
+        assert_eq!(probe(1_000_000_000_000), U256::from(1 << 20));
+        assert_eq!(probe(1_000_000_000_000_000_000), U256::from(1_000_000_000_000u64));
+    }
+
+    #[test]
+    fn estimates_routes_through_tiny_pools() {
+        let (cheap, dear) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        let mut pairs = Pairs::default();
+        // B is worth half an A in the first pair and a whole A in the second
+        pairs.add(cheap, 100_000, 200_000);
+        pairs.add(dear, 100_000, 100_000);
+        let route_path = RoutePath {
+            hops: vec![hop(cheap, TOKEN_A, TOKEN_B), hop(dear, TOKEN_B, TOKEN_A)],
+        };
+
+        // a 1-wei probe would be quoted 0 and estimated at a loss
+        let ratio = estimate_profit_ratio(&mut pairs, &route_path).unwrap().unwrap();
+        assert!((900_000..1_000_000).contains(&ratio), "{ratio}");
+    }
+}
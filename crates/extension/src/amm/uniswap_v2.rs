@@ -0,0 +1,311 @@
+use std::{ collections::HashMap, str::FromStr };
+
+use alloy_primitives::{ Address, U256 };
+use eyre::{ eyre, Error, Result };
+
+use super::PoolSource;
+
+/// `UniswapV2Pair.factory`.
+pub const FACTORY_SLOT: u64 = 5;
+/// `UniswapV2Pair.token0`.
+pub const TOKEN0_SLOT: u64 = 6;
+/// `UniswapV2Pair.token1`.
+pub const TOKEN1_SLOT: u64 = 7;
+/// `UniswapV2Pair` packs `reserve0 | reserve1 << 112 | blockTimestampLast << 224` in slot 8.
+pub const RESERVES_SLOT: u64 = 8;
+/// Swap fee of a Uniswap V2 pair, in basis points, and of the forks without a configured fee.
+pub const FEE_BPS: u64 = 30;
+
+const BPS: u64 = 10_000;
+
+/// Swap fee of the pairs of a Uniswap V2 fork, parsed from `<factory>:<bps>`.
+///
+/// Forks hardcode their fee in the pair bytecode (e.g. 25 bps for PancakeSwap), so it's known by
+/// the factory that deployed the pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UniswapV2Fee {
+    pub factory: Address,
+    pub fee_bps: u64,
+}
+
+impl FromStr for UniswapV2Fee {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (factory, fee_bps) = s
+            .split_once(':')
+            .ok_or_else(|| eyre!("expected <factory>:<bps>, got {s}"))?;
+        let fee_bps = fee_bps.parse()?;
+        if fee_bps >= BPS {
+            return Err(eyre!("fee must be under {BPS} bps, got {fee_bps}"));
+        }
+        Ok(Self { factory: factory.parse()?, fee_bps })
+    }
+}
+
+/// Swap fees of the Uniswap V2 forks by factory, [`FEE_BPS`] for any other factory.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UniswapV2Fees {
+    fees: HashMap<Address, u64>,
+}
+
+impl UniswapV2Fees {
+    pub fn fee_bps(&self, factory: Address) -> u64 {
+        self.fees.get(&factory).copied().unwrap_or(FEE_BPS)
+    }
+}
+
+impl FromIterator<UniswapV2Fee> for UniswapV2Fees {
+    fn from_iter<I: IntoIterator<Item = UniswapV2Fee>>(fees: I) -> Self {
+        Self { fees: fees.into_iter().map(|fee| (fee.factory, fee.fee_bps)).collect() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UniswapV2Pool {
+    pub address: Address,
+    pub token0: Address,
+    pub token1: Address,
+    pub reserve0: U256,
+    pub reserve1: U256,
+    pub fee_bps: u64,
+}
+
+impl UniswapV2Pool {
+    /// Reads the tokens and the packed reserves of `address` from storage, and the fee of the
+    /// fork of its factory.
+    pub fn load<S: PoolSource + ?Sized>(source: &mut S, address: Address) -> Result<Self> {
+        let factory = source.storage(address, U256::from(FACTORY_SLOT))?;
+        let token0 = source.storage(address, U256::from(TOKEN0_SLOT))?;
+        let token1 = source.storage(address, U256::from(TOKEN1_SLOT))?;
+        let packed = source.storage(address, U256::from(RESERVES_SLOT))?;
+        let mask = (U256::from(1) << 112) - U256::from(1);
+        let fee_bps = source.uniswap_v2_fee_bps(to_address(factory));
+        if fee_bps >= BPS {
+            return Err(eyre!("fee of {address} must be under {BPS} bps, got {fee_bps}"));
+        }
+        Ok(Self {
+            address,
+            token0: to_address(token0),
+            token1: to_address(token1),
+            reserve0: packed & mask,
+            reserve1: (packed >> 112) & mask,
+            fee_bps,
+        })
+    }
+
+    /// `getAmountOut` of `UniswapV2Library`, with the fee of the pair.
+    pub fn amount_out(&self, src_token: Address, amount_in: U256) -> Option<U256> {
+        let (reserve_in, reserve_out) = self.reserves(src_token)?;
+        if amount_in.is_zero() || reserve_in.is_zero() || reserve_out.is_zero() {
+            return None;
+        }
+        let amount_in_with_fee = amount_in * U256::from(BPS - self.fee_bps);
+        let numerator = amount_in_with_fee * reserve_out;
+        let denominator = reserve_in * U256::from(BPS) + amount_in_with_fee;
+        Some(numerator / denominator)
+    }
+
+    pub fn reserve_of(&self, token: Address) -> Option<U256> {
+        self.reserves(token).map(|(reserve_in, _)| reserve_in)
+    }
+
+    /// (reserve in, reserve out) when swapping from `src_token`.
+    fn reserves(&self, src_token: Address) -> Option<(U256, U256)> {
+        if src_token == self.token0 {
+            Some((self.reserve0, self.reserve1))
+        } else if src_token == self.token1 {
+            Some((self.reserve1, self.reserve0))
+        } else {
+            None
+        }
+    }
+}
+
+fn to_address(word: U256) -> Address {
+    Address::from_word(word.to_be_bytes::<32>().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ collections::HashMap, sync::Arc };
+
+    use alloy_primitives::{ address, Address, B256, U256 };
+    use reth_provider::test_utils::{ ExtendedAccount, MockEthProvider };
+    use reth_revm::{ db::BundleState, state::Bytecode };
+    use searcher_reth_repository::types::dex_type;
+
+    use crate::{
+        amm::{ PoolSource, PoolState },
+        strategy::path_finding::{ types::Hop, PathFinder },
+    };
+
+    use super::{
+        UniswapV2Fee,
+        UniswapV2Fees,
+        UniswapV2Pool,
+        BPS,
+        FACTORY_SLOT,
+        FEE_BPS,
+        RESERVES_SLOT,
+        TOKEN0_SLOT,
+        TOKEN1_SLOT,
+    };
+
+    const PAIR: Address = address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc");
+    const FORK_PAIR: Address = address!("A478c2975Ab1Ea89e8196811F51A7B7Ade33eB11");
+    const FACTORY: Address = address!("5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f");
+    const FORK_FACTORY: Address = address!("cA143Ce32Fe78f1f7019d7d551a6402fC5350c73");
+    const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+    const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+
+    /// The pair's slots as written by its constructor, `initialize` and `_update`.
+    fn pair_storage(factory: Address, reserve0: u128, reserve1: u128) -> Vec<(B256, U256)> {
+        let packed = U256::from(reserve0) |
+            (U256::from(reserve1) << 112) |
+            (U256::from(1_700_000_000u64) << 224);
+        let word = |address: Address| U256::from_be_slice(address.as_slice());
+        vec![
+            (B256::from(U256::from(FACTORY_SLOT)), word(factory)),
+            (B256::from(U256::from(TOKEN0_SLOT)), word(USDC)),
+            (B256::from(U256::from(TOKEN1_SLOT)), word(WETH)),
+            (B256::from(U256::from(RESERVES_SLOT)), packed)
+        ]
+    }
+
+    fn pool(fee_bps: u64, reserve0: u128, reserve1: u128) -> UniswapV2Pool {
+        UniswapV2Pool {
+            address: PAIR,
+            token0: USDC,
+            token1: WETH,
+            reserve0: U256::from(reserve0),
+            reserve1: U256::from(reserve1),
+            fee_bps,
+        }
+    }
+
+    /// The K check of `UniswapV2Pair.swap` (with the fork's fee in bps instead of 3 per mille)
+    /// after `amount_in` was transferred in for `amount_out`.
+    fn pair_accepts(
+        fee_bps: u64,
+        reserve_in: U256,
+        reserve_out: U256,
+        amount_in: U256,
+        amount_out: U256
+    ) -> bool {
+        if amount_out >= reserve_out {
+            return false;
+        }
+        let bps = U256::from(BPS);
+        let balance_in_adjusted = (reserve_in + amount_in) * bps - amount_in * U256::from(fee_bps);
+        let balance_out_adjusted = (reserve_out - amount_out) * bps;
+        balance_in_adjusted * balance_out_adjusted >= reserve_in * reserve_out * bps * bps
+    }
+
+    #[test]
+    fn amount_out_is_the_most_the_pair_accepts() {
+        let amounts = [
+            1u128,
+            999,
+            1_000_000,
+            123_456_789_012,
+            1_000_000_000_000_000,
+            5_000_000_000_000_000_000,
+            28_512_345_678_901,
+            11_234_567_890_123_456_789_012,
+        ];
+        for fee_bps in [30, 25, 17] {
+            // ~28.5M USDC against ~11.2k WETH, and a shallow pair
+            for (reserve0, reserve1) in [
+                (28_512_345_678_901, 11_234_567_890_123_456_789_012),
+                (1_000, 3),
+            ] {
+                let pool = pool(fee_bps, reserve0, reserve1);
+                for (src_token, reserve_in, reserve_out) in [
+                    (USDC, pool.reserve0, pool.reserve1),
+                    (WETH, pool.reserve1, pool.reserve0),
+                ] {
+                    for amount_in in amounts.map(U256::from) {
+                        let amount_out = pool.amount_out(src_token, amount_in).unwrap();
+                        let accepts = |amount_out| {
+                            pair_accepts(fee_bps, reserve_in, reserve_out, amount_in, amount_out)
+                        };
+                        let case = format!("{fee_bps} bps, {amount_in} {src_token}");
+                        assert!(accepts(amount_out), "{case}");
+                        assert!(!accepts(amount_out + U256::from(1)), "{case}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lower_fees_quote_more() {
+        let amount_in = U256::from(1_000_000_000u64);
+        let quotes = [30, 25, 17, 0].map(|fee_bps| {
+            pool(fee_bps, 28_512_345_678_901, 11_234_567_890_123_456_789_012)
+                .amount_out(USDC, amount_in)
+                .unwrap()
+        });
+        assert!(quotes.windows(2).all(|pair| pair[0] < pair[1]), "{quotes:?}");
+    }
+
+    #[test]
+    fn loads_the_fee_of_the_pair_factory() {
+        let provider: MockEthProvider = MockEthProvider::default();
+        provider.add_account(
+            PAIR,
+            ExtendedAccount::new(0, U256::ZERO).extend_storage(pair_storage(FACTORY, 1_000, 3))
+        );
+        provider.add_account(
+            FORK_PAIR,
+            ExtendedAccount::new(0, U256::ZERO).extend_storage(pair_storage(FORK_FACTORY, 7, 5))
+        );
+        let bundle = BundleState::default();
+        let fees = [UniswapV2Fee { factory: FORK_FACTORY, fee_bps: 25 }]
+            .into_iter()
+            .collect::<UniswapV2Fees>();
+        let path_finder = PathFinder::new(
+            provider,
+            &bundle,
+            HashMap::new(),
+            Bytecode::default()
+        ).with_uniswap_v2_fees(Arc::new(fees));
+        let mut simulator = path_finder.simulator();
+
+        for (address, fee_bps, reserve0, reserve1) in [
+            (PAIR, FEE_BPS, 1_000, 3),
+            (FORK_PAIR, 25, 7, 5),
+        ] {
+            let hop = Hop {
+                dexType: dex_type::UNISWAP_V2,
+                dex: address,
+                srcToken: USDC,
+                dstToken: WETH,
+            };
+            let Some(PoolState::UniswapV2(pool)) = simulator.pool(&hop).unwrap() else {
+                panic!("pair not loaded as a Uniswap V2 pool");
+            };
+            assert_eq!(pool, UniswapV2Pool {
+                address,
+                token0: USDC,
+                token1: WETH,
+                reserve0: U256::from(reserve0),
+                reserve1: U256::from(reserve1),
+                fee_bps,
+            });
+        }
+    }
+
+    #[test]
+    fn parses_fork_fees() {
+        let fee = format!("{FORK_FACTORY}:25").parse::<UniswapV2Fee>().unwrap();
+        assert_eq!(fee, UniswapV2Fee { factory: FORK_FACTORY, fee_bps: 25 });
+        assert!(format!("{FORK_FACTORY}:10000").parse::<UniswapV2Fee>().is_err());
+        assert!(format!("{FORK_FACTORY}").parse::<UniswapV2Fee>().is_err());
+
+        let fees = [fee].into_iter().collect::<UniswapV2Fees>();
+        assert_eq!(fees.fee_bps(FORK_FACTORY), 25);
+        assert_eq!(fees.fee_bps(FACTORY), FEE_BPS);
+    }
+}
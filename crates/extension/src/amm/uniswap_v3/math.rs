@@ -0,0 +1,527 @@
+//! Port of the Uniswap V3 `TickMath`, `SqrtPriceMath` and `SwapMath` libraries, restricted to
+//! what a swap needs. Every function rounds exactly like its Solidity counterpart.
+
+use alloy_primitives::{ ruint::UintTryFrom, I256, U256, U512 };
+
+pub const MIN_TICK: i32 = -887272;
+pub const MAX_TICK: i32 = 887272;
+
+/// `getSqrtRatioAtTick(MIN_TICK)`.
+pub const MIN_SQRT_RATIO: U256 = U256::from_limbs([4295128739, 0, 0, 0]);
+/// `getSqrtRatioAtTick(MAX_TICK)`.
+pub const MAX_SQRT_RATIO: U256 = U256::from_limbs([
+    0x5d951d5263988d26,
+    0xefd1fc6a50648849,
+    0xfffd8963,
+    0,
+]);
+
+/// Fees are expressed in hundredths of a bip.
+pub const FEE_DENOMINATOR: u64 = 1_000_000;
+
+const RESOLUTION: usize = 96;
+
+/// `FullMath.mulDiv`, `None` on overflow or division by zero.
+pub fn mul_div(a: U256, b: U256, denominator: U256) -> Option<U256> {
+    if denominator.is_zero() {
+        return None;
+    }
+    let result = (U512::from(a) * U512::from(b)) / U512::from(denominator);
+    U256::uint_try_from(result).ok()
+}
+
+/// `FullMath.mulDivRoundingUp`.
+pub fn mul_div_rounding_up(a: U256, b: U256, denominator: U256) -> Option<U256> {
+    let result = mul_div(a, b, denominator)?;
+    if (U512::from(a) * U512::from(b)) % U512::from(denominator) == U512::ZERO {
+        Some(result)
+    } else {
+        result.checked_add(U256::from(1))
+    }
+}
+
+/// `UnsafeMath.divRoundingUp`.
+fn div_rounding_up(a: U256, b: U256) -> U256 {
+    a / b + U256::from(!(a % b).is_zero())
+}
+
+/// `TickMath.getSqrtRatioAtTick`.
+pub fn get_sqrt_ratio_at_tick(tick: i32) -> Option<U256> {
+    let abs_tick = tick.unsigned_abs();
+    if abs_tick > MAX_TICK as u32 {
+        return None;
+    }
+
+    const FACTORS: [(u32, u128); 19] = [
+        (0x2, 0xfff97272373d413259a46990580e213a),
+        (0x4, 0xfff2e50f5f656932ef12357cf3c7fdcc),
+        (0x8, 0xffe5caca7e10e4e61c3624eaa0941cd0),
+        (0x10, 0xffcb9843d60f6159c9db58835c926644),
+        (0x20, 0xff973b41fa98c081472e6896dfb254c0),
+        (0x40, 0xff2ea16466c96a3843ec78b326b52861),
+        (0x80, 0xfe5dee046a99a2a811c461f1969c3053),
+        (0x100, 0xfcbe86c7900a88aedcffc83b479aa3a4),
+        (0x200, 0xf987a7253ac413176f2b074cf7815e54),
+        (0x400, 0xf3392b0822b70005940c7a398e4b70f3),
+        (0x800, 0xe7159475a2c29b7443b29c7fa6e889d9),
+        (0x1000, 0xd097f3bdfd2022b8845ad8f792aa5825),
+        (0x2000, 0xa9f746462d870fdf8a65dc1f90e061e5),
+        (0x4000, 0x70d869a156d2a1b890bb3df62baf32f7),
+        (0x8000, 0x31be135f97d08fd981231505542fcfa6),
+        (0x10000, 0x9aa508b5b7a84e1c677de54f3e99bc9),
+        (0x20000, 0x5d6af8dedb81196699c329225ee604),
+        (0x40000, 0x2216e584f5fa1ea926041bedfe98),
+        (0x80000, 0x48a170391f7dc42444e8fa2),
+    ];
+
+    let mut ratio = if abs_tick & 0x1 != 0 {
+        U256::from(0xfffcb933bd6fad37aa2d162d1a594001_u128)
+    } else {
+        U256::from(1) << 128
+    };
+    for (bit, factor) in FACTORS {
+        if abs_tick & bit != 0 {
+            ratio = (ratio * U256::from(factor)) >> 128;
+        }
+    }
+    if tick > 0 {
+        ratio = U256::MAX / ratio;
+    }
+
+    // Q128.128 to Q64.96, rounding up
+    let rounding = U256::from(!(ratio & U256::from(u32::MAX)).is_zero());
+    Some((ratio >> 32) + rounding)
+}
+
+/// `SqrtPriceMath.getNextSqrtPriceFromAmount0RoundingUp`, `add` if the amount is added to the
+/// pool, removed otherwise.
+fn next_sqrt_price_from_amount0(
+    sqrt_price: U256,
+    liquidity: u128,
+    amount: U256,
+    add: bool
+) -> Option<U256> {
+    if amount.is_zero() {
+        return Some(sqrt_price);
+    }
+    let numerator1 = U256::from(liquidity) << RESOLUTION;
+    let product = amount.checked_mul(sqrt_price);
+
+    if !add {
+        let denominator = numerator1.checked_sub(product?).filter(|d| !d.is_zero())?;
+        return mul_div_rounding_up(numerator1, sqrt_price, denominator).filter(fits_u160);
+    }
+    if let Some(denominator) = product.and_then(|product| numerator1.checked_add(product)) {
+        return mul_div_rounding_up(numerator1, sqrt_price, denominator);
+    }
+    Some(div_rounding_up(numerator1, (numerator1 / sqrt_price).checked_add(amount)?))
+}
+
+/// `SqrtPriceMath.getNextSqrtPriceFromAmount1RoundingDown`, `add` if the amount is added to the
+/// pool, removed otherwise.
+fn next_sqrt_price_from_amount1(
+    sqrt_price: U256,
+    liquidity: u128,
+    amount: U256,
+    add: bool
+) -> Option<U256> {
+    let liquidity = U256::from(liquidity);
+    let q96 = U256::from(1) << RESOLUTION;
+    if add {
+        let quotient = if fits_u160(&amount) {
+            (amount << RESOLUTION) / liquidity
+        } else {
+            mul_div(amount, q96, liquidity)?
+        };
+        sqrt_price.checked_add(quotient).filter(fits_u160)
+    } else {
+        let quotient = if fits_u160(&amount) {
+            div_rounding_up(amount << RESOLUTION, liquidity)
+        } else {
+            mul_div_rounding_up(amount, q96, liquidity)?
+        };
+        sqrt_price.checked_sub(quotient).filter(|next| !next.is_zero())
+    }
+}
+
+fn fits_u160(value: &U256) -> bool {
+    value.bit_len() <= 160
+}
+
+/// `SqrtPriceMath.getNextSqrtPriceFromInput`.
+fn next_sqrt_price_from_input(
+    sqrt_price: U256,
+    liquidity: u128,
+    amount_in: U256,
+    zero_for_one: bool
+) -> Option<U256> {
+    if sqrt_price.is_zero() || liquidity == 0 {
+        return None;
+    }
+    if zero_for_one {
+        next_sqrt_price_from_amount0(sqrt_price, liquidity, amount_in, true)
+    } else {
+        next_sqrt_price_from_amount1(sqrt_price, liquidity, amount_in, true)
+    }
+}
+
+/// `SqrtPriceMath.getNextSqrtPriceFromOutput`.
+fn next_sqrt_price_from_output(
+    sqrt_price: U256,
+    liquidity: u128,
+    amount_out: U256,
+    zero_for_one: bool
+) -> Option<U256> {
+    if sqrt_price.is_zero() || liquidity == 0 {
+        return None;
+    }
+    if zero_for_one {
+        next_sqrt_price_from_amount1(sqrt_price, liquidity, amount_out, false)
+    } else {
+        next_sqrt_price_from_amount0(sqrt_price, liquidity, amount_out, false)
+    }
+}
+
+/// `SqrtPriceMath.getAmount0Delta`.
+pub fn get_amount0_delta(
+    sqrt_ratio_a: U256,
+    sqrt_ratio_b: U256,
+    liquidity: u128,
+    round_up: bool
+) -> Option<U256> {
+    let (lower, upper) = if sqrt_ratio_a > sqrt_ratio_b {
+        (sqrt_ratio_b, sqrt_ratio_a)
+    } else {
+        (sqrt_ratio_a, sqrt_ratio_b)
+    };
+    if lower.is_zero() {
+        return None;
+    }
+    let numerator1 = U256::from(liquidity) << RESOLUTION;
+    let numerator2 = upper - lower;
+
+    if round_up {
+        Some(div_rounding_up(mul_div_rounding_up(numerator1, numerator2, upper)?, lower))
+    } else {
+        Some(mul_div(numerator1, numerator2, upper)? / lower)
+    }
+}
+
+/// `SqrtPriceMath.getAmount1Delta`.
+pub fn get_amount1_delta(
+    sqrt_ratio_a: U256,
+    sqrt_ratio_b: U256,
+    liquidity: u128,
+    round_up: bool
+) -> Option<U256> {
+    let (lower, upper) = if sqrt_ratio_a > sqrt_ratio_b {
+        (sqrt_ratio_b, sqrt_ratio_a)
+    } else {
+        (sqrt_ratio_a, sqrt_ratio_b)
+    };
+    let q96 = U256::from(1) << RESOLUTION;
+
+    if round_up {
+        mul_div_rounding_up(U256::from(liquidity), upper - lower, q96)
+    } else {
+        mul_div(U256::from(liquidity), upper - lower, q96)
+    }
+}
+
+/// Result of `SwapMath.computeSwapStep`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapStep {
+    pub sqrt_price_next: U256,
+    pub amount_in: U256,
+    pub amount_out: U256,
+    pub fee_amount: U256,
+}
+
+/// `SwapMath.computeSwapStep`: an exact input if `amount_remaining` is positive, an exact
+/// output of its absolute value otherwise. `None` where the Solidity version reverts, or for a fee
+/// over [`FEE_DENOMINATOR`].
+pub fn compute_swap_step(
+    sqrt_price_current: U256,
+    sqrt_price_target: U256,
+    liquidity: u128,
+    amount_remaining: I256,
+    fee_pips: u32
+) -> Option<SwapStep> {
+    let zero_for_one = sqrt_price_current >= sqrt_price_target;
+    let exact_in = !amount_remaining.is_negative();
+    let amount_remaining_abs = amount_remaining.unsigned_abs();
+    let fee_complement = U256::from(FEE_DENOMINATOR.checked_sub(fee_pips as u64)?);
+
+    let (sqrt_price_next, amount_to_target) = if exact_in {
+        let amount_remaining_less_fee = mul_div(
+            amount_remaining_abs,
+            fee_complement,
+            U256::from(FEE_DENOMINATOR)
+        )?;
+        let amount_in = if zero_for_one {
+            get_amount0_delta(sqrt_price_target, sqrt_price_current, liquidity, true)?
+        } else {
+            get_amount1_delta(sqrt_price_current, sqrt_price_target, liquidity, true)?
+        };
+        let sqrt_price_next = if amount_remaining_less_fee >= amount_in {
+            sqrt_price_target
+        } else {
+            next_sqrt_price_from_input(
+                sqrt_price_current,
+                liquidity,
+                amount_remaining_less_fee,
+                zero_for_one
+            )?
+        };
+        (sqrt_price_next, amount_in)
+    } else {
+        let amount_out = if zero_for_one {
+            get_amount1_delta(sqrt_price_target, sqrt_price_current, liquidity, false)?
+        } else {
+            get_amount0_delta(sqrt_price_current, sqrt_price_target, liquidity, false)?
+        };
+        let sqrt_price_next = if amount_remaining_abs >= amount_out {
+            sqrt_price_target
+        } else {
+            next_sqrt_price_from_output(
+                sqrt_price_current,
+                liquidity,
+                amount_remaining_abs,
+                zero_for_one
+            )?
+        };
+        (sqrt_price_next, amount_out)
+    };
+    let max = sqrt_price_target == sqrt_price_next;
+
+    let (amount_in, mut amount_out) = if zero_for_one {
+        (
+            if max && exact_in {
+                amount_to_target
+            } else {
+                get_amount0_delta(sqrt_price_next, sqrt_price_current, liquidity, true)?
+            },
+            if max && !exact_in {
+                amount_to_target
+            } else {
+                get_amount1_delta(sqrt_price_next, sqrt_price_current, liquidity, false)?
+            },
+        )
+    } else {
+        (
+            if max && exact_in {
+                amount_to_target
+            } else {
+                get_amount1_delta(sqrt_price_current, sqrt_price_next, liquidity, true)?
+            },
+            if max && !exact_in {
+                amount_to_target
+            } else {
+                get_amount0_delta(sqrt_price_current, sqrt_price_next, liquidity, false)?
+            },
+        )
+    };
+    // the output never exceeds the amount asked for
+    if !exact_in {
+        amount_out = amount_out.min(amount_remaining_abs);
+    }
+
+    let fee_amount = if exact_in && !max {
+        // the remainder of the input is taken as fee
+        amount_remaining_abs.checked_sub(amount_in)?
+    } else {
+        mul_div_rounding_up(amount_in, U256::from(fee_pips), fee_complement)?
+    };
+
+    Some(SwapStep { sqrt_price_next, amount_in, amount_out, fee_amount })
+}
+
+/// `LiquidityMath.addDelta`.
+pub fn add_delta(liquidity: u128, delta: i128) -> Option<u128> {
+    if delta < 0 {
+        liquidity.checked_sub(delta.unsigned_abs())
+    } else {
+        liquidity.checked_add(delta as u128)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{ I256, U256 };
+
+    use super::{
+        compute_swap_step,
+        get_sqrt_ratio_at_tick,
+        SwapStep,
+        MAX_SQRT_RATIO,
+        MAX_TICK,
+        MIN_SQRT_RATIO,
+        MIN_TICK,
+    };
+
+    fn u256(value: &str) -> U256 {
+        value.parse().unwrap()
+    }
+
+    /// `encodePriceSqrt(101, 100)` of the v3-core tests, `floor(sqrt(1.01) * 2^96)`.
+    const PRICE_101_100: &str = "79623317895830914510639640423";
+    /// `encodePriceSqrt(1000, 100)`.
+    const PRICE_1000_100: &str = "250541448375047931186413801569";
+    /// `encodePriceSqrt(10000, 100)`.
+    const PRICE_10000_100: &str = "792281625142643375935439503360";
+    const E18: i128 = 1_000_000_000_000_000_000;
+
+    fn step(price: U256, target: U256, liquidity: u128, amount: i128, fee_pips: u32) -> SwapStep {
+        let amount = I256::try_from(amount).unwrap();
+        compute_swap_step(price, target, liquidity, amount, fee_pips).unwrap()
+    }
+
+    #[test]
+    fn sqrt_ratio_at_tick_matches_tick_math() {
+        let cases = [
+            (MIN_TICK, "4295128739"),
+            (MIN_TICK + 1, "4295343490"),
+            (0, "79228162514264337593543950336"),
+            (MAX_TICK - 1, "1461373636630004318706518188784493106690254656249"),
+            (MAX_TICK, "1461446703485210103287273052203988822378723970342"),
+        ];
+        for (tick, sqrt_ratio) in cases {
+            assert_eq!(get_sqrt_ratio_at_tick(tick), Some(u256(sqrt_ratio)), "tick {tick}");
+        }
+        assert_eq!(get_sqrt_ratio_at_tick(MIN_TICK), Some(MIN_SQRT_RATIO));
+        assert_eq!(get_sqrt_ratio_at_tick(MAX_TICK), Some(MAX_SQRT_RATIO));
+        assert_eq!(get_sqrt_ratio_at_tick(MIN_TICK - 1), None);
+        assert_eq!(get_sqrt_ratio_at_tick(MAX_TICK + 1), None);
+    }
+
+    #[test]
+    fn exact_in_capped_at_the_target_one_for_zero() {
+        let (price, target) = (U256::from(1) << 96, u256(PRICE_101_100));
+        let step = step(price, target, 2 * (E18 as u128), E18, 600);
+        assert_eq!(step, SwapStep {
+            sqrt_price_next: target,
+            amount_in: u256("9975124224178055"),
+            amount_out: u256("9925619580021728"),
+            fee_amount: u256("5988667735148"),
+        });
+        assert!(step.amount_in + step.fee_amount < U256::from(E18));
+    }
+
+    #[test]
+    fn exact_out_capped_at_the_target_one_for_zero() {
+        let (price, target) = (U256::from(1) << 96, u256(PRICE_101_100));
+        let step = step(price, target, 2 * (E18 as u128), -E18, 600);
+        assert_eq!(step, SwapStep {
+            sqrt_price_next: target,
+            amount_in: u256("9975124224178055"),
+            amount_out: u256("9925619580021728"),
+            fee_amount: u256("5988667735148"),
+        });
+        assert!(step.amount_out < U256::from(E18));
+    }
+
+    #[test]
+    fn exact_in_fully_spent_one_for_zero() {
+        let (price, target) = (U256::from(1) << 96, u256(PRICE_1000_100));
+        let step = step(price, target, 2 * (E18 as u128), E18, 600);
+        assert_eq!(step.amount_in, u256("999400000000000000"));
+        assert_eq!(step.fee_amount, u256("600000000000000"));
+        assert_eq!(step.amount_out, u256("666399946655997866"));
+        assert_eq!(step.amount_in + step.fee_amount, U256::from(E18));
+        assert!(step.sqrt_price_next < target);
+    }
+
+    #[test]
+    fn exact_out_fully_received_one_for_zero() {
+        let (price, target) = (U256::from(1) << 96, u256(PRICE_10000_100));
+        let step = step(price, target, 2 * (E18 as u128), -E18, 600);
+        assert_eq!(step.amount_in, u256("2000000000000000000"));
+        assert_eq!(step.fee_amount, u256("1200720432259356"));
+        assert_eq!(step.amount_out, U256::from(E18));
+        assert!(step.sqrt_price_next < target);
+    }
+
+    #[test]
+    fn exact_out_capped_at_the_amount_asked_zero_for_one() {
+        let step = step(
+            u256("417332158212080721273783715441582"),
+            u256("1452870262520218020823638996"),
+            159344665391607089467575320103,
+            -1,
+            1
+        );
+        assert_eq!(step, SwapStep {
+            sqrt_price_next: u256("417332158212080721273783715441581"),
+            amount_in: U256::from(1),
+            amount_out: U256::from(1),
+            fee_amount: U256::from(1),
+        });
+    }
+
+    #[test]
+    fn exact_in_of_a_target_price_of_one_zero_for_one() {
+        let amount = 3915081100057732413702495386755767;
+        let step = step(U256::from(2), U256::from(1), 1, amount, 1);
+        assert_eq!(step, SwapStep {
+            sqrt_price_next: U256::from(1),
+            amount_in: u256("39614081257132168796771975168"),
+            amount_out: U256::ZERO,
+            fee_amount: u256("39614120871253040049813"),
+        });
+        assert!(step.amount_in + step.fee_amount <= U256::from(amount));
+    }
+
+    #[test]
+    fn exact_in_entirely_taken_as_fee_one_for_zero() {
+        let step = step(
+            U256::from(2413),
+            u256("79887613182836312"),
+            1985041575832132834610021537970,
+            10,
+            1872
+        );
+        assert_eq!(step, SwapStep {
+            sqrt_price_next: U256::from(2413),
+            amount_in: U256::ZERO,
+            amount_out: U256::ZERO,
+            fee_amount: U256::from(10),
+        });
+    }
+
+    #[test]
+    fn exact_out_with_insufficient_intermediate_liquidity() {
+        let price = u256("20282409603651670423947251286016");
+
+        let target = price * U256::from(11) / U256::from(10);
+        let step_up = step(price, target, 1024, -4, 3000);
+        assert_eq!(step_up, SwapStep {
+            sqrt_price_next: target,
+            amount_in: U256::from(26215),
+            amount_out: U256::ZERO,
+            fee_amount: U256::from(79),
+        });
+
+        let target = price * U256::from(9) / U256::from(10);
+        let step_down = step(price, target, 1024, -263000, 3000);
+        assert_eq!(step_down, SwapStep {
+            sqrt_price_next: target,
+            amount_in: U256::from(1),
+            amount_out: U256::from(26214),
+            fee_amount: U256::from(1),
+        });
+    }
+
+    #[test]
+    fn takes_the_whole_input_at_the_max_fee() {
+        let (price, target) = (U256::from(1) << 96, u256(PRICE_101_100));
+        let amount = I256::try_from(E18).unwrap();
+        let step = compute_swap_step(price, target, 1 << 60, amount, 1_000_000).unwrap();
+        assert_eq!(step, SwapStep {
+            sqrt_price_next: price,
+            amount_in: U256::ZERO,
+            amount_out: U256::ZERO,
+            fee_amount: U256::from(E18),
+        });
+        assert_eq!(compute_swap_step(price, target, 1 << 60, amount, 1_000_001), None);
+    }
+}
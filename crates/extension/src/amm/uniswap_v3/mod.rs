@@ -0,0 +1,273 @@
+pub mod math;
+
+use alloy_primitives::{ keccak256, Address, Bytes, I256, U256 };
+use eyre::{ eyre, Result };
+
+use crate::strategy::path_finding::types::Hop;
+
+use super::PoolSource;
+use math::{
+    add_delta,
+    compute_swap_step,
+    get_sqrt_ratio_at_tick,
+    FEE_DENOMINATOR,
+    MAX_SQRT_RATIO,
+    MAX_TICK,
+    MIN_SQRT_RATIO,
+    MIN_TICK,
+};
+
+/// `UniswapV3Pool.slot0`: sqrtPriceX96 (160 bits) | tick (24 bits) | ...
+pub const SLOT0_SLOT: u64 = 0;
+/// `UniswapV3Pool.liquidity`.
+pub const LIQUIDITY_SLOT: u64 = 4;
+/// `UniswapV3Pool.ticks`, `mapping(int24 => Tick.Info)`.
+pub const TICKS_SLOT: u64 = 5;
+/// `UniswapV3Pool.tickBitmap`, `mapping(int16 => uint256)`.
+pub const TICK_BITMAP_SLOT: u64 = 6;
+
+/// `fee()`, an immutable of the pool.
+const FEE_SELECTOR: [u8; 4] = [0xdd, 0xca, 0x3f, 0x43];
+/// `tickSpacing()`, an immutable of the pool.
+const TICK_SPACING_SELECTOR: [u8; 4] = [0xd0, 0xc9, 0x3a, 0x7c];
+
+/// Bitmap words a single swap may walk before giving up (and leaving the route to the EVM).
+pub const MAX_SWAP_STEPS: usize = 512;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UniswapV3Pool {
+    pub address: Address,
+    pub token0: Address,
+    pub token1: Address,
+    pub fee: u32,
+    pub tick_spacing: i32,
+    pub sqrt_price_x96: U256,
+    pub tick: i32,
+    pub liquidity: u128,
+}
+
+impl UniswapV3Pool {
+    /// Reads `slot0` and `liquidity` from storage. Tokens are taken from the hop (a pool always
+    /// sorts them), fee and tick spacing are immutables and need a call.
+    pub fn load<S: PoolSource + ?Sized>(source: &mut S, hop: &Hop) -> Result<Self> {
+        let address = hop.dex;
+        let (token0, token1) = if hop.srcToken < hop.dstToken {
+            (hop.srcToken, hop.dstToken)
+        } else {
+            (hop.dstToken, hop.srcToken)
+        };
+
+        let slot0 = source.storage(address, U256::from(SLOT0_SLOT))?;
+        let liquidity = source.storage(address, U256::from(LIQUIDITY_SLOT))?;
+        let fee = decode_word(&source.call(address, Bytes::from_static(&FEE_SELECTOR))?)?;
+        let tick_spacing = decode_word(
+            &source.call(address, Bytes::from_static(&TICK_SPACING_SELECTOR))?
+        )?;
+        // a fee of the whole input would divide by zero in `computeSwapStep`
+        let fee = u32::try_from(fee)
+            .ok()
+            .filter(|fee| u64::from(*fee) < FEE_DENOMINATOR)
+            .ok_or_else(|| eyre!("invalid fee {fee} of pool {address}"))?;
+
+        Ok(Self {
+            address,
+            token0,
+            token1,
+            fee,
+            tick_spacing: sign_extend(tick_spacing, 24) as i32,
+            sqrt_price_x96: slot0 & ((U256::from(1) << 160) - U256::from(1)),
+            tick: sign_extend(slot0 >> 160, 24) as i32,
+            liquidity: (liquidity & U256::from(u128::MAX)).to::<u128>(),
+        })
+    }
+
+    /// Virtual reserve of `token` in the current tick range, used to size probes.
+    pub fn reserve_of<S: PoolSource + ?Sized>(
+        &self,
+        _source: &mut S,
+        token: Address
+    ) -> Result<Option<U256>> {
+        if self.sqrt_price_x96.is_zero() {
+            return Ok(None);
+        }
+        let liquidity = U256::from(self.liquidity);
+        Ok(if token == self.token0 {
+            math::mul_div(liquidity, U256::from(1) << 96, self.sqrt_price_x96)
+        } else if token == self.token1 {
+            math::mul_div(liquidity, self.sqrt_price_x96, U256::from(1) << 96)
+        } else {
+            None
+        })
+    }
+
+    /// Exact-input swap, walking initialized ticks from the tick bitmap like `UniswapV3Pool.swap`.
+    /// `Ok(None)` if the swap is impossible or walks more than [`MAX_SWAP_STEPS`] words.
+    pub fn amount_out<S: PoolSource + ?Sized>(
+        &self,
+        source: &mut S,
+        src_token: Address,
+        amount_in: U256
+    ) -> Result<Option<U256>> {
+        let zero_for_one = if src_token == self.token0 {
+            true
+        } else if src_token == self.token1 {
+            false
+        } else {
+            return Ok(None);
+        };
+        if amount_in.is_zero() || self.sqrt_price_x96.is_zero() || self.tick_spacing <= 0 {
+            return Ok(None);
+        }
+        let sqrt_price_limit = if zero_for_one {
+            MIN_SQRT_RATIO + U256::from(1)
+        } else {
+            MAX_SQRT_RATIO - U256::from(1)
+        };
+
+        let mut amount_remaining = amount_in;
+        let mut amount_out = U256::ZERO;
+        let mut sqrt_price = self.sqrt_price_x96;
+        let mut tick = self.tick;
+        let mut liquidity = self.liquidity;
+
+        for _ in 0..MAX_SWAP_STEPS {
+            if amount_remaining.is_zero() || sqrt_price == sqrt_price_limit {
+                return Ok(Some(amount_out));
+            }
+
+            let (tick_next, initialized) = self.next_initialized_tick_within_one_word(
+                source,
+                tick,
+                zero_for_one
+            )?;
+            let tick_next = tick_next.clamp(MIN_TICK, MAX_TICK);
+            let Some(sqrt_price_next) = get_sqrt_ratio_at_tick(tick_next) else {
+                return Ok(None);
+            };
+            let sqrt_price_target = if zero_for_one {
+                sqrt_price_next.max(sqrt_price_limit)
+            } else {
+                sqrt_price_next.min(sqrt_price_limit)
+            };
+
+            let Ok(amount_remaining_signed) = I256::try_from(amount_remaining) else {
+                return Ok(None);
+            };
+            let Some(step) = compute_swap_step(
+                sqrt_price,
+                sqrt_price_target,
+                liquidity,
+                amount_remaining_signed,
+                self.fee
+            ) else {
+                return Ok(None);
+            };
+            sqrt_price = step.sqrt_price_next;
+            let spent = step.amount_in + step.fee_amount;
+            let Some(remaining) = amount_remaining.checked_sub(spent) else {
+                return Ok(None);
+            };
+            amount_remaining = remaining;
+            amount_out += step.amount_out;
+
+            if sqrt_price != sqrt_price_next {
+                // the input ran out inside the range, the tick is irrelevant from here on
+                continue;
+            }
+            if initialized {
+                let liquidity_net = self.liquidity_net(source, tick_next)?;
+                let liquidity_net = if zero_for_one { -liquidity_net } else { liquidity_net };
+                let Some(next) = add_delta(liquidity, liquidity_net) else {
+                    return Ok(None);
+                };
+                liquidity = next;
+            }
+            tick = if zero_for_one { tick_next - 1 } else { tick_next };
+        }
+
+        Ok(None)
+    }
+
+    /// `TickBitmap.nextInitializedTickWithinOneWord`.
+    fn next_initialized_tick_within_one_word<S: PoolSource + ?Sized>(
+        &self,
+        source: &mut S,
+        tick: i32,
+        lte: bool
+    ) -> Result<(i32, bool)> {
+        let spacing = self.tick_spacing;
+        let mut compressed = tick / spacing;
+        if tick < 0 && tick % spacing != 0 {
+            compressed -= 1;
+        }
+
+        if lte {
+            let (word_pos, bit_pos) = position(compressed);
+            let mask = (U256::from(1) << bit_pos) - U256::from(1) + (U256::from(1) << bit_pos);
+            let masked = self.tick_bitmap(source, word_pos)? & mask;
+            let initialized = !masked.is_zero();
+            let next = if initialized {
+                let msb = 255 - masked.leading_zeros() as i32;
+                (compressed - (bit_pos as i32 - msb)) * spacing
+            } else {
+                (compressed - bit_pos as i32) * spacing
+            };
+            Ok((next, initialized))
+        } else {
+            let (word_pos, bit_pos) = position(compressed + 1);
+            let mask = !((U256::from(1) << bit_pos) - U256::from(1));
+            let masked = self.tick_bitmap(source, word_pos)? & mask;
+            let initialized = !masked.is_zero();
+            let next = if initialized {
+                let lsb = masked.trailing_zeros() as i32;
+                (compressed + 1 + (lsb - bit_pos as i32)) * spacing
+            } else {
+                (compressed + 1 + (255 - bit_pos as i32)) * spacing
+            };
+            Ok((next, initialized))
+        }
+    }
+
+    fn tick_bitmap<S: PoolSource + ?Sized>(&self, source: &mut S, word_pos: i16) -> Result<U256> {
+        source.storage(self.address, mapping_slot(word_pos as i64, TICK_BITMAP_SLOT))
+    }
+
+    /// `Tick.Info.liquidityNet`, the upper half of the first slot of the struct.
+    fn liquidity_net<S: PoolSource + ?Sized>(&self, source: &mut S, tick: i32) -> Result<i128> {
+        let word = source.storage(self.address, mapping_slot(tick as i64, TICKS_SLOT))?;
+        Ok((word >> 128usize).to::<u128>() as i128)
+    }
+}
+
+/// `TickBitmap.position`.
+fn position(compressed: i32) -> (i16, u8) {
+    ((compressed >> 8) as i16, (compressed & 0xff) as u8)
+}
+
+/// Storage slot of `mapping[key]` for a signed integer key.
+fn mapping_slot(key: i64, slot: u64) -> U256 {
+    // abi-encoded as a sign-extended 256-bit word
+    let key = if key < 0 {
+        U256::from(key.unsigned_abs()).wrapping_neg()
+    } else {
+        U256::from(key)
+    };
+    let mut preimage = [0u8; 64];
+    preimage[..32].copy_from_slice(&key.to_be_bytes::<32>());
+    preimage[32..].copy_from_slice(&U256::from(slot).to_be_bytes::<32>());
+    U256::from_be_bytes(keccak256(preimage).0)
+}
+
+/// Sign-extends the low `bits` bits of `word`.
+fn sign_extend(word: U256, bits: usize) -> i64 {
+    let value = (word & ((U256::from(1) << bits) - U256::from(1))).to::<u64>() as i64;
+    let shift = 64 - bits;
+    (value << shift) >> shift
+}
+
+fn decode_word(output: &Bytes) -> Result<U256> {
+    if output.len() < 32 {
+        return Err(eyre!("unexpected return data length {}", output.len()));
+    }
+    Ok(U256::from_be_slice(&output[..32]))
+}
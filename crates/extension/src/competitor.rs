@@ -0,0 +1,428 @@
+use std::collections::{ BTreeMap, HashMap, HashSet };
+
+use alloy_consensus::{ BlockHeader, Transaction, TxReceipt };
+use alloy_primitives::{ Address, Log, B256, I256, U256 };
+use alloy_sol_types::{ sol, SolEvent };
+use reth_execution_types::Chain;
+use reth_primitives_traits::{ NodePrimitives, SignedTransaction };
+use searcher_reth_repository::types::{ CompetitorArbitrage, CompetitorMatch };
+
+use crate::{
+    strategy::path_finding::{ index::RouteIndex, types::RoutePath },
+    tracker::Transfer,
+    SearchConfig,
+};
+
+sol! {
+    interface IUniswapV2Pair {
+        event Swap(
+            address indexed sender,
+            uint256 amount0In,
+            uint256 amount1In,
+            uint256 amount0Out,
+            uint256 amount1Out,
+            address indexed to
+        );
+    }
+
+    interface IUniswapV3Pool {
+        event Swap(
+            address indexed sender,
+            address indexed recipient,
+            int256 amount0,
+            int256 amount1,
+            uint160 sqrtPriceX96,
+            uint128 liquidity,
+            int24 tick
+        );
+    }
+
+    interface IWETH9 {
+        event Deposit(address indexed dst, uint256 wad);
+        event Withdrawal(address indexed src, uint256 wad);
+    }
+}
+
+/// Longest cycle followed through the swaps of a transaction.
+const MAX_CYCLE_LEGS: usize = 8;
+
+/// A pool, the token it received and the token it sent.
+type HopKey = (Address, Address, Address);
+
+/// A pool swap within a transaction, from the net ERC-20 flows of the pool.
+#[derive(Debug, Clone, Copy)]
+struct Leg {
+    pool: Address,
+    token_in: Address,
+    amount_in: U256,
+    token_out: Address,
+    amount_out: U256,
+    /// Index of the first log moving tokens of the pool.
+    first_log: usize,
+}
+
+impl Leg {
+    fn key(&self) -> HopKey {
+        (self.pool, self.token_in, self.token_out)
+    }
+}
+
+/// Our routes by their hops, for the configuration version they were built from.
+#[derive(Debug, Default)]
+struct RouteKeys {
+    version: Option<u64>,
+    exact: HashMap<Vec<HopKey>, B256>,
+    /// Routes by their rotation starting at the smallest hop, to match cycles starting from
+    /// another token. Routes of the same cycle from different tokens share their rotation.
+    rotated: HashMap<Vec<HopKey>, Vec<B256>>,
+}
+
+impl RouteKeys {
+    fn update(&mut self, config: &SearchConfig) {
+        if self.version == Some(config.version) {
+            return;
+        }
+        self.exact.clear();
+        self.rotated.clear();
+        for route_path in config.route_paths.iter() {
+            self.insert(route_path);
+        }
+        self.version = Some(config.version);
+    }
+
+    fn insert(&mut self, route_path: &RoutePath) {
+        let key = route_path.hops
+            .iter()
+            .map(|hop| (hop.dex, hop.srcToken, hop.dstToken))
+            .collect::<Vec<_>>();
+        let id = route_path.id();
+        self.rotated.entry(rotated(&key)).or_default().push(id);
+        self.exact.insert(key, id);
+    }
+}
+
+/// Detects the arbitrages other searchers captured in committed blocks, on the pools of the
+/// route index, and matches them against our routes.
+///
+/// The ERC-20 transfers of every successful transaction give the net token flows of the pools
+/// it touched, indexed pools and pools emitting Uniswap V2/V3 swap events. Deposits into and
+/// withdrawals from the wrapped native token count as its transfers, for the pools that pay or
+/// take native tokens by unwrapping or wrapping their balance. A pool that received
+/// one token and sent another is a swap, and swaps chaining back to their first token form a
+/// cycle, whose profit is the amount out of its last swap minus the amount into its first one.
+#[derive(Debug, Default)]
+pub struct CompetitorDetector {
+    routes: RouteKeys,
+}
+
+impl CompetitorDetector {
+    /// Arbitrages in the blocks of `chain` touching an indexed pool, except the transactions
+    /// sent by `ignored` (our executor). `selected` holds the routes our search selected for
+    /// each block.
+    pub fn detect<N: NodePrimitives>(
+        &mut self,
+        chain: &Chain<N>,
+        config: &SearchConfig,
+        selected: &BTreeMap<u64, HashSet<B256>>,
+        ignored: Option<Address>
+    ) -> Vec<CompetitorArbitrage> {
+        self.routes.update(config);
+        let mut arbitrages = Vec::new();
+
+        for (block, receipts) in chain.blocks_and_receipts() {
+            let header = block.header();
+            let selected = selected.get(&header.number());
+            for ((sender, tx), receipt) in block.transactions_with_sender().zip(receipts) {
+                if !receipt.status() || Some(*sender) == ignored {
+                    continue;
+                }
+                let legs = legs(receipt.logs(), config.native_token, |pool| {
+                    config.route_index.contains(pool)
+                });
+                let cycles = cycles(&legs)
+                    .into_iter()
+                    .filter(|cycle| cycle.iter().any(|leg| config.route_index.contains(&leg.pool)));
+                for (cycle_index, cycle) in cycles.enumerate() {
+                    let (route_id, match_kind) =
+                        self.classify(&cycle, &config.route_index, selected);
+                    let (first, last) = (cycle[0], cycle[cycle.len() - 1]);
+                    arbitrages.push(CompetitorArbitrage {
+                        tx_hash: *tx.tx_hash(),
+                        cycle_index: cycle_index as u64,
+                        block_number: header.number(),
+                        timestamp: header.timestamp(),
+                        sender: *sender,
+                        contract: tx.to().unwrap_or_default(),
+                        token: first.token_in,
+                        pools: cycle.iter().map(|leg| leg.pool).collect(),
+                        profit: I256::from_raw(last.amount_out).saturating_sub(
+                            I256::from_raw(first.amount_in)
+                        ),
+                        route_id,
+                        match_kind,
+                    });
+                }
+            }
+        }
+
+        arbitrages
+    }
+
+    fn classify(
+        &self,
+        cycle: &[Leg],
+        route_index: &RouteIndex,
+        selected: Option<&HashSet<B256>>
+    ) -> (Option<B256>, CompetitorMatch) {
+        let key = cycle.iter().map(Leg::key).collect::<Vec<_>>();
+        let exact = self.routes.exact.get(&key);
+        let rotations = self.routes.rotated.get(&rotated(&key)).map_or(&[][..], Vec::as_slice);
+        // the same cycle selected from any token raced us
+        let raced = exact
+            .into_iter()
+            .chain(rotations)
+            .find(|id| selected.is_some_and(|selected| selected.contains(*id)));
+        if let Some(id) = raced {
+            return (Some(*id), CompetitorMatch::Selected);
+        }
+        if let Some(id) = exact {
+            return (Some(*id), CompetitorMatch::NotSelected);
+        }
+        if let Some(id) = rotations.first() {
+            return (Some(*id), CompetitorMatch::Rotation);
+        }
+        if cycle.iter().all(|leg| route_index.contains(&leg.pool)) {
+            (None, CompetitorMatch::NotEnumerated)
+        } else {
+            (None, CompetitorMatch::UnindexedPool)
+        }
+    }
+}
+
+/// Rotation of `key` starting at its smallest hop.
+fn rotated(key: &[HopKey]) -> Vec<HopKey> {
+    let start = key
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, hop)| **hop)
+        .map_or(0, |(index, _)| index);
+    key[start..].iter().chain(&key[..start]).copied().collect()
+}
+
+/// Swaps of the pools that moved tokens in `logs`, in order of their first transfer. Deposits
+/// and withdrawals of `wrapped_native` are transfers from and to the zero address.
+fn legs(
+    logs: &[Log],
+    wrapped_native: Option<Address>,
+    is_indexed: impl Fn(&Address) -> bool
+) -> Vec<Leg> {
+    let swapping = logs
+        .iter()
+        .filter(|log| {
+            log.topics().first().is_some_and(|topic| {
+                *topic == IUniswapV2Pair::Swap::SIGNATURE_HASH ||
+                    *topic == IUniswapV3Pool::Swap::SIGNATURE_HASH
+            })
+        })
+        .map(|log| log.address)
+        .collect::<HashSet<_>>();
+
+    // net flow of every token of every pool, and the first log moving tokens of the pool
+    let mut flows: HashMap<Address, (usize, BTreeMap<Address, I256>)> = HashMap::new();
+    for (index, log) in logs.iter().enumerate() {
+        let Some((from, to, value)) = transfer(log, wrapped_native) else {
+            continue;
+        };
+        let value = I256::from_raw(value);
+        for (pool, delta) in [(to, value), (from, value.saturating_neg())] {
+            if !is_indexed(&pool) && !swapping.contains(&pool) {
+                continue;
+            }
+            let (_, tokens) = flows.entry(pool).or_insert_with(|| (index, BTreeMap::new()));
+            let flow = tokens.entry(log.address).or_default();
+            *flow = flow.saturating_add(delta);
+        }
+    }
+
+    let mut legs = flows
+        .into_iter()
+        .filter_map(|(pool, (first_log, tokens))| {
+            let mut inflows = tokens.iter().filter(|(_, flow)| flow.is_positive());
+            let mut outflows = tokens.iter().filter(|(_, flow)| flow.is_negative());
+            match (inflows.next(), inflows.next(), outflows.next(), outflows.next()) {
+                (Some((token_in, amount_in)), None, Some((token_out, amount_out)), None) =>
+                    Some(Leg {
+                        pool,
+                        token_in: *token_in,
+                        amount_in: amount_in.unsigned_abs(),
+                        token_out: *token_out,
+                        amount_out: amount_out.unsigned_abs(),
+                        first_log,
+                    }),
+                // liquidity changes and multi-token pools don't make a single swap
+                _ => None,
+            }
+        })
+        .collect::<Vec<_>>();
+    legs.sort_by_key(|leg| leg.first_log);
+    legs
+}
+
+/// Sender, recipient and amount of the tokens `log` moves.
+fn transfer(log: &Log, wrapped_native: Option<Address>) -> Option<(Address, Address, U256)> {
+    if let Ok(transfer) = Transfer::decode_log_data(&log.data) {
+        return Some((transfer.from, transfer.to, transfer.value));
+    }
+    if wrapped_native != Some(log.address) {
+        return None;
+    }
+    if let Ok(deposit) = IWETH9::Deposit::decode_log_data(&log.data) {
+        return Some((Address::ZERO, deposit.dst, deposit.wad));
+    }
+    IWETH9::Withdrawal::decode_log_data(&log.data)
+        .ok()
+        .map(|withdrawal| (withdrawal.src, Address::ZERO, withdrawal.wad))
+}
+
+/// Cycles formed by chaining `legs` on their tokens, every leg used at most once. Each cycle
+/// starts at its earliest leg and follows the earliest unused leg out of every token.
+fn cycles(legs: &[Leg]) -> Vec<Vec<Leg>> {
+    let mut used = vec![false; legs.len()];
+    let mut cycles = Vec::new();
+
+    for start in 0..legs.len() {
+        if used[start] {
+            continue;
+        }
+        let mut chain = vec![start];
+        let mut token = legs[start].token_out;
+        while token != legs[start].token_in && chain.len() < MAX_CYCLE_LEGS {
+            let next = (0..legs.len()).find(|&index| {
+                !used[index] && !chain.contains(&index) && legs[index].token_in == token
+            });
+            let Some(next) = next else {
+                break;
+            };
+            chain.push(next);
+            token = legs[next].token_out;
+        }
+        if token == legs[start].token_in {
+            for &index in &chain {
+                used[index] = true;
+            }
+            cycles.push(chain.into_iter().map(|index| legs[index]).collect());
+        }
+    }
+
+    cycles
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use alloy_primitives::{ address, Address, Log, U256 };
+    use alloy_sol_types::SolEvent;
+    use searcher_reth_repository::types::CompetitorMatch;
+
+    use crate::{
+        strategy::path_finding::{ index::RouteIndex, types::{ Hop, RoutePath } },
+        tracker::Transfer,
+    };
+
+    use super::{ cycles, legs, CompetitorDetector, IWETH9 };
+
+    const SEARCHER: Address = address!("00000000000000000000000000000000000000ee");
+    const PAIR: Address = address!("0d4a11d5EEaaC28EC3F61d100daF4d40471f1852");
+    const CURVE_POOL: Address = address!("D51a44d3FaE010294C616388b506AcdA1bfAAE46");
+    const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    const USDT: Address = address!("dAC17F958D2ee523a2206206994597C13D831ec7");
+
+    fn transfer(token: Address, from: Address, to: Address, value: u64) -> Log {
+        let value = U256::from(value);
+        Log { address: token, data: Transfer { from, to, value }.encode_log_data() }
+    }
+
+    /// Buys USDT with WETH on the pair and sells it on a pool paying native tokens, which
+    /// unwraps its WETH.
+    fn logs() -> Vec<Log> {
+        vec![
+            transfer(WETH, SEARCHER, PAIR, 1_000),
+            transfer(USDT, PAIR, CURVE_POOL, 2_000),
+            Log {
+                address: WETH,
+                data: IWETH9::Withdrawal { src: CURVE_POOL, wad: U256::from(1_010) }
+                    .encode_log_data(),
+            }
+        ]
+    }
+
+    fn route(hops: &[(Address, Address, Address)]) -> RoutePath {
+        let hops = hops
+            .iter()
+            .map(|&(dex, src_token, dst_token)| Hop {
+                dexType: 0,
+                dex,
+                srcToken: src_token,
+                dstToken: dst_token,
+            })
+            .collect();
+        RoutePath { hops }
+    }
+
+    #[test]
+    fn counts_wrapped_native_withdrawals_as_transfers() {
+        let is_indexed = |pool: &Address| *pool == PAIR || *pool == CURVE_POOL;
+
+        // without them the pool only receives USDT, which is not a swap
+        assert_eq!(legs(&logs(), None, is_indexed).len(), 1);
+
+        let legs = legs(&logs(), Some(WETH), is_indexed);
+        let cycles = cycles(&legs);
+        assert_eq!(cycles.len(), 1);
+        let cycle = cycles[0]
+            .iter()
+            .map(|leg| (leg.pool, leg.token_in, leg.token_out))
+            .collect::<Vec<_>>();
+        assert_eq!(cycle, vec![(PAIR, WETH, USDT), (CURVE_POOL, USDT, WETH)]);
+        assert_eq!(cycles[0][0].amount_in, U256::from(1_000));
+        assert_eq!(cycles[0][1].amount_out, U256::from(1_010));
+    }
+
+    #[test]
+    fn matches_rotations_of_selected_routes() {
+        let from_weth = route(&[(PAIR, WETH, USDT), (CURVE_POOL, USDT, WETH)]);
+        let from_usdt = route(&[(CURVE_POOL, USDT, WETH), (PAIR, WETH, USDT)]);
+        let route_paths = vec![from_weth.clone(), from_usdt.clone()];
+        let route_index = RouteIndex::new(&route_paths);
+        let mut detector = CompetitorDetector::default();
+        for route_path in &route_paths {
+            detector.routes.insert(route_path);
+        }
+        let legs = legs(&logs(), Some(WETH), |pool| route_index.contains(pool));
+        let cycle = &cycles(&legs)[0];
+
+        // the cycle starts from WETH, our search selected the same cycle from USDT
+        let selected = HashSet::from([from_usdt.id()]);
+        assert_eq!(
+            detector.classify(cycle, &route_index, Some(&selected)),
+            (Some(from_usdt.id()), CompetitorMatch::Selected)
+        );
+        assert_eq!(
+            detector.classify(cycle, &route_index, None),
+            (Some(from_weth.id()), CompetitorMatch::NotSelected)
+        );
+
+        // only the rotation is a route of ours
+        let mut detector = CompetitorDetector::default();
+        detector.routes.insert(&from_usdt);
+        assert_eq!(
+            detector.classify(cycle, &route_index, Some(&selected)),
+            (Some(from_usdt.id()), CompetitorMatch::Selected)
+        );
+        assert_eq!(
+            detector.classify(cycle, &route_index, Some(&HashSet::new())),
+            (Some(from_usdt.id()), CompetitorMatch::Rotation)
+        );
+    }
+}
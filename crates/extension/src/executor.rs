@@ -0,0 +1,183 @@
+use std::{ fs, path::Path };
+
+use alloy_consensus::{ SignableTransaction, TxEip1559, TxEnvelope };
+use alloy_eips::eip2718::Encodable2718;
+use alloy_primitives::{ Address, Bytes, TxKind, U256 };
+use alloy_signer::SignerSync;
+use alloy_signer_local::PrivateKeySigner;
+use alloy_sol_types::SolValue;
+use eyre::{ eyre, Result };
+
+use crate::strategy::path_finding::{ gas::intrinsic_gas, types::ProfitablePath };
+
+/// Percentage added on top of the simulated execution gas of a route for its gas limit, by default.
+pub const DEFAULT_GAS_LIMIT_MARGIN: u64 = 20;
+
+/// Signs the transactions executing found routes on the deployed searcher contract.
+#[derive(Debug, Clone)]
+pub struct Executor {
+    signer: PrivateKeySigner,
+    /// Address of the searcher contract on chain.
+    contract: Address,
+    /// Percentage added on top of the simulated execution gas to get the gas limit.
+    gas_limit_margin: u64,
+}
+
+impl Executor {
+    pub fn new(signer: PrivateKeySigner, contract: Address, gas_limit_margin: u64) -> Self {
+        Self { signer, contract, gas_limit_margin }
+    }
+
+    /// Loads the signing key from an encrypted JSON keystore, with the password read from
+    /// `password_file` (trailing newline excluded).
+    pub fn from_keystore(
+        keystore: &Path,
+        password_file: &Path,
+        contract: Address,
+        gas_limit_margin: u64
+    ) -> Result<Self> {
+        let password = fs::read_to_string(password_file)?;
+        let password = password.trim_end_matches(['\r', '\n']);
+        let signer = PrivateKeySigner::decrypt_keystore(keystore, password)
+            .map_err(|err| eyre!("failed to decrypt keystore {}: {err}", keystore.display()))?;
+        Ok(Self::new(signer, contract, gas_limit_margin))
+    }
+
+    /// Address sending the transactions, whose nonce they use.
+    pub fn address(&self) -> Address {
+        self.signer.address()
+    }
+
+    pub fn signer(&self) -> &PrivateKeySigner {
+        &self.signer
+    }
+
+    /// Signs the EIP-1559 transaction executing `path` with `nonce`, returns it EIP-2718 encoded.
+    ///
+    /// The max fee is the gas price the path was priced at plus its bid, so the net profit holds
+    /// as long as the base fee prediction does, and the bid is paid on top of `priority_fee`.
+    ///
+    /// The route was simulated as a system call, which charges no intrinsic gas and doesn't run
+    /// from this sender. The gas limit is the exact intrinsic gas of the signed calldata plus the
+    /// simulated execution gas with the margin, which also absorbs the sender difference.
+    pub fn sign_route(
+        &self,
+        path: &ProfitablePath,
+        chain_id: u64,
+        nonce: u64,
+        priority_fee: u128
+    ) -> Result<Bytes> {
+        let max_fee_per_gas =
+            path.gas_price.saturating_to::<u128>().saturating_add(path.bid_per_gas);
+        let max_priority_fee_per_gas = priority_fee.saturating_add(path.bid_per_gas);
+        let input = path.route_path.sized(path.amount_in).abi_encode();
+        let intrinsic = intrinsic_gas(&input);
+        let execution = path.gas_used.saturating_sub(intrinsic);
+        let gas_limit = execution.saturating_mul(100 + self.gas_limit_margin) / 100 + intrinsic;
+        let tx = TxEip1559 {
+            chain_id,
+            nonce,
+            gas_limit,
+            max_fee_per_gas,
+            max_priority_fee_per_gas: max_priority_fee_per_gas.min(max_fee_per_gas),
+            to: TxKind::Call(self.contract),
+            value: U256::ZERO,
+            access_list: Default::default(),
+            input: input.into(),
+        };
+        let signature = self.signer.sign_hash_sync(&tx.signature_hash())?;
+        Ok(TxEnvelope::from(tx.into_signed(signature)).encoded_2718().into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_consensus::{ Signed, TxEip1559, TxEnvelope };
+    use alloy_eips::eip2718::Decodable2718;
+    use alloy_primitives::{ b256, Address, TxKind, U256 };
+    use alloy_signer_local::PrivateKeySigner;
+    use alloy_sol_types::SolValue;
+
+    use crate::strategy::path_finding::{
+        gas::intrinsic_gas,
+        types::{ Hop, ProfitablePath, RoutePath },
+    };
+
+    use super::Executor;
+
+    const CONTRACT: Address = Address::with_last_byte(0xee);
+    const CHAIN_ID: u64 = 1;
+    const GWEI: u128 = 1_000_000_000;
+
+    fn executor(gas_limit_margin: u64) -> Executor {
+        let key = b256!("4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318");
+        Executor::new(PrivateKeySigner::from_bytes(&key).unwrap(), CONTRACT, gas_limit_margin)
+    }
+
+    fn path() -> ProfitablePath {
+        let hop = |dex: u8, src_token: u8, dst_token: u8| Hop {
+            dexType: 0,
+            dex: Address::with_last_byte(dex),
+            srcToken: Address::with_last_byte(src_token),
+            dstToken: Address::with_last_byte(dst_token),
+        };
+        ProfitablePath {
+            route_path: RoutePath { hops: vec![hop(1, 0xaa, 0xbb), hop(2, 0xbb, 0xaa)] },
+            amount_in: U256::from(10u64.pow(18)),
+            amount_out: U256::from(1_100_000_000_000_000_000u64),
+            gas_used: 150_000,
+            gas_price: U256::from(10 * GWEI),
+            gas_cost: U256::from(1_500_000 * GWEI),
+            bid: U256::from(300_000 * GWEI),
+            bid_per_gas: 2 * GWEI,
+            profit: U256::from(10u64.pow(17)),
+            profit_ratio: 100_000,
+        }
+    }
+
+    fn sign(executor: &Executor, path: &ProfitablePath, priority_fee: u128) -> Signed<TxEip1559> {
+        let raw = executor.sign_route(path, CHAIN_ID, 7, priority_fee).unwrap();
+        match TxEnvelope::decode_2718(&mut raw.as_ref()).unwrap() {
+            TxEnvelope::Eip1559(tx) => tx,
+            tx => panic!("expected an EIP-1559 transaction, got {tx:?}"),
+        }
+    }
+
+    #[test]
+    fn signs_the_sized_route_for_the_contract() {
+        let (executor, path) = (executor(20), path());
+        let signed = sign(&executor, &path, GWEI);
+        let tx = signed.tx();
+
+        assert_eq!(signed.recover_signer().unwrap(), executor.address());
+        assert_eq!((tx.chain_id, tx.nonce), (CHAIN_ID, 7));
+        assert_eq!(tx.to, TxKind::Call(CONTRACT));
+        assert_eq!(tx.value, U256::ZERO);
+        assert_eq!(tx.input, path.route_path.sized(path.amount_in).abi_encode());
+    }
+
+    #[test]
+    fn adds_the_margin_to_the_execution_gas_only() {
+        let path = path();
+        let intrinsic = intrinsic_gas(&path.route_path.sized(path.amount_in).abi_encode());
+        let execution = path.gas_used - intrinsic;
+
+        assert_eq!(sign(&executor(0), &path, 0).tx().gas_limit, path.gas_used);
+        assert_eq!(sign(&executor(20), &path, 0).tx().gas_limit, execution * 120 / 100 + intrinsic);
+    }
+
+    #[test]
+    fn bids_on_top_of_the_priority_fee() {
+        let signed = sign(&executor(20), &path(), GWEI);
+
+        assert_eq!(signed.tx().max_fee_per_gas, 12 * GWEI);
+        assert_eq!(signed.tx().max_priority_fee_per_gas, 3 * GWEI);
+    }
+
+    #[test]
+    fn caps_the_priority_fee_at_the_max_fee() {
+        let signed = sign(&executor(20), &path(), 100 * GWEI);
+
+        assert_eq!(signed.tx().max_priority_fee_per_gas, signed.tx().max_fee_per_gas);
+    }
+}
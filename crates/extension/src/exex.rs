@@ -1,20 +1,41 @@
-use std::{ future::Future, sync::Arc };
+use std::{ collections::{ BTreeMap, HashMap, HashSet }, future::Future, sync::Arc };
 
-use eyre::Result;
+use eyre::{ eyre, Result };
 use futures_util::StreamExt;
 
+use alloy_eips::BlockNumHash;
+use alloy_primitives::{ keccak256, B256, U256 };
+use reth_chainspec::{ EthChainSpec, EthereumHardforks };
 use reth_exex::{ ExExContext, ExExEvent, ExExNotification };
-use reth_node_api::{ FullNodeComponents, FullNodeTypes };
-use alloy_sol_types::SolValue;
-use reth_provider::{
-    BlockHashReader,
-    DatabaseProviderFactory,
-    LatestStateProviderRef,
-    StateCommitmentProvider,
+use reth_node_api::{ FullNodeComponents, FullNodeTypes, NodeTypes };
+use reth_primitives_traits::NodePrimitives;
+use reth_provider::{ HeaderProvider, StateProviderFactory };
+use reth_revm::db::BundleState;
+use reth_tracing::tracing::{ debug, info, warn };
+use searcher_reth_repository::SearcherRepository;
+use tokio::sync::watch;
+use crate::{
+    competitor::CompetitorDetector,
+    mempool::MempoolSearcher,
+    oracle::PriceOracle,
+    output::{ send, OutputMessage, OutputSocket },
+    relay,
+    strategy::{
+        negative_cycle::NegativeCycleFinder,
+        path_finding::{
+            budget::SearchBudget,
+            env::SimulationEnv,
+            gas::GasPricing,
+            strategy::Strategy,
+            PathFinder,
+        },
+        SearchStrategy,
+    },
+    SearchConfig,
 };
-use tokio::sync::RwLock;
-use tokio::net::UnixDatagram;
-use crate::{ strategy::path_finding::{ PathFinder, strategy::Strategy }, SearcherExtension };
+
+/// Number of recent blocks whose opportunities are retracted when they are reorged out.
+pub(crate) const RETRACTABLE_BLOCKS: u64 = 256;
 
 pub struct SearcherExEx;
 
@@ -22,56 +43,561 @@ pub struct SearcherExEx;
 impl SearcherExEx {
     pub async fn exex<Node>(
         mut ctx: ExExContext<Node>,
-        extension: Arc<RwLock<SearcherExtension>>,
-        sock: Arc<UnixDatagram>
+        mut config_rx: watch::Receiver<Arc<SearchConfig>>,
+        sock: Arc<OutputSocket>,
+        repo: Arc<SearcherRepository>
     )
         -> Result<impl Future<Output = Result<()>>>
         where
             Node: FullNodeComponents,
-            <<Node as FullNodeTypes>::Provider as DatabaseProviderFactory>::Provider: BlockHashReader +
-                StateCommitmentProvider
+            <<Node as FullNodeTypes>::Types as NodeTypes>::ChainSpec: EthereumHardforks
     {
+        // backruns of pending transactions are searched on their own tasks, if enabled
+        let mempool_concurrency = {
+            let config = config_rx.borrow();
+            config.mempool_search.then_some(config.mempool_concurrency)
+        };
+        if let Some(concurrency) = mempool_concurrency {
+            let mempool = MempoolSearcher::new(
+                ctx.pool().clone(),
+                ctx.provider().clone(),
+                ctx.config.chain.clone(),
+                config_rx.clone(),
+                sock.clone(),
+                ctx.task_executor().clone()
+            ).with_concurrency(concurrency);
+            ctx.task_executor().spawn(mempool.run());
+        }
+
+        let chain_id = ctx.config.chain.chain_id();
+
         Ok(async move {
-            let extension = extension.read().await;
-            let bytecode = extension.contract.clone();
-            let route_paths = extension.route_paths.clone();
-
-            while let Some(notification) = ctx.notifications.next().await {
-                match notification {
-                    Ok(ExExNotification::ChainCommitted { new: chain }) => {
-                        let block = chain.tip();
+            let mut last_full_sweep: Option<u64> = None;
+            let mut last_config_version: Option<u64> = None;
+            // blocks whose opportunities were sent and would have to be retracted on a reorg
+            let mut emitted: BTreeMap<u64, B256> = BTreeMap::new();
+            // routes selected for each upcoming block, to tell why competitors' arbitrages
+            // were missed
+            let mut selected: BTreeMap<u64, HashSet<B256>> = BTreeMap::new();
+            let mut competitors = CompetitorDetector::default();
+
+            // notification that arrived during a search, handled once the search returned
+            let mut pending = None;
+
+            loop {
+                let notification = match pending.take() {
+                    Some(notification) => notification,
+                    None => match ctx.notifications.next().await {
+                        Some(notification) => notification,
+                        None => break,
+                    },
+                };
+                let notification = match notification {
+                    Ok(notification) => notification,
+                    Err(err) => {
+                        warn!(target: "searcher_exex", %err, "failed to receive notification");
+                        continue;
+                    }
+                };
+
+                if let Some(reverted) = notification.reverted_chain() {
+                    for block in reverted.blocks().values() {
                         let num_hash = block.num_hash();
-                        if bytecode.clone().is_empty() {
-                            ctx.events.send(ExExEvent::FinishedHeight(num_hash))?;
-                            continue;
+                        if emitted.get(&num_hash.number) == Some(&num_hash.hash) {
+                            emitted.remove(&num_hash.number);
+                            send(&sock, OutputMessage::retraction(num_hash)).await;
+                            info!(
+                                target: "searcher_exex",
+                                block = num_hash.number,
+                                "opportunities retracted"
+                            );
                         }
-                        // Create a read-only database provider that we can use to get lastest state
-                        let database_provider: <<Node as FullNodeTypes>::Provider as DatabaseProviderFactory>::Provider = ctx
-                            .provider()
-                            .database_provider_ro()?;
-                        let latest_state_provider = LatestStateProviderRef::new(&database_provider);
-                        // create a task to simulate contract execution in searcher executor parallel
-                        let mut finder = PathFinder::new(latest_state_provider, bytecode.clone());
-                        let optimal_paths = finder.filter_candidates(
-                            route_paths.clone(),
-                            extension.max_profit_ratio,
-                            extension.min_profit_ratio
-                        )?;
-
-                        let encoded_paths = optimal_paths.abi_encode();
-
-                        let sock = sock.clone();
+                    }
+                    // routes touched by the reverted blocks were last simulated on their state,
+                    // so the next search sweeps every route on the new tip
+                    last_full_sweep = None;
+                }
+
+                // the new canonical tip is the committed chain's tip or, on a plain revert, the
+                // fork point of the reverted chain
+                let committed = notification.committed_chain();
+                let Some(base_chain) = committed.clone().or_else(|| notification.reverted_chain())
+                else {
+                    continue;
+                };
+                let fork_block = base_chain.fork_block();
+                let num_hash = committed
+                    .as_ref()
+                    .map_or(fork_block, |chain| chain.tip().num_hash());
+                // configuration changes are picked up at block boundaries, and a new version may
+                // bring new routes or a new contract, so it sweeps every route once
+                let config = config_rx.borrow_and_update().clone();
+                if last_config_version != Some(config.version) {
+                    last_config_version = Some(config.version);
+                    last_full_sweep = None;
+                }
+                // mark the outcomes of reorged blocks, then settle the transactions the committed
+                // blocks included or left behind, which overwrites the ones they include again
+                let mut executions = notification
+                    .reverted_chain()
+                    .map(|chain| config.tracker.revert(&chain))
+                    .unwrap_or_default();
+                if let Some(chain) = &committed {
+                    executions.extend(config.tracker.settle(chain));
+                }
+                if !executions.is_empty() {
+                    let repo = repo.clone();
+                    tokio::spawn(async move {
+                        if let Err(err) = repo.insert_executions(chain_id, &executions).await {
+                            warn!(target: "searcher_exex", %err, "failed to record executions");
+                        }
+                    });
+                }
+                if let Some(chain) = &committed {
+                    let executor = config.executor.as_ref().map(|executor| executor.address());
+                    let arbitrages = competitors.detect(chain, &config, &selected, executor);
+                    if !arbitrages.is_empty() {
+                        let repo = repo.clone();
                         tokio::spawn(async move {
-                            sock.send(&encoded_paths).await.unwrap();
+                            if let Err(err) =
+                                repo.insert_competitor_arbitrages(chain_id, &arbitrages).await
+                            {
+                                warn!(
+                                    target: "searcher_exex",
+                                    %err,
+                                    "failed to record competitor arbitrages"
+                                );
+                            }
                         });
-                        // transfer optimal_paths to the socket
+                    }
+                }
+                let finish = |ctx: &ExExContext<Node>| -> Result<()> {
+                    // only committed blocks can be reported as finished
+                    if committed.is_some() {
                         ctx.events.send(ExExEvent::FinishedHeight(num_hash))?;
                     }
-                    _ => {}
+                    Ok(())
+                };
+                if config.contract.is_empty() {
+                    finish(&ctx)?;
+                    continue;
+                }
+                // the database may lag behind the notification, so simulate on the committed
+                // chain's post-state layered on the state at its fork point. A block whose state
+                // can't be read is skipped instead of stopping the exex
+                let state = (|| {
+                    let header = match &committed {
+                        Some(chain) => chain.tip().header().clone(),
+                        None => ctx
+                            .provider()
+                            .header(&fork_block.hash)?
+                            .ok_or_else(|| eyre!("missing header of block {}", fork_block.hash))?,
+                    };
+                    let provider = ctx.provider().history_by_block_hash(fork_block.hash)?;
+                    Ok::<_, eyre::Error>((header, provider))
+                })();
+                let (header, fork_state_provider) = match state {
+                    Ok(state) => state,
+                    Err(err) => {
+                        warn!(
+                            target: "searcher_exex",
+                            block = num_hash.number,
+                            %err,
+                            "failed to read the state of the block, skipping it"
+                        );
+                        finish(&ctx)?;
+                        continue;
+                    }
+                };
+                let block_hashes: HashMap<_, _> = committed
+                    .iter()
+                    .flat_map(|chain| chain.blocks().iter())
+                    .map(|(number, block)| (*number, block.hash()))
+                    .collect();
+
+                // only re-simulate routes through pools whose storage changed, with a
+                // periodic full sweep to catch anything the index misses
+                // (the negative cycle search always needs the whole rate graph)
+                let full_sweep = config.strategy == SearchStrategy::NegativeCycle ||
+                    last_full_sweep.is_none_or(|last| {
+                        num_hash.number.saturating_sub(last) >= config.full_sweep_interval
+                    });
+                let candidates = if full_sweep {
+                    last_full_sweep = Some(num_hash.number);
+                    config.route_paths.as_ref().clone()
+                } else {
+                    let changed_pools = committed
+                        .iter()
+                        .flat_map(|chain| chain.execution_outcome().bundle.state())
+                        .filter(|(_, account)| {
+                            account.storage.values().any(|slot| slot.is_changed())
+                        })
+                        .map(|(address, _)| address);
+                    config.route_index
+                        .touched_routes(changed_pools)
+                        .into_iter()
+                        .map(|index| config.route_paths[index].clone())
+                        .collect()
+                };
+
+                // simulations run in the environment of the next block, and net
+                // profits are priced at its gas price
+                let env = SimulationEnv::next_block(
+                    ctx.config.chain.as_ref(),
+                    &header,
+                    config.block_time,
+                    config.coinbase
+                );
+                let gas_price =
+                    U256::from(env.block.basefee) + U256::from(config.priority_fee);
+
+                // search off the exex task, so that the next notification can cancel it, and
+                // within the time budget. An interrupted search returns the paths found so far,
+                // most promising candidates first.
+                let candidates_len = candidates.len();
+                let budget = SearchBudget::new(config.time_budget);
+                let mut search = tokio::task::spawn_blocking({
+                    let (committed, config, budget) =
+                        (committed.clone(), config.clone(), budget.clone());
+                    move || {
+                        let empty_bundle = BundleState::default();
+                        let bundle = committed
+                            .as_ref()
+                            .map_or(&empty_bundle, |chain| &chain.execution_outcome().bundle);
+                        // simulate contract execution in parallel over the post-state of the chain
+                        let finder = PathFinder::new(
+                            fork_state_provider,
+                            bundle,
+                            block_hashes,
+                            config.contract.clone()
+                        )
+                            .with_env(env)
+                            .with_uniswap_v2_fees(config.uniswap_v2_fees.clone());
+                        // prices of this block, shared by every simulator of the finder.
+                        // Without a native token gas can't be priced, profits are gross
+                        let gas_pricing = match config.native_token {
+                            Some(native_token) => {
+                                let oracle = PriceOracle::load(
+                                    &mut finder.simulator(),
+                                    num_hash,
+                                    Some(native_token),
+                                    &config.reference_pools
+                                );
+                                GasPricing::new(gas_price, Arc::new(oracle))
+                            }
+                            None => GasPricing::gross(gas_price),
+                        };
+                        let mut finder = finder
+                            .with_threads(config.simulation_threads)
+                            .with_gas_pricing(gas_pricing)
+                            .with_budget(budget)
+                            .with_bidding(config.bidding.clone());
+                        let nonce = config.executor
+                            .as_ref()
+                            .map(|executor| finder.nonce(executor.address()))
+                            .transpose()?;
+                        let selection = match config.strategy {
+                            SearchStrategy::Enumeration => finder.filter_candidates(
+                                candidates,
+                                config.max_profit_ratio,
+                                config.min_profit_ratio
+                            )?,
+                            SearchStrategy::NegativeCycle => NegativeCycleFinder::new(
+                                finder,
+                                config.pool_hops.clone(),
+                                config.beginning_tokens.clone()
+                            ).filter_candidates(
+                                candidates,
+                                config.max_profit_ratio,
+                                config.min_profit_ratio
+                            )?,
+                        };
+                        Ok::<_, eyre::Error>((selection, nonce))
+                    }
+                });
+                let searched = tokio::select! {
+                    selection = &mut search => selection,
+                    notification = ctx.notifications.next() => {
+                        // only a notification that moves the tip away from the searched block
+                        // makes the search stale, it is handled once the search returned anyway
+                        let stale = match &notification {
+                            Some(Ok(notification)) => moves_tip(notification, num_hash),
+                            Some(Err(_)) => false,
+                            None => true,
+                        };
+                        if stale {
+                            budget.cancel();
+                        }
+                        pending = notification;
+                        search.await
+                    }
+                };
+                let (selection, nonce) = match searched.map_err(eyre::Error::from).and_then(|r| r) {
+                    Ok(searched) => searched,
+                    Err(err) => {
+                        warn!(
+                            target: "searcher_exex",
+                            block = num_hash.number,
+                            %err,
+                            "route path search failed, skipping the block"
+                        );
+                        finish(&ctx)?;
+                        continue;
+                    }
+                };
+                for rejected in &selection.rejected {
+                    debug!(
+                        target: "searcher_exex",
+                        block = num_hash.number,
+                        reason = ?rejected.reason,
+                        "route path rejected"
+                    );
                 }
+                info!(
+                    target: "searcher_exex",
+                    block = num_hash.number,
+                    full_sweep,
+                    interrupted = selection.interrupted,
+                    candidates = candidates_len,
+                    optimal = selection.optimal_paths.len(),
+                    rejected = selection.rejected.len(),
+                    "route paths simulated"
+                );
+
+                selected.insert(
+                    num_hash.number + 1,
+                    selection.optimal_paths.iter().map(|path| path.route_path.id()).collect()
+                );
+                selected = selected.split_off(&num_hash.number.saturating_sub(RETRACTABLE_BLOCKS));
+
+                // transfer optimal_paths to the socket
+                let opportunities = selection.opportunities();
+                if !opportunities.is_empty() {
+                    emitted.insert(num_hash.number, num_hash.hash);
+                    // reorgs deeper than the window are not retracted
+                    emitted = emitted.split_off(
+                        &num_hash.number.saturating_sub(RETRACTABLE_BLOCKS)
+                    );
+                }
+                send(
+                    &sock,
+                    OutputMessage::opportunities(num_hash, config.version, opportunities)
+                ).await;
+                // a single bundle for the best route: every route spends the current nonce, so at
+                // most one of them could land and the others would only compete with it
+                let best = selection.optimal_paths.first();
+                if let (Some(executor), Some(nonce), Some(path)) = (&config.executor, nonce, best) {
+                    match executor.sign_route(path, chain_id, nonce, config.priority_fee) {
+                        Ok(tx) => {
+                            config.tracker.track(
+                                keccak256(&tx),
+                                path,
+                                config.version,
+                                num_hash.number + 1,
+                                config.last_target_block(num_hash.number + 1)
+                            );
+                            if let Some(relays) = &config.relays {
+                                relay::submit(relays, num_hash.number + 1, vec![tx.clone()]);
+                            }
+                            let bundle = OutputMessage::bundle(
+                                num_hash.number + 1,
+                                config.version,
+                                None,
+                                vec![tx]
+                            );
+                            send(&sock, bundle).await;
+                        }
+                        Err(err) => {
+                            warn!(target: "searcher_exex", %err, "failed to sign route path");
+                        }
+                    }
+                }
+                finish(&ctx)?;
             }
 
             Ok(())
         })
     }
 }
+
+/// Whether `notification` reorgs the chain or advances it past `tip`.
+fn moves_tip<N: NodePrimitives>(notification: &ExExNotification<N>, tip: BlockNumHash) -> bool {
+    notification.reverted_chain().is_some() ||
+        notification
+            .committed_chain()
+            .is_some_and(|chain| chain.tip().num_hash().number > tip.number)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ sync::Arc, time::Duration };
+
+    use alloy_eips::BlockNumHash;
+    use alloy_primitives::{ address, hex, Bytes };
+    use clap::Parser;
+    use reth_execution_types::{ Chain, ExecutionOutcome };
+    use reth_exex::ExExEvent;
+    use reth_exex_test_utils::{ test_exex_context, TestExExHandle };
+    use reth_revm::state::Bytecode;
+    use reth_testing_utils::generators::{ self, random_block, BlockParams, Rng };
+    use searcher_reth_ipc::{ Message, MessageBody, Reassembler, DEFAULT_MAX_DATAGRAM };
+    use searcher_reth_repository::SearcherRepository;
+    use tokio::{ net::UnixDatagram, sync::watch, time::timeout };
+
+    use crate::{
+        output::OutputSocket,
+        strategy::path_finding::{ index::RouteIndex, types::{ Hop, RoutePath } },
+        SearcherExtension,
+        SetupArgs,
+    };
+
+    use super::SearcherExEx;
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Returns `SwapResult(1, 2)` for any route: every route is profitable.
+    const PROFITABLE_CONTRACT: [u8; 15] = hex!("6001600052600260205260406000f3");
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: SetupArgs,
+    }
+
+    /// The exex of a test node with a single always-profitable route, and the other end of its
+    /// output socket.
+    async fn start() -> eyre::Result<(TestExExHandle, UnixDatagram)> {
+        let (ctx, handle) = test_exex_context().await?;
+
+        let extension = SearcherExtension::new(Cli::parse_from(["searcher"]).args)?;
+        let mut config = extension.subscribe().borrow().as_ref().clone();
+        // a dex type without native quoting, so the route always goes to the contract
+        let route = RoutePath {
+            hops: vec![
+                Hop {
+                    dexType: u8::MAX,
+                    dex: address!("0000000000000000000000000000000000000001"),
+                    srcToken: address!("00000000000000000000000000000000000000aa"),
+                    dstToken: address!("00000000000000000000000000000000000000bb"),
+                },
+                Hop {
+                    dexType: u8::MAX,
+                    dex: address!("0000000000000000000000000000000000000002"),
+                    srcToken: address!("00000000000000000000000000000000000000bb"),
+                    dstToken: address!("00000000000000000000000000000000000000aa"),
+                }
+            ],
+        };
+        config.contract = Bytecode::new_raw(Bytes::from_static(&PROFITABLE_CONTRACT));
+        config.route_index = Arc::new(RouteIndex::new(std::slice::from_ref(&route)));
+        config.route_paths = Arc::new(vec![route]);
+        config.simulation_threads = 1;
+        let (config_tx, config_rx) = watch::channel(Arc::new(config));
+
+        let (sock, receiver) = UnixDatagram::pair()?;
+        let sock = Arc::new(OutputSocket::new(sock, 1));
+        let repo = Arc::new(SearcherRepository::new("sqlite::memory:").await?);
+        let exex = SearcherExEx::exex(ctx, config_rx, sock, repo).await?;
+        tokio::spawn(async move {
+            // keep the configuration alive for as long as the exex runs
+            let _config_tx = config_tx;
+            exex.await
+        });
+        Ok((handle, receiver))
+    }
+
+    /// A single-block chain on top of `parent`.
+    fn chain(rng: &mut impl Rng, parent: BlockNumHash) -> Chain {
+        let params = BlockParams {
+            parent: Some(parent.hash),
+            tx_count: Some(0),
+            ..Default::default()
+        };
+        let block = random_block(rng, parent.number + 1, params).try_recover().unwrap();
+        Chain::from_block(block, ExecutionOutcome::default(), None)
+    }
+
+    async fn next_message(receiver: &UnixDatagram, reassembler: &mut Reassembler) -> Message {
+        let mut datagram = vec![0; DEFAULT_MAX_DATAGRAM];
+        loop {
+            let len = timeout(TIMEOUT, receiver.recv(&mut datagram)).await
+                .expect("no message on the output socket")
+                .unwrap();
+            if let Some(message) = reassembler.push(&datagram[..len]).unwrap() {
+                return message;
+            }
+        }
+    }
+
+    async fn next_finished_height(handle: &mut TestExExHandle) -> BlockNumHash {
+        match timeout(TIMEOUT, handle.events_rx.recv()).await.expect("no exex event") {
+            Some(ExExEvent::FinishedHeight(height)) => height,
+            event => panic!("unexpected exex event {event:?}"),
+        }
+    }
+
+    fn message_block(message: &Message) -> BlockNumHash {
+        BlockNumHash::new(message.header.block_number, message.header.block_hash)
+    }
+
+    fn assert_opportunities(message: &Message, block: BlockNumHash) {
+        assert_eq!(message_block(message), block);
+        let MessageBody::Opportunities(opportunities) = &message.body else {
+            panic!("expected opportunities, got {:?}", message.body);
+        };
+        assert!(!opportunities.is_empty());
+    }
+
+    fn assert_retraction(message: &Message, block: BlockNumHash) {
+        assert_eq!(message_block(message), block);
+        assert!(matches!(message.body, MessageBody::Retraction));
+    }
+
+    #[tokio::test]
+    async fn retracts_reorged_blocks_and_searches_the_new_tip() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let (mut handle, receiver) = start().await?;
+        let mut reassembler = Reassembler::default();
+        let genesis = handle.genesis.num_hash();
+
+        let old = chain(&mut rng, genesis);
+        let old_tip = old.tip().num_hash();
+        handle.send_notification_chain_committed(old.clone()).await?;
+        assert_opportunities(&next_message(&receiver, &mut reassembler).await, old_tip);
+        assert_eq!(next_finished_height(&mut handle).await, old_tip);
+
+        let new = chain(&mut rng, genesis);
+        let new_tip = new.tip().num_hash();
+        handle.send_notification_chain_reorged(old, new).await?;
+        assert_retraction(&next_message(&receiver, &mut reassembler).await, old_tip);
+        assert_opportunities(&next_message(&receiver, &mut reassembler).await, new_tip);
+        assert_eq!(next_finished_height(&mut handle).await, new_tip);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn retracts_reverted_blocks_without_finishing_them() -> eyre::Result<()> {
+        let mut rng = generators::rng();
+        let (mut handle, receiver) = start().await?;
+        let mut reassembler = Reassembler::default();
+        let genesis = handle.genesis.num_hash();
+
+        let reverted = chain(&mut rng, genesis);
+        let reverted_tip = reverted.tip().num_hash();
+        handle.send_notification_chain_committed(reverted.clone()).await?;
+        assert_opportunities(&next_message(&receiver, &mut reassembler).await, reverted_tip);
+        assert_eq!(next_finished_height(&mut handle).await, reverted_tip);
+
+        // the fork point becomes the tip and is searched again, but isn't reported as finished
+        handle.send_notification_chain_reverted(reverted).await?;
+        assert_retraction(&next_message(&receiver, &mut reassembler).await, reverted_tip);
+        assert_opportunities(&next_message(&receiver, &mut reassembler).await, genesis);
+
+        let committed = chain(&mut rng, genesis);
+        let committed_tip = committed.tip().num_hash();
+        handle.send_notification_chain_committed(committed).await?;
+        assert_opportunities(&next_message(&receiver, &mut reassembler).await, committed_tip);
+        assert_eq!(next_finished_height(&mut handle).await, committed_tip);
+        Ok(())
+    }
+}
@@ -1,17 +1,89 @@
+pub mod amm;
+pub mod competitor;
+pub mod executor;
 pub mod exex;
+pub mod mempool;
+pub mod oracle;
+pub mod output;
+pub mod relay;
+pub mod router;
 pub mod strategy;
+pub mod tracker;
 
-use eyre::{ Error, Result };
+use std::{ collections::HashSet, path::PathBuf, sync::Arc, time::Duration };
+
+use alloy_primitives::{ Address, U256 };
+use amm::uniswap_v2::{ UniswapV2Fee, UniswapV2Fees };
+use eyre::{ eyre, Error, Result };
 use revm::{ primitives::Bytes, state::Bytecode };
 
 use clap::Args;
-use strategy::path_finding::types::RoutePath;
+use executor::{ Executor, DEFAULT_GAS_LIMIT_MARGIN };
+use mempool::DEFAULT_CONCURRENCY;
+use oracle::ReferencePool;
+use relay::{ Relay, RelayClient };
+use strategy::{
+    path_finding::{
+        bidding::{ BidPolicyKind, BiddingPolicy, CappedShare, CompetitionAdjusted, FixedShare },
+        candidate::CandidateLimits,
+        index::RouteIndex,
+        types::{ Hop, RoutePath },
+    },
+    SearchStrategy,
+};
+use tokio::sync::watch;
+use tracker::InclusionTracker;
 
 pub struct SearcherExtension {
+    config: SearchConfig,
+    candidate_limits: CandidateLimits,
+    /// Publishes every new version of `config` to the running ExEx.
+    config_tx: watch::Sender<Arc<SearchConfig>>,
+}
+
+/// Configuration a block is searched with, published as a new version on every update.
+#[derive(Debug, Clone)]
+pub struct SearchConfig {
+    pub(crate) version: u64,
     pub(crate) contract: Bytecode,
     pub(crate) max_profit_ratio: u64,
     pub(crate) min_profit_ratio: u64,
-    pub(crate) route_paths: Vec<RoutePath>,
+    pub(crate) route_paths: Arc<Vec<RoutePath>>,
+    pub(crate) route_index: Arc<RouteIndex>,
+    /// Every indexed pool in both directions, the rate graph of the negative cycle search.
+    pub(crate) pool_hops: Arc<Vec<Hop>>,
+    /// Tokens every route starts and ends with, the sources of the negative cycle search.
+    pub(crate) beginning_tokens: Arc<HashSet<Address>>,
+    pub(crate) strategy: SearchStrategy,
+    pub(crate) native_token: Option<Address>,
+    pub(crate) priority_fee: u128,
+    pub(crate) block_time: u64,
+    pub(crate) coinbase: Option<Address>,
+    pub(crate) time_budget: Duration,
+    pub(crate) mempool_search: bool,
+    pub(crate) mempool_time_budget: Duration,
+    pub(crate) mempool_concurrency: usize,
+    pub(crate) reference_pools: Vec<ReferencePool>,
+    /// Swap fees of the Uniswap V2 forks by factory.
+    pub(crate) uniswap_v2_fees: Arc<UniswapV2Fees>,
+    pub(crate) simulation_threads: usize,
+    pub(crate) full_sweep_interval: u64,
+    /// Signs bundles for the found routes, when a keystore is configured.
+    pub(crate) executor: Option<Arc<Executor>>,
+    /// Submits the signed bundles, when relays are configured.
+    pub(crate) relays: Option<RelayClient>,
+    /// Picks the bid of every route out of its profit.
+    pub(crate) bidding: Arc<dyn BiddingPolicy>,
+    /// Settles the signed transactions, shared by every version.
+    pub(crate) tracker: Arc<InclusionTracker>,
+}
+
+impl SearchConfig {
+    /// Last block a bundle for `block_number` is submitted for.
+    pub(crate) fn last_target_block(&self, block_number: u64) -> u64 {
+        let target_blocks = self.relays.as_ref().map_or(1, RelayClient::target_blocks);
+        block_number + target_blocks - 1
+    }
 }
 
 #[derive(Debug, Clone, Args)]
@@ -19,7 +91,7 @@ pub struct SetupArgs {
     #[clap(long = "database-url", default_value = "")]
     pub database_url: String,
 
-    #[clap(long = "bytecode", default_value = "")]
+    #[clap(long = "bytecode", default_value = "")] // implementing `CONTRACT_ABI_VERSION`
     pub bytecode: String,
 
     #[clap(long = "socket-path", default_value = "/tmp/ipc_socket")]
@@ -30,30 +102,322 @@ pub struct SetupArgs {
 
     #[clap(long = "mint-profit", default_value = "500")] // 0.0005%
     pub min_profit: Option<u64>,
+
+    #[clap(long = "max-hops", default_value = "3")] // 2..=6
+    pub max_hops: Option<usize>,
+
+    #[clap(long = "max-candidates", default_value = "100000")]
+    pub max_candidates: Option<usize>,
+
+    #[clap(long = "strategy", value_enum, default_value_t = SearchStrategy::Enumeration)]
+    pub strategy: SearchStrategy,
+
+    #[clap(long = "native-token")] // wrapped native token, e.g. WETH, profits are gross without it
+    pub native_token: Option<Address>,
+
+    #[clap(long = "priority-fee", default_value = "0")] // wei
+    pub priority_fee: Option<u128>,
+
+    // <kind>:<pool>:<token>:<quote>, kind is v2, v3 or twap=<seconds>
+    #[clap(long = "reference-pool")]
+    pub reference_pools: Vec<ReferencePool>,
+
+    // <factory>:<bps>, swap fee of the pairs of a Uniswap V2 fork, 30 bps otherwise
+    #[clap(long = "v2-fee")]
+    pub uniswap_v2_fees: Vec<UniswapV2Fee>,
+
+    #[clap(long = "block-time", default_value = "12")] // seconds
+    pub block_time: Option<u64>,
+
+    #[clap(long = "coinbase")] // of the simulated blocks, defaults to the last block's
+    pub coinbase: Option<Address>,
+
+    #[clap(long = "time-budget")] // milliseconds per block, defaults to the block time
+    pub time_budget: Option<u64>,
+
+    #[clap(long = "mempool-search")] // search backruns of pending transactions
+    pub mempool_search: bool,
+
+    #[clap(long = "mempool-time-budget", default_value = "200")] // milliseconds per transaction
+    pub mempool_time_budget: Option<u64>,
+
+    #[clap(long = "mempool-concurrency", default_value = "4")] // transactions searched at once
+    pub mempool_concurrency: Option<usize>,
+
+    #[clap(long = "keystore")] // encrypted JSON keystore of the executor key
+    pub keystore: Option<PathBuf>,
+
+    #[clap(long = "keystore-password-file")]
+    pub keystore_password_file: Option<PathBuf>,
+
+    #[clap(long = "executor-contract")] // searcher contract on chain, signed bundles are emitted
+    pub executor_contract: Option<Address>,
+
+    #[clap(long = "gas-limit-margin", default_value = "20")] // percent over the execution gas
+    pub gas_limit_margin: Option<u64>,
+
+    // <url>[,<target blocks>], signed bundles are submitted to every relay
+    #[clap(long = "relay")]
+    pub relays: Vec<Relay>,
+
+    #[clap(long = "bid-policy", value_enum, default_value_t = BidPolicyKind::Fixed)]
+    pub bid_policy: BidPolicyKind,
+
+    #[clap(long = "bid-share", default_value = "0")] // bps of the net profit, min share if adjusted
+    pub bid_share: Option<u64>,
+
+    #[clap(long = "bid-max-share", default_value = "9000")] // bps, max share if adjusted
+    pub bid_max_share: Option<u64>,
+
+    #[clap(long = "bid-cap")] // wei, required by the capped policy
+    pub bid_cap: Option<U256>,
+
+    #[clap(long = "bid-min-samples", default_value = "10")] // submissions before adjusting a bid
+    pub bid_min_samples: Option<u64>,
+
+    #[clap(long = "max-datagram", default_value = "65536")] // bytes, larger messages are chunked
+    pub max_datagram: Option<usize>,
+
+    #[clap(long = "simulation-threads")] // defaults to the available parallelism
+    pub simulation_threads: Option<usize>,
+
+    #[clap(long = "full-sweep-interval", default_value = "100")] // blocks
+    pub full_sweep_interval: Option<u64>,
 }
 
 impl SearcherExtension {
     pub fn new(args: SetupArgs) -> Result<Self, Error> {
         let bytecode = args.bytecode.clone();
         let bytecode = Bytecode::new_raw_checked(Bytes(bytecode.into())).unwrap();
-        Ok(Self {
+        let defaults = CandidateLimits::default();
+        let candidate_limits = CandidateLimits::new(
+            args.max_hops.unwrap_or(defaults.max_hops),
+            args.max_candidates.unwrap_or(defaults.max_candidates)
+        )?;
+        let executor = match (args.keystore, args.executor_contract) {
+            (Some(keystore), Some(contract)) => {
+                let password_file = args.keystore_password_file.ok_or_else(|| {
+                    eyre!("--keystore-password-file is required with --keystore")
+                })?;
+                let margin = args.gas_limit_margin.unwrap_or(DEFAULT_GAS_LIMIT_MARGIN);
+                Some(Arc::new(
+                    Executor::from_keystore(&keystore, &password_file, contract, margin)?
+                ))
+            }
+            (None, None) => None,
+            _ => {
+                return Err(eyre!("--keystore and --executor-contract must be set together"));
+            }
+        };
+        // relays identify the searcher by the executor key
+        let relays = match &executor {
+            _ if args.relays.is_empty() => None,
+            Some(executor) => Some(RelayClient::new(args.relays, executor.signer().clone())),
+            None => {
+                return Err(eyre!("--relay requires --keystore and --executor-contract"));
+            }
+        };
+        let tracker = Arc::new(InclusionTracker::default());
+        let bid_share = args.bid_share.unwrap_or_default();
+        let bidding: Arc<dyn BiddingPolicy> = match args.bid_policy {
+            BidPolicyKind::Fixed => Arc::new(FixedShare::new(bid_share)),
+            BidPolicyKind::Capped => {
+                let cap = args.bid_cap.ok_or_else(|| {
+                    eyre!("--bid-policy capped requires --bid-cap")
+                })?;
+                Arc::new(CappedShare::new(bid_share, cap))
+            }
+            BidPolicyKind::Competition => Arc::new(CompetitionAdjusted::new(
+                bid_share,
+                args.bid_max_share.unwrap_or(9000),
+                args.bid_min_samples.unwrap_or(10),
+                tracker.history().clone()
+            )),
+        };
+        let block_time = args.block_time.unwrap_or(12);
+        let config = SearchConfig {
+            version: 0,
             contract: bytecode,
             max_profit_ratio: args.max_profit.unwrap_or(1000),
             min_profit_ratio: args.min_profit.unwrap_or(500),
-            route_paths: Vec::new(),
-        })
+            route_paths: Arc::new(Vec::new()),
+            route_index: Arc::new(RouteIndex::default()),
+            pool_hops: Arc::new(Vec::new()),
+            beginning_tokens: Arc::new(HashSet::new()),
+            strategy: args.strategy,
+            native_token: args.native_token,
+            priority_fee: args.priority_fee.unwrap_or_default(),
+            reference_pools: args.reference_pools,
+            uniswap_v2_fees: Arc::new(args.uniswap_v2_fees.into_iter().collect()),
+            block_time,
+            coinbase: args.coinbase,
+            time_budget: args.time_budget.map_or(
+                Duration::from_secs(block_time),
+                Duration::from_millis
+            ),
+            mempool_search: args.mempool_search,
+            mempool_time_budget: Duration::from_millis(args.mempool_time_budget.unwrap_or(200)),
+            mempool_concurrency: args.mempool_concurrency.unwrap_or(DEFAULT_CONCURRENCY),
+            simulation_threads: args.simulation_threads.unwrap_or_else(|| {
+                std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+            }),
+            full_sweep_interval: args.full_sweep_interval.unwrap_or(100).max(1),
+            executor,
+            relays,
+            bidding,
+            tracker,
+        };
+        let (config_tx, _) = watch::channel(Arc::new(config.clone()));
+        Ok(Self { config, candidate_limits, config_tx })
+    }
+
+    /// Subscribes to the configuration, the receiver sees the latest version at any time.
+    pub fn subscribe(&self) -> watch::Receiver<Arc<SearchConfig>> {
+        self.config_tx.subscribe()
+    }
+
+    pub fn config_version(&self) -> u64 {
+        self.config.version
+    }
+
+    /// Publishes the current configuration as a new version.
+    fn publish(&mut self) {
+        self.config.version += 1;
+        self.config_tx.send_replace(Arc::new(self.config.clone()));
     }
 
     pub fn update_contract(&mut self, bytecode: String) {
-        self.contract = Bytecode::new_raw_checked(Bytes(bytecode.into())).unwrap();
+        self.config.contract = Bytecode::new_raw_checked(Bytes(bytecode.into())).unwrap();
+        self.publish();
     }
 
     pub fn update_profit_rate(&mut self, min_profit: Option<u64>, max_profit: Option<u64>) {
-        self.min_profit_ratio = min_profit.unwrap_or(self.min_profit_ratio);
-        self.max_profit_ratio = max_profit.unwrap_or(self.max_profit_ratio);
+        self.config.min_profit_ratio = min_profit.unwrap_or(self.config.min_profit_ratio);
+        self.config.max_profit_ratio = max_profit.unwrap_or(self.config.max_profit_ratio);
+        self.publish();
+    }
+
+    pub fn candidate_limits(&self) -> CandidateLimits {
+        self.candidate_limits
+    }
+
+    pub fn update_candidate_limits(
+        &mut self,
+        max_hops: Option<usize>,
+        max_candidates: Option<usize>
+    ) -> Result<(), Error> {
+        self.candidate_limits = CandidateLimits::new(
+            max_hops.unwrap_or(self.candidate_limits.max_hops),
+            max_candidates.unwrap_or(self.candidate_limits.max_candidates)
+        )?;
+        Ok(())
+    }
+
+    pub fn update_route_paths(
+        &mut self,
+        route_paths: Vec<RoutePath>,
+        pool_hops: Vec<Hop>,
+        beginning_tokens: HashSet<Address>
+    ) {
+        self.config.route_index = Arc::new(RouteIndex::new(&route_paths));
+        self.config.route_paths = Arc::new(route_paths);
+        self.config.pool_hops = Arc::new(pool_hops);
+        self.config.beginning_tokens = Arc::new(beginning_tokens);
+        self.publish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use alloy_primitives::Address;
+    use clap::Parser;
+
+    use crate::strategy::path_finding::{
+        candidate::CandidateLimits,
+        types::{ Hop, RoutePath },
+    };
+
+    use super::{ SearcherExtension, SetupArgs };
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: SetupArgs,
+    }
+
+    fn extension() -> SearcherExtension {
+        SearcherExtension::new(Cli::parse_from(["searcher"]).args).unwrap()
+    }
+
+    fn route() -> RoutePath {
+        let hop = |src_token: u8, dst_token: u8| Hop {
+            dexType: 0,
+            dex: Address::with_last_byte(1),
+            srcToken: Address::with_last_byte(src_token),
+            dstToken: Address::with_last_byte(dst_token),
+        };
+        RoutePath { hops: vec![hop(0xaa, 0xbb), hop(0xbb, 0xaa)] }
+    }
+
+    #[test]
+    fn publishes_every_update_as_a_new_version() {
+        let mut extension = extension();
+        let mut config = extension.subscribe();
+        assert_eq!(config.borrow_and_update().version, 0);
+
+        extension.update_profit_rate(Some(100), None);
+        assert!(config.has_changed().unwrap());
+        {
+            let latest = config.borrow_and_update();
+            assert_eq!(latest.version, 1);
+            assert_eq!((latest.min_profit_ratio, latest.max_profit_ratio), (100, 1000));
+        }
+
+        extension.update_contract("6001".to_string());
+        let route = route();
+        let beginning_tokens = HashSet::from([route.hops[0].srcToken]);
+        extension.update_route_paths(
+            vec![route.clone()],
+            route.hops.clone(),
+            beginning_tokens.clone()
+        );
+
+        let latest = config.borrow_and_update();
+        assert_eq!(latest.version, 3);
+        assert_eq!(extension.config_version(), 3);
+        assert_eq!(latest.route_paths.as_slice(), [route.clone()]);
+        assert_eq!(latest.pool_hops.as_slice(), route.hops.as_slice());
+        assert_eq!(*latest.beginning_tokens, beginning_tokens);
+    }
+
+    #[test]
+    fn subscribers_start_from_the_latest_version() {
+        let mut extension = extension();
+        // publishing without any subscriber keeps the configuration
+        extension.update_profit_rate(None, Some(2000));
+        extension.update_profit_rate(None, Some(3000));
+
+        let mut config = extension.subscribe();
+        assert!(!config.has_changed().unwrap());
+        let latest = config.borrow_and_update();
+        assert_eq!(latest.version, 2);
+        assert_eq!(latest.max_profit_ratio, 3000);
     }
 
-    pub fn update_route_paths(&mut self, route_paths: Vec<RoutePath>) {
-        self.route_paths = route_paths;
+    #[test]
+    fn keeps_candidate_limits_out_of_the_published_configuration() {
+        let mut extension = extension();
+        let config = extension.subscribe();
+
+        extension.update_candidate_limits(Some(4), None).unwrap();
+        assert_eq!(extension.candidate_limits(), CandidateLimits::new(4, 100_000).unwrap());
+        assert!(extension.update_candidate_limits(Some(1), Some(10)).is_err());
+        assert_eq!(extension.candidate_limits().max_hops, 4);
+
+        // the limits only apply to the next enumeration, published with its route paths
+        assert!(!config.has_changed().unwrap());
+        assert_eq!(extension.config_version(), 0);
     }
 }
@@ -0,0 +1,425 @@
+use std::{ collections::HashMap, sync::Arc };
+
+use alloy_consensus::Transaction;
+use alloy_eips::{ eip2718::Encodable2718, BlockNumHash };
+use alloy_primitives::{ keccak256, Address, Bytes, U256 };
+use eyre::{ eyre, Result };
+use reth_chainspec::{ EthChainSpec, EthereumHardforks };
+use reth_provider::{ BlockNumReader, HeaderProvider, StateProviderFactory };
+use reth_revm::{
+    context::{ result::ResultAndState, TxEnv },
+    database::StateProviderDatabase,
+    db::{ states::bundle_state::BundleRetention, State },
+    Context,
+    DatabaseCommit,
+    ExecuteEvm,
+    MainBuilder,
+    MainContext,
+};
+use reth_tracing::tracing::{ debug, info, warn };
+use reth_transaction_pool::{ PoolTransaction, TransactionPool, ValidPoolTransaction };
+use reth::tasks::TaskExecutor;
+use tokio::sync::{ watch, Semaphore };
+
+use crate::{
+    oracle::PriceOracle,
+    output::{ send, OutputMessage, OutputSocket },
+    relay,
+    router::{ DecodedSwap, RouterDecoder },
+    strategy::path_finding::{
+        budget::SearchBudget,
+        env::SimulationEnv,
+        gas::GasPricing,
+        index::RouteIndex,
+        strategy::Strategy,
+        types::Selection,
+        PathFinder,
+    },
+    SearchConfig,
+};
+
+/// Default number of pending transactions searched at once.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Searches backruns of pending transactions, off the ExEx task, on tasks of the node's executor.
+///
+/// Calls of known routers that don't swap through a pool of the route index are skipped. Every
+/// other new pending transaction is executed on top of the latest block. When it changes the
+/// storage of pools in the route index, the routes through those pools are searched on the state
+/// it leaves, within the mempool time budget, and the opportunities are emitted with its hash.
+pub struct MempoolSearcher<Pool, Provider, ChainSpec> {
+    pool: Pool,
+    provider: Provider,
+    chain_spec: Arc<ChainSpec>,
+    config_rx: watch::Receiver<Arc<SearchConfig>>,
+    sock: Arc<OutputSocket>,
+    decoder: RouterDecoder,
+    executor: TaskExecutor,
+    /// Largest number of transactions searched at once.
+    concurrency: usize,
+}
+
+impl<Pool, Provider, ChainSpec> MempoolSearcher<Pool, Provider, ChainSpec>
+    where
+        Pool: TransactionPool<Transaction: PoolTransaction<Consensus: Encodable2718>> + 'static,
+        Provider: StateProviderFactory + HeaderProvider + BlockNumReader + Clone + 'static,
+        ChainSpec: EthChainSpec + EthereumHardforks + 'static
+{
+    pub fn new(
+        pool: Pool,
+        provider: Provider,
+        chain_spec: Arc<ChainSpec>,
+        config_rx: watch::Receiver<Arc<SearchConfig>>,
+        sock: Arc<OutputSocket>,
+        executor: TaskExecutor
+    ) -> Self {
+        Self {
+            pool,
+            provider,
+            chain_spec,
+            config_rx,
+            sock,
+            decoder: RouterDecoder::default(),
+            executor,
+            concurrency: DEFAULT_CONCURRENCY,
+        }
+    }
+
+    /// Sets the largest number of transactions searched at once.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Sets the routers whose calls are decoded instead of simulated to find the pools they swap
+    /// through.
+    pub fn with_decoder(mut self, decoder: RouterDecoder) -> Self {
+        self.decoder = decoder;
+        self
+    }
+
+    /// Follows the pending transactions of the pool until it shuts down, searching the backruns
+    /// of up to `concurrency` transactions at once.
+    pub async fn run(self) {
+        let mut pending = self.pool.new_pending_pool_transactions_listener();
+        let slots = Arc::new(Semaphore::new(self.concurrency));
+        let searcher = Arc::new(self);
+
+        while let Some(event) = pending.recv().await {
+            // new transactions queue up in the listener while every slot is busy
+            let Ok(slot) = slots.clone().acquire_owned().await else {
+                break;
+            };
+            let task = {
+                let searcher = searcher.clone();
+                async move {
+                    searcher.backrun(event.transaction).await;
+                    drop(slot);
+                }
+            };
+            searcher.executor.spawn(task);
+        }
+    }
+
+    /// Searches the backruns of `tx`, then emits and submits them.
+    async fn backrun(&self, tx: Arc<ValidPoolTransaction<Pool::Transaction>>) {
+        let config = self.config_rx.borrow().clone();
+        let tx_hash = *tx.hash();
+        // transactions that were mined or replaced while waiting are not worth a search
+        if config.contract.is_empty() || !self.pool.contains(&tx_hash) {
+            return;
+        }
+        let call = &tx.transaction;
+        let (to, input, value) = (call.to(), call.input(), call.value());
+        if !worth_searching(&self.decoder, &config.route_index, to, input, value) {
+            return;
+        }
+
+        // the target leads the signed bundles of its backruns
+        let target = config.executor
+            .is_some()
+            .then(|| Bytes::from(tx.transaction.clone_into_consensus().inner().encoded_2718()));
+
+        let search = tokio::task::spawn_blocking({
+            let (provider, chain_spec, config) =
+                (self.provider.clone(), self.chain_spec.clone(), config.clone());
+            move || search_backrun(&provider, chain_spec.as_ref(), &tx, &config)
+        });
+        let backrun = match search.await.map_err(eyre::Error::from).and_then(|r| r) {
+            Ok(Some(found)) => found,
+            Ok(None) => return,
+            Err(err) => {
+                debug!(target: "searcher_mempool", %tx_hash, %err, "backrun search failed");
+                return;
+            }
+        };
+        let Backrun { block, chain_id, nonce, selection } = backrun;
+        info!(
+            target: "searcher_mempool",
+            %tx_hash,
+            block = block.number,
+            interrupted = selection.interrupted,
+            optimal = selection.optimal_paths.len(),
+            rejected = selection.rejected.len(),
+            "backrun route paths simulated"
+        );
+
+        let opportunities = selection.opportunities();
+        if !opportunities.is_empty() {
+            send(
+                &self.sock,
+                OutputMessage::backrun(block, config.version, tx_hash, opportunities)
+            ).await;
+        }
+        // a single bundle for the best route: every route spends the current nonce, so at most
+        // one of them could land and the others would only compete with it
+        let best = selection.optimal_paths.first();
+        let (Some(executor), Some(nonce), Some(target), Some(path)) =
+            (&config.executor, nonce, target, best)
+        else {
+            return;
+        };
+        match executor.sign_route(path, chain_id, nonce, config.priority_fee) {
+            Ok(raw) => {
+                config.tracker.track(
+                    keccak256(&raw),
+                    path,
+                    config.version,
+                    block.number + 1,
+                    config.last_target_block(block.number + 1)
+                );
+                let txs = vec![target, raw];
+                if let Some(relays) = &config.relays {
+                    relay::submit(relays, block.number + 1, txs.clone());
+                }
+                let bundle = OutputMessage::bundle(
+                    block.number + 1,
+                    config.version,
+                    Some(tx_hash),
+                    txs
+                );
+                send(&self.sock, bundle).await;
+            }
+            Err(err) => {
+                warn!(target: "searcher_mempool", %err, "failed to sign route path");
+            }
+        }
+    }
+}
+
+/// Whether the backrun of a call to `to` is worth searching. Router calls only matter when they
+/// swap through a pool of the route index, other calls are executed to find the pools they touch.
+fn worth_searching(
+    decoder: &RouterDecoder,
+    route_index: &RouteIndex,
+    to: Option<Address>,
+    input: &[u8],
+    value: U256
+) -> bool {
+    let Some(swaps) = to.and_then(|to| decoder.decode(to, input, value)) else {
+        return true;
+    };
+    swaps
+        .iter()
+        .flat_map(DecodedSwap::pools)
+        .any(|pool| route_index.contains(pool))
+}
+
+/// Routes searched behind a pending transaction.
+struct Backrun {
+    /// Block the transaction was executed on top of.
+    block: BlockNumHash,
+    chain_id: u64,
+    /// Nonce of the executor after the transaction, if an executor is configured.
+    nonce: Option<u64>,
+    selection: Selection,
+}
+
+/// Executes `tx` on top of the latest block and searches the routes through the pools it
+/// touched on the resulting state. Returns `None` when the transaction fails or touches no pool
+/// of the route index.
+fn search_backrun<Provider, ChainSpec, T>(
+    provider: &Provider,
+    chain_spec: &ChainSpec,
+    tx: &ValidPoolTransaction<T>,
+    config: &SearchConfig
+) -> Result<Option<Backrun>>
+    where
+        Provider: StateProviderFactory + HeaderProvider + BlockNumReader,
+        ChainSpec: EthChainSpec + EthereumHardforks,
+        T: PoolTransaction
+{
+    let budget = SearchBudget::new(config.mempool_time_budget);
+    let number = provider.best_block_number()?;
+    let header = provider
+        .sealed_header(number)?
+        .ok_or_else(|| eyre!("missing header of block {number}"))?;
+    let block = BlockNumHash::new(number, header.hash());
+    // the transaction and its backrun land in the next block
+    let env = SimulationEnv::next_block(
+        chain_spec,
+        header.header(),
+        config.block_time,
+        config.coinbase
+    );
+
+    let mut state = State::builder()
+        .with_database(StateProviderDatabase::new(provider.history_by_block_hash(block.hash)?))
+        .with_bundle_update()
+        .build();
+    let ResultAndState { result, state: changes } = Context::mainnet()
+        .with_db(&mut state)
+        .with_cfg(env.cfg.clone())
+        .with_block(env.block.clone())
+        .build_mainnet()
+        .transact(tx_env(&tx.transaction, tx.sender()))
+        .map_err(|err| eyre!("{err}"))?;
+    if !result.is_success() {
+        return Ok(None);
+    }
+
+    let touched_pools = changes
+        .iter()
+        .filter(|(_, account)| account.storage.values().any(|slot| slot.is_changed()))
+        .map(|(address, _)| address);
+    let candidates = config.route_index
+        .touched_routes(touched_pools)
+        .into_iter()
+        .map(|index| config.route_paths[index].clone())
+        .collect::<Vec<_>>();
+    if candidates.is_empty() {
+        return Ok(None);
+    }
+
+    state.commit(changes);
+    state.merge_transitions(BundleRetention::PlainState);
+    let bundle = state.take_bundle();
+
+    let gas_price = U256::from(env.block.basefee) + U256::from(config.priority_fee);
+    let chain_id = env.cfg.chain_id;
+    let finder = PathFinder::new(
+        provider.history_by_block_hash(block.hash)?,
+        &bundle,
+        HashMap::new(),
+        config.contract.clone()
+    )
+        .with_env(env)
+        .with_uniswap_v2_fees(config.uniswap_v2_fees.clone());
+    let gas_pricing = match config.native_token {
+        Some(native_token) => {
+            let oracle = PriceOracle::load(
+                &mut finder.simulator(),
+                block,
+                Some(native_token),
+                &config.reference_pools
+            );
+            GasPricing::new(gas_price, Arc::new(oracle))
+        }
+        None => GasPricing::gross(gas_price),
+    };
+    let nonce = config.executor
+        .as_ref()
+        .map(|executor| finder.nonce(executor.address()))
+        .transpose()?;
+    // the touched routes are few, the negative cycle search needs the whole rate graph
+    let selection = finder
+        .with_threads(config.simulation_threads)
+        .with_gas_pricing(gas_pricing)
+        .with_budget(budget)
+        .with_bidding(config.bidding.clone())
+        .filter_candidates(candidates, config.max_profit_ratio, config.min_profit_ratio)?;
+
+    Ok(Some(Backrun { block, chain_id, nonce, selection }))
+}
+
+/// Transaction environment of a pending transaction sent by `sender`.
+fn tx_env<T: Transaction>(tx: &T, sender: Address) -> TxEnv {
+    TxEnv {
+        tx_type: tx.ty(),
+        caller: sender,
+        gas_limit: tx.gas_limit(),
+        gas_price: tx.max_fee_per_gas(),
+        kind: tx.kind(),
+        value: tx.value(),
+        data: tx.input().clone(),
+        nonce: tx.nonce(),
+        chain_id: tx.chain_id(),
+        access_list: tx.access_list().cloned().unwrap_or_default(),
+        gas_priority_fee: tx.max_priority_fee_per_gas(),
+        blob_hashes: tx.blob_versioned_hashes().map(<[_]>::to_vec).unwrap_or_default(),
+        max_fee_per_blob_gas: tx.max_fee_per_blob_gas().unwrap_or_default(),
+        authorization_list: tx.authorization_list().map(<[_]>::to_vec).unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{ address, Address, U256 };
+    use alloy_sol_types::SolCall;
+    use searcher_reth_repository::types::dex_type;
+
+    use crate::{
+        router::{
+            uniswap_v2::IUniswapV2Router02::swapExactTokensForTokensCall,
+            uniswap_v2_pair,
+            RouterDecoder,
+            UNISWAP_V2_FACTORY,
+        },
+        strategy::path_finding::{ index::RouteIndex, types::{ Hop, RoutePath } },
+    };
+
+    use super::worth_searching;
+
+    const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+    const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    const DAI: Address = address!("6B175474E89094C44Da98b954EedeAC495271d0F");
+
+    const UNISWAP_V2_ROUTER: Address = address!("7a250d5630B4cF539739dF2C5dAcb4c659F2488D");
+
+    /// Routes through the USDC/WETH pair only.
+    fn route_index() -> RouteIndex {
+        let pair = uniswap_v2_pair(&UNISWAP_V2_FACTORY, USDC, WETH);
+        let hop = |src_token: Address, dst_token: Address| Hop {
+            dexType: dex_type::UNISWAP_V2,
+            dex: pair,
+            srcToken: src_token,
+            dstToken: dst_token,
+        };
+        RouteIndex::new(&[RoutePath { hops: vec![hop(USDC, WETH), hop(WETH, USDC)] }])
+    }
+
+    fn swap(path: Vec<Address>) -> Vec<u8> {
+        swapExactTokensForTokensCall {
+            amountIn: U256::from(1_000_000),
+            amountOutMin: U256::ZERO,
+            path,
+            to: Address::ZERO,
+            deadline: U256::MAX,
+        }.abi_encode()
+    }
+
+    fn worth(to: Option<Address>, input: &[u8]) -> bool {
+        worth_searching(&RouterDecoder::default(), &route_index(), to, input, U256::ZERO)
+    }
+
+    #[test]
+    fn searches_router_swaps_through_indexed_pools() {
+        assert!(worth(Some(UNISWAP_V2_ROUTER), &swap(vec![USDC, WETH])));
+        assert!(worth(Some(UNISWAP_V2_ROUTER), &swap(vec![DAI, USDC, WETH])));
+    }
+
+    #[test]
+    fn skips_router_swaps_elsewhere() {
+        assert!(!worth(Some(UNISWAP_V2_ROUTER), &swap(vec![DAI, WETH])));
+        // a path of a single token swaps through no pool
+        assert!(!worth(Some(UNISWAP_V2_ROUTER), &swap(vec![USDC])));
+    }
+
+    #[test]
+    fn simulates_calls_it_cannot_decode() {
+        // other contracts, router calls that are not swaps and contract creations
+        assert!(worth(Some(Address::with_last_byte(1)), &swap(vec![DAI, WETH])));
+        assert!(worth(Some(UNISWAP_V2_ROUTER), &[0xde, 0xad, 0xbe, 0xef]));
+        assert!(worth(None, &swap(vec![DAI, WETH])));
+    }
+}
@@ -0,0 +1,398 @@
+use std::{ collections::HashMap, str::FromStr };
+
+use alloy_eips::BlockNumHash;
+use alloy_primitives::{ Address, U256 };
+use alloy_sol_types::{ sol, SolCall };
+use eyre::{ eyre, Error, Result };
+use searcher_reth_repository::types::dex_type;
+
+use crate::{
+    amm::{
+        uniswap_v2::UniswapV2Pool,
+        uniswap_v3::{ math::{ get_sqrt_ratio_at_tick, mul_div }, UniswapV3Pool },
+        PoolSource,
+    },
+    strategy::path_finding::{ gas::PriceSource, types::Hop },
+};
+
+sol! {
+    interface IUniswapV3Oracle {
+        function observe(uint32[] secondsAgos)
+            external
+            view
+            returns (int56[] tickCumulatives, uint160[] secondsPerLiquidityCumulativeX128s);
+    }
+}
+
+/// Rates are 1e18 fixed-point amounts of the quote token per smallest unit of the base token.
+pub const RATE_PRECISION: u64 = 1_000_000_000_000_000_000;
+
+/// How a reference pool is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceKind {
+    /// Uniswap V2 reserves.
+    UniswapV2,
+    /// Uniswap V3 `slot0` spot price.
+    UniswapV3,
+    /// Uniswap V3 time-weighted average tick over `observe([seconds, 0])`.
+    UniswapV3Twap { seconds: u32 },
+}
+
+/// A pool pricing `token` in `quote`, usually a `Priority::Beginning` token.
+///
+/// Parsed from `<kind>:<pool>:<token>:<quote>` where kind is `v2`, `v3` or `twap=<seconds>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferencePool {
+    pub kind: ReferenceKind,
+    pub pool: Address,
+    pub token: Address,
+    pub quote: Address,
+}
+
+impl FromStr for ReferencePool {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parts = s.split(':').collect::<Vec<_>>();
+        let [kind, pool, token, quote] = parts.as_slice() else {
+            return Err(eyre!("expected <kind>:<pool>:<token>:<quote>, got {s}"));
+        };
+        let kind = match *kind {
+            "v2" => ReferenceKind::UniswapV2,
+            "v3" => ReferenceKind::UniswapV3,
+            kind => {
+                let seconds = kind
+                    .strip_prefix("twap=")
+                    .ok_or_else(|| eyre!("unknown reference pool kind {kind}"))?
+                    .parse()?;
+                ReferenceKind::UniswapV3Twap { seconds }
+            }
+        };
+        Ok(Self { kind, pool: pool.parse()?, token: token.parse()?, quote: quote.parse()? })
+    }
+}
+
+impl ReferencePool {
+    /// Rate of `token` in `quote` at the state of `source`.
+    fn rate<S: PoolSource + ?Sized>(&self, source: &mut S) -> Result<Option<U256>> {
+        let wad = U256::from(RATE_PRECISION);
+        match self.kind {
+            ReferenceKind::UniswapV2 => {
+                let pool = UniswapV2Pool::load(source, self.pool)?;
+                let (Some(reserve_token), Some(reserve_quote)) = (
+                    pool.reserve_of(self.token),
+                    pool.reserve_of(self.quote),
+                ) else {
+                    return Ok(None);
+                };
+                Ok(mul_div(reserve_quote, wad, reserve_token))
+            }
+            ReferenceKind::UniswapV3 => {
+                let pool = UniswapV3Pool::load(source, &self.hop(dex_type::UNISWAP_V3))?;
+                Ok(self.rate_at_sqrt_price(pool.token0, pool.sqrt_price_x96))
+            }
+            ReferenceKind::UniswapV3Twap { seconds } => {
+                let call = IUniswapV3Oracle::observeCall { secondsAgos: vec![seconds, 0] };
+                let output = source.call(self.pool, call.abi_encode().into())?;
+                let observed = IUniswapV3Oracle::observeCall::abi_decode_returns(&output)?;
+                let [start, end] = observed.tickCumulatives.as_slice() else {
+                    return Ok(None);
+                };
+                if seconds == 0 {
+                    return Ok(None);
+                }
+
+                // OracleLibrary.consult rounds the mean tick towards negative infinity
+                let delta = i64::try_from(*end)? - i64::try_from(*start)?;
+                let mut tick = delta / i64::from(seconds);
+                if delta < 0 && delta % i64::from(seconds) != 0 {
+                    tick -= 1;
+                }
+                let Some(sqrt_price) = get_sqrt_ratio_at_tick(tick as i32) else {
+                    return Ok(None);
+                };
+                let token0 = self.token.min(self.quote);
+                Ok(self.rate_at_sqrt_price(token0, sqrt_price))
+            }
+        }
+    }
+
+    /// Rate of `token` in `quote` for a V3 price of token1 per token0 of `(sqrt_price / 2^96)^2`.
+    fn rate_at_sqrt_price(&self, token0: Address, sqrt_price: U256) -> Option<U256> {
+        let wad = U256::from(RATE_PRECISION);
+        let q96 = U256::from(1) << 96;
+        if self.token == token0 {
+            mul_div(mul_div(sqrt_price, sqrt_price, q96)?, wad, q96)
+        } else {
+            mul_div(mul_div(wad, q96, sqrt_price)?, q96, sqrt_price)
+        }
+    }
+
+    fn hop(&self, dex_type: u8) -> Hop {
+        Hop { dexType: dex_type, dex: self.pool, srcToken: self.token, dstToken: self.quote }
+    }
+}
+
+/// Token prices of one block, derived from the reference pools.
+#[derive(Debug, Clone, Default)]
+pub struct PriceOracle {
+    /// Block the prices were read at.
+    pub block: BlockNumHash,
+    wrapped_native: Option<Address>,
+    /// token -> (quote, rate)
+    rates: HashMap<Address, (Address, U256)>,
+}
+
+impl PriceOracle {
+    /// Reads every reference pool at the state of `source`. Pools that can't be read are skipped.
+    pub fn load<S: PoolSource + ?Sized>(
+        source: &mut S,
+        block: BlockNumHash,
+        wrapped_native: Option<Address>,
+        reference_pools: &[ReferencePool]
+    ) -> Self {
+        let rates = reference_pools
+            .iter()
+            .filter_map(|reference| {
+                let rate = reference.rate(source).ok().flatten()?;
+                (!rate.is_zero()).then_some((reference.token, (reference.quote, rate)))
+            })
+            .collect();
+        Self { block, wrapped_native, rates }
+    }
+
+    /// Rate of `token` in the token its quote chain ends at, following every reference pool from
+    /// a token to its quote. Each token has one reference rate, and the walk stops after as many
+    /// steps as there are rates, so a cycle of reference pools can't loop forever.
+    fn resolve(&self, token: Address) -> (Address, U256) {
+        let wad = U256::from(RATE_PRECISION);
+        let (mut anchor, mut rate) = (token, wad);
+        for _ in 0..self.rates.len() {
+            let Some((quote, next)) = self.rates.get(&anchor) else {
+                break;
+            };
+            rate = mul_div(rate, *next, wad).unwrap_or_default();
+            anchor = *quote;
+        }
+        (anchor, rate)
+    }
+
+    /// Converts `amount` of `from` into `to`, `None` if they don't share a quote chain.
+    pub fn convert(&self, from: Address, to: Address, amount: U256) -> Option<U256> {
+        if from == to {
+            return Some(amount);
+        }
+        let (from_anchor, from_rate) = self.resolve(from);
+        let (to_anchor, to_rate) = self.resolve(to);
+        if from_anchor != to_anchor || to_rate.is_zero() {
+            return None;
+        }
+        mul_div(amount, from_rate, to_rate)
+    }
+
+    /// Price of one unit of `token` in `quote`, as a [`RATE_PRECISION`] fixed-point rate.
+    pub fn price(&self, token: Address, quote: Address) -> Option<U256> {
+        self.convert(token, quote, U256::from(RATE_PRECISION))
+    }
+}
+
+impl PriceSource for PriceOracle {
+    fn native_to_token(&self, token: Address, amount: U256) -> Option<U256> {
+        self.convert(self.wrapped_native?, token, amount)
+    }
+
+    fn token_to_native(&self, token: Address, amount: U256) -> Option<U256> {
+        self.convert(token, self.wrapped_native?, amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use alloy_eips::BlockNumHash;
+    use alloy_primitives::{ aliases::I56, Address, Bytes, U256 };
+    use alloy_sol_types::SolCall;
+    use eyre::{ eyre, Result };
+
+    use crate::{
+        amm::{
+            uniswap_v2::{ RESERVES_SLOT, TOKEN0_SLOT, TOKEN1_SLOT },
+            uniswap_v3::math::get_sqrt_ratio_at_tick,
+            PoolSource,
+        },
+        strategy::path_finding::gas::PriceSource,
+    };
+
+    use super::{
+        IUniswapV3Oracle,
+        PriceOracle,
+        ReferenceKind,
+        ReferencePool,
+        RATE_PRECISION,
+    };
+
+    const WETH: Address = Address::with_last_byte(0xaa);
+    const USDC: Address = Address::with_last_byte(0xbb);
+    const TOKEN: Address = Address::with_last_byte(0xcc);
+    const WETH_USDC: Address = Address::with_last_byte(1);
+    const TOKEN_USDC: Address = Address::with_last_byte(2);
+    const ORACLE: Address = Address::with_last_byte(3);
+
+    const ETHER: u128 = 1_000_000_000_000_000_000;
+
+    /// Uniswap V2 pairs in storage and V3 `observe` outputs.
+    #[derive(Default)]
+    struct Pools {
+        slots: HashMap<(Address, U256), U256>,
+        observations: HashMap<Address, Bytes>,
+    }
+
+    impl Pools {
+        fn add_pair(&mut self, pair: Address, reserves: [(Address, u128); 2]) {
+            let [(token0, reserve0), (token1, reserve1)] = reserves;
+            let word = |token: Address| U256::from_be_slice(token.as_slice());
+            let packed = U256::from(reserve0) | (U256::from(reserve1) << 112);
+            self.slots.insert((pair, U256::from(TOKEN0_SLOT)), word(token0));
+            self.slots.insert((pair, U256::from(TOKEN1_SLOT)), word(token1));
+            self.slots.insert((pair, U256::from(RESERVES_SLOT)), packed);
+        }
+
+        fn add_observation(&mut self, pool: Address, start: i64, end: i64) {
+            let tick_cumulatives = [start, end].map(|tick| I56::try_from(tick).unwrap());
+            let output = IUniswapV3Oracle::observeCall::abi_encode_returns(
+                &IUniswapV3Oracle::observeReturn {
+                    tickCumulatives: tick_cumulatives.to_vec(),
+                    secondsPerLiquidityCumulativeX128s: vec![Default::default(); 2],
+                }
+            );
+            self.observations.insert(pool, output.into());
+        }
+    }
+
+    impl PoolSource for Pools {
+        fn storage(&mut self, address: Address, slot: U256) -> Result<U256> {
+            Ok(self.slots.get(&(address, slot)).copied().unwrap_or_default())
+        }
+
+        fn call(&mut self, address: Address, _input: Bytes) -> Result<Bytes> {
+            self.observations.get(&address).cloned().ok_or_else(|| eyre!("no code at {address}"))
+        }
+
+        fn timestamp(&mut self) -> u64 {
+            0
+        }
+    }
+
+    fn v2(pool: Address, token: Address, quote: Address) -> ReferencePool {
+        ReferencePool { kind: ReferenceKind::UniswapV2, pool, token, quote }
+    }
+
+    fn twap(seconds: u32) -> ReferencePool {
+        ReferencePool {
+            kind: ReferenceKind::UniswapV3Twap { seconds },
+            pool: ORACLE,
+            token: WETH,
+            quote: USDC,
+        }
+    }
+
+    /// 1 WETH = 2000 USDC and 1 TOKEN = 1 USDC, with 6 decimals for USDC.
+    fn oracle() -> PriceOracle {
+        let mut pools = Pools::default();
+        pools.add_pair(WETH_USDC, [(WETH, 1_000 * ETHER), (USDC, 2_000_000_000_000)]);
+        pools.add_pair(TOKEN_USDC, [(USDC, 1_000_000_000), (TOKEN, 1_000 * ETHER)]);
+        PriceOracle::load(
+            &mut pools,
+            BlockNumHash::default(),
+            Some(WETH),
+            &[v2(WETH_USDC, WETH, USDC), v2(TOKEN_USDC, TOKEN, USDC)]
+        )
+    }
+
+    #[test]
+    fn parses_reference_pools() {
+        let pool = format!("{WETH_USDC}:{WETH}:{USDC}");
+        assert_eq!(
+            format!("v2:{pool}").parse::<ReferencePool>().unwrap(),
+            v2(WETH_USDC, WETH, USDC)
+        );
+        assert_eq!(
+            format!("twap=1800:{pool}").parse::<ReferencePool>().unwrap().kind,
+            ReferenceKind::UniswapV3Twap { seconds: 1800 }
+        );
+        assert!(format!("v4:{pool}").parse::<ReferencePool>().is_err());
+        assert!(format!("twap=-1:{pool}").parse::<ReferencePool>().is_err());
+        assert!(format!("v2:{WETH_USDC}:{WETH}").parse::<ReferencePool>().is_err());
+    }
+
+    #[test]
+    fn reads_rates_from_reserves() {
+        let oracle = oracle();
+
+        // 2000 USDC per WETH, 1 USDC per TOKEN, in the smallest units
+        assert_eq!(oracle.price(WETH, USDC), Some(U256::from(2_000_000_000u64)));
+        assert_eq!(oracle.price(TOKEN, USDC), Some(U256::from(1_000_000u64)));
+    }
+
+    #[test]
+    fn converts_through_a_shared_quote() {
+        let oracle = oracle();
+        let ether = U256::from(ETHER);
+
+        assert_eq!(oracle.native_to_token(TOKEN, ether), Some(ether * U256::from(2_000)));
+        assert_eq!(oracle.token_to_native(TOKEN, ether * U256::from(2_000)), Some(ether));
+        assert_eq!(oracle.native_to_token(WETH, ether), Some(ether));
+        assert_eq!(oracle.native_to_token(Address::ZERO, ether), None);
+    }
+
+    #[test]
+    fn prices_nothing_without_a_wrapped_native_token() {
+        let oracle = PriceOracle { wrapped_native: None, ..oracle() };
+
+        assert_eq!(oracle.native_to_token(TOKEN, U256::from(ETHER)), None);
+        assert_eq!(oracle.token_to_native(TOKEN, U256::from(ETHER)), None);
+    }
+
+    #[test]
+    fn skips_unreadable_and_cyclic_references() {
+        let mut pools = Pools::default();
+        pools.add_pair(WETH_USDC, [(WETH, ETHER), (USDC, ETHER)]);
+        let oracle = PriceOracle::load(
+            &mut pools,
+            BlockNumHash::default(),
+            Some(WETH),
+            &[v2(WETH_USDC, WETH, USDC), v2(WETH_USDC, USDC, WETH), twap(60)]
+        );
+
+        // WETH -> USDC -> WETH never reaches an anchor outside of the cycle
+        assert_eq!(oracle.rates.len(), 2);
+        assert_eq!(oracle.price(WETH, TOKEN), None);
+    }
+
+    fn twap_rate(start: i64, end: i64, seconds: u32) -> Option<U256> {
+        let mut pools = Pools::default();
+        pools.add_observation(ORACLE, start, end);
+        twap(seconds).rate(&mut pools).unwrap()
+    }
+
+    fn rate_at_tick(tick: i32) -> Option<U256> {
+        twap(1).rate_at_sqrt_price(WETH, get_sqrt_ratio_at_tick(tick)?)
+    }
+
+    #[test]
+    fn averages_ticks_over_the_twap_window() {
+        assert_eq!(twap_rate(1_000, 1_000, 10), Some(U256::from(RATE_PRECISION)));
+        assert_eq!(twap_rate(0, 200, 10), rate_at_tick(20));
+        assert_eq!(twap_rate(0, 15, 10), rate_at_tick(1));
+        assert_eq!(twap_rate(0, 200, 0), None);
+    }
+
+    #[test]
+    fn rounds_negative_mean_ticks_down() {
+        assert_eq!(twap_rate(0, -200, 10), rate_at_tick(-20));
+        assert_eq!(twap_rate(0, -15, 10), rate_at_tick(-2));
+        assert_eq!(twap_rate(100, 85, 10), rate_at_tick(-2));
+        assert_ne!(rate_at_tick(-2), rate_at_tick(-1));
+    }
+}
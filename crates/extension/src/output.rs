@@ -0,0 +1,107 @@
+use std::sync::atomic::{ AtomicU64, Ordering };
+
+use alloy_eips::BlockNumHash;
+use alloy_primitives::{ Bytes, TxHash };
+use reth_tracing::tracing::warn;
+use searcher_reth_ipc::{ Header, Message, MessageBody, Opportunity, DEFAULT_MAX_DATAGRAM };
+use tokio::net::UnixDatagram;
+
+/// A message for the output socket, before it is numbered.
+#[derive(Debug, Clone)]
+pub struct OutputMessage {
+    /// Block the message is about, the target block of bundles (with a zero hash).
+    pub block: BlockNumHash,
+    /// Zero for retractions, which don't depend on the configuration.
+    pub config_version: u64,
+    pub body: MessageBody,
+}
+
+impl OutputMessage {
+    pub fn opportunities(
+        block: BlockNumHash,
+        config_version: u64,
+        opportunities: Vec<Opportunity>
+    ) -> Self {
+        Self { block, config_version, body: MessageBody::Opportunities(opportunities) }
+    }
+
+    pub fn retraction(block: BlockNumHash) -> Self {
+        Self { block, config_version: 0, body: MessageBody::Retraction }
+    }
+
+    pub fn backrun(
+        block: BlockNumHash,
+        config_version: u64,
+        target_tx: TxHash,
+        opportunities: Vec<Opportunity>
+    ) -> Self {
+        Self { block, config_version, body: MessageBody::backrun(target_tx, opportunities) }
+    }
+
+    /// A bundle of `transactions` for block `block_number`, led by `target_tx` for backruns.
+    pub fn bundle(
+        block_number: u64,
+        config_version: u64,
+        target_tx: Option<TxHash>,
+        transactions: Vec<Bytes>
+    ) -> Self {
+        Self {
+            block: BlockNumHash::new(block_number, Default::default()),
+            config_version,
+            body: MessageBody::bundle(target_tx, transactions),
+        }
+    }
+}
+
+/// The output socket, numbering the messages sent on it in the framed format of
+/// `searcher-reth-ipc`.
+#[derive(Debug)]
+pub struct OutputSocket {
+    sock: UnixDatagram,
+    chain_id: u64,
+    /// Sequence number of the next message.
+    sequence: AtomicU64,
+    max_datagram: usize,
+}
+
+impl OutputSocket {
+    pub fn new(sock: UnixDatagram, chain_id: u64) -> Self {
+        Self { sock, chain_id, sequence: AtomicU64::new(0), max_datagram: DEFAULT_MAX_DATAGRAM }
+    }
+
+    /// Sets the size of the datagrams, larger messages are split in chunks.
+    pub fn with_max_datagram(mut self, max_datagram: usize) -> Self {
+        self.max_datagram = max_datagram;
+        self
+    }
+}
+
+/// Sends `message` on the output socket, in order with the messages sent before it.
+///
+/// The chunks of a message are sent back to back, but messages sent concurrently from different
+/// tasks may interleave their chunks, which receivers reassemble by sequence number.
+pub(crate) async fn send(sock: &OutputSocket, message: OutputMessage) {
+    let message = Message {
+        header: Header {
+            chain_id: sock.chain_id,
+            block_number: message.block.number,
+            block_hash: message.block.hash,
+            config_version: message.config_version,
+            sequence: sock.sequence.fetch_add(1, Ordering::Relaxed),
+        },
+        body: message.body,
+    };
+    let frames = match message.encode(sock.max_datagram) {
+        Ok(frames) => frames,
+        Err(err) => {
+            warn!(target: "searcher_exex", %err, "failed to encode output message");
+            return;
+        }
+    };
+    for frame in frames {
+        if let Err(err) = sock.sock.send(&frame).await {
+            warn!(target: "searcher_exex", %err, "failed to send output message");
+            return;
+        }
+    }
+}
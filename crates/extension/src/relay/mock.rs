@@ -0,0 +1,107 @@
+use std::sync::{ atomic::{ AtomicU32, Ordering }, Arc, Mutex };
+
+use alloy_primitives::{ keccak256, Bytes, B256 };
+use eyre::Result;
+use jsonrpsee::{
+    server::{ Server, ServerHandle },
+    types::{ error::INTERNAL_ERROR_CODE, ErrorObjectOwned },
+    RpcModule,
+};
+
+use super::{ CallBundleParams, CallBundleResponse, Relay, SendBundleParams, SendBundleResponse };
+
+/// In-process relay answering `eth_sendBundle` and `eth_callBundle`, recording every bundle it
+/// receives, to exercise the submission path offline. Signature headers are not checked.
+#[derive(Debug)]
+pub struct MockRelay {
+    url: String,
+    handle: ServerHandle,
+    state: Arc<MockState>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    bundles: Mutex<Vec<SendBundleParams>>,
+    calls: Mutex<Vec<CallBundleParams>>,
+    /// Number of upcoming requests answered with an internal error.
+    failures: AtomicU32,
+}
+
+impl MockState {
+    fn check_failure(&self) -> Result<(), ErrorObjectOwned> {
+        let failing = self.failures
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |failures| failures.checked_sub(1))
+            .is_ok();
+        if failing {
+            return Err(ErrorObjectOwned::owned(INTERNAL_ERROR_CODE, "mock failure", None::<()>));
+        }
+        Ok(())
+    }
+}
+
+impl MockRelay {
+    /// Starts a relay on a free local port.
+    pub async fn spawn() -> Result<Self> {
+        let server = Server::builder().build("127.0.0.1:0").await?;
+        let url = format!("http://{}", server.local_addr()?);
+        let state = Arc::new(MockState::default());
+
+        let mut module = RpcModule::new(state.clone());
+        module.register_method("eth_sendBundle", |params, state, _| {
+            state.check_failure()?;
+            let bundle = params.one::<SendBundleParams>()?;
+            let bundle_hash = bundle_hash(&bundle.txs);
+            state.bundles.lock().unwrap().push(bundle);
+            Ok::<_, ErrorObjectOwned>(SendBundleResponse { bundle_hash })
+        })?;
+        module.register_method("eth_callBundle", |params, state, _| {
+            state.check_failure()?;
+            let call = params.one::<CallBundleParams>()?;
+            let bundle_hash = bundle_hash(&call.txs);
+            state.calls.lock().unwrap().push(call);
+            Ok::<_, ErrorObjectOwned>(CallBundleResponse {
+                bundle_hash,
+                total_gas_used: 0,
+                results: Vec::new(),
+            })
+        })?;
+
+        let handle = server.start(module);
+        Ok(Self { url, handle, state })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The relay targeting one block per bundle.
+    pub fn relay(&self) -> Relay {
+        Relay { url: self.url.clone(), target_blocks: 1 }
+    }
+
+    /// Answers the next `count` requests with an internal error, which clients retry.
+    pub fn fail_next(&self, count: u32) {
+        self.state.failures.store(count, Ordering::Relaxed);
+    }
+
+    /// Bundles received through `eth_sendBundle`, in order.
+    pub fn bundles(&self) -> Vec<SendBundleParams> {
+        self.state.bundles.lock().unwrap().clone()
+    }
+
+    /// Bundles received through `eth_callBundle`, in order.
+    pub fn calls(&self) -> Vec<CallBundleParams> {
+        self.state.calls.lock().unwrap().clone()
+    }
+
+    pub async fn stop(self) {
+        if self.handle.stop().is_ok() {
+            self.handle.stopped().await;
+        }
+    }
+}
+
+/// Hash of a bundle: keccak256 of its concatenated transaction hashes.
+fn bundle_hash(txs: &[Bytes]) -> B256 {
+    keccak256(txs.iter().flat_map(keccak256).collect::<Vec<_>>())
+}
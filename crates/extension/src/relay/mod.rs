@@ -0,0 +1,380 @@
+pub mod mock;
+
+use std::{ str::FromStr, sync::Arc, time::Duration };
+
+use alloy_primitives::{ hex, keccak256, Bytes, B256, U64 };
+use alloy_signer::SignerSync;
+use alloy_signer_local::PrivateKeySigner;
+use eyre::{ eyre, Error, Result };
+use futures_util::future::join_all;
+use reth_tracing::tracing::{ debug, warn };
+use reqwest::{ header::CONTENT_TYPE, StatusCode };
+use serde::{ de::DeserializeOwned, Deserialize, Serialize };
+use serde_json::{ json, Value };
+
+/// Header carrying the signature of a request body, `<address>:<signature>`.
+pub const SIGNATURE_HEADER: &str = "X-Flashbots-Signature";
+/// JSON-RPC error code of internal relay errors, the only ones worth retrying.
+const INTERNAL_ERROR_CODE: i64 = -32603;
+
+/// A bundle relay and the number of consecutive blocks each bundle is submitted for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relay {
+    pub url: String,
+    /// Submits a bundle for its block and the `target_blocks - 1` following ones.
+    pub target_blocks: u64,
+}
+
+impl FromStr for Relay {
+    type Err = Error;
+
+    /// Parses `<url>[,<target blocks>]`, one target block by default.
+    fn from_str(s: &str) -> Result<Self> {
+        let (url, target_blocks) = match s.rsplit_once(',') {
+            Some((url, target_blocks)) => (url, target_blocks.parse()?),
+            None => (s, 1),
+        };
+        if target_blocks == 0 {
+            return Err(eyre!("relay {url} must target at least one block"));
+        }
+        Ok(Self { url: url.to_string(), target_blocks })
+    }
+}
+
+/// Submits `txs` as a bundle for `block_number` on its own task, so that slow relays don't hold
+/// the search back, and logs the outcome of every submission.
+pub(crate) fn submit(client: &RelayClient, block_number: u64, txs: Vec<Bytes>) {
+    let client = client.clone();
+    tokio::spawn(async move {
+        for submission in client.send_bundle(block_number, &txs).await {
+            let Submission { relay, block_number, result } = submission;
+            match result {
+                Ok(SendBundleResponse { bundle_hash }) => {
+                    debug!(target: "searcher_relay", %relay, block_number, %bundle_hash, "sent");
+                }
+                Err(err) => {
+                    warn!(target: "searcher_relay", %relay, block_number, %err, "rejected");
+                }
+            }
+        }
+    });
+}
+
+/// Params of `eth_sendBundle`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SendBundleParams {
+    pub txs: Vec<Bytes>,
+    pub block_number: U64,
+}
+
+/// Result of `eth_sendBundle`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SendBundleResponse {
+    pub bundle_hash: B256,
+}
+
+/// Params of `eth_callBundle`: simulates the bundle in `block_number` on the state after
+/// `state_block_number`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallBundleParams {
+    pub txs: Vec<Bytes>,
+    pub block_number: U64,
+    pub state_block_number: U64,
+}
+
+/// Result of `eth_callBundle`, the per-transaction results are kept as returned.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallBundleResponse {
+    pub bundle_hash: B256,
+    #[serde(default)]
+    pub total_gas_used: u64,
+    #[serde(default)]
+    pub results: Vec<Value>,
+}
+
+/// Outcome of submitting a bundle to one relay for one block.
+#[derive(Debug)]
+pub struct Submission {
+    pub relay: String,
+    pub block_number: u64,
+    pub result: Result<SendBundleResponse>,
+}
+
+/// JSON-RPC response envelope.
+#[derive(Debug, Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+/// Error of a single attempt, and whether another attempt may succeed.
+struct AttemptError {
+    error: Error,
+    retryable: bool,
+}
+
+/// Submits bundles to relays over the `eth_sendBundle` / `eth_callBundle` JSON-RPC dialect.
+///
+/// Every request body is signed by `signer`, which identifies the searcher to the relays.
+/// Transport errors, rate limits, server errors and internal relay errors are retried with
+/// exponential backoff.
+#[derive(Debug, Clone)]
+pub struct RelayClient {
+    http: reqwest::Client,
+    relays: Arc<Vec<Relay>>,
+    signer: PrivateKeySigner,
+    max_retries: u32,
+    retry_backoff: Duration,
+}
+
+impl RelayClient {
+    pub fn new(relays: Vec<Relay>, signer: PrivateKeySigner) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            relays: Arc::new(relays),
+            signer,
+            max_retries: 2,
+            retry_backoff: Duration::from_millis(100),
+        }
+    }
+
+    /// Sets the retries after a failed attempt, the backoff doubles from `retry_backoff`.
+    pub fn with_retries(mut self, max_retries: u32, retry_backoff: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.retry_backoff = retry_backoff;
+        self
+    }
+
+    pub fn relays(&self) -> &[Relay] {
+        &self.relays
+    }
+
+    /// Most consecutive blocks a relay is sent each bundle for.
+    pub fn target_blocks(&self) -> u64 {
+        self.relays.iter().map(|relay| relay.target_blocks).max().unwrap_or(1)
+    }
+
+    /// Submits `txs` as a bundle for `block_number` to every relay, and the following blocks
+    /// each relay targets, concurrently.
+    pub async fn send_bundle(&self, block_number: u64, txs: &[Bytes]) -> Vec<Submission> {
+        let submissions = self.relays.iter().flat_map(|relay| {
+            (block_number..block_number + relay.target_blocks).map(move |block_number| {
+                let params = SendBundleParams {
+                    txs: txs.to_vec(),
+                    block_number: U64::from(block_number),
+                };
+                async move {
+                    Submission {
+                        relay: relay.url.clone(),
+                        block_number,
+                        result: self.request(&relay.url, "eth_sendBundle", params).await,
+                    }
+                }
+            })
+        });
+        join_all(submissions).await
+    }
+
+    /// Simulates `txs` as a bundle for `block_number` on `relay`, on the state after
+    /// `state_block_number`.
+    pub async fn call_bundle(
+        &self,
+        relay: &Relay,
+        block_number: u64,
+        state_block_number: u64,
+        txs: &[Bytes]
+    ) -> Result<CallBundleResponse> {
+        let params = CallBundleParams {
+            txs: txs.to_vec(),
+            block_number: U64::from(block_number),
+            state_block_number: U64::from(state_block_number),
+        };
+        self.request(&relay.url, "eth_callBundle", params).await
+    }
+
+    async fn request<P: Serialize, R: DeserializeOwned>(
+        &self,
+        url: &str,
+        method: &str,
+        params: P
+    ) -> Result<R> {
+        let body = serde_json::to_vec(
+            &json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": [params] })
+        )?;
+        let signature = self.sign(&body)?;
+
+        let mut backoff = self.retry_backoff;
+        let mut attempt = 0;
+        loop {
+            match self.attempt(url, &body, &signature).await {
+                Ok(result) => {
+                    return Ok(result);
+                }
+                Err(AttemptError { error, retryable }) => {
+                    if !retryable || attempt >= self.max_retries {
+                        return Err(error.wrap_err(format!("{method} to {url} failed")));
+                    }
+                }
+            }
+            attempt += 1;
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+        }
+    }
+
+    async fn attempt<R: DeserializeOwned>(
+        &self,
+        url: &str,
+        body: &[u8],
+        signature: &str
+    ) -> Result<R, AttemptError> {
+        let response = self.http
+            .post(url)
+            .header(CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, signature)
+            .body(body.to_vec())
+            .send().await
+            .map_err(|err| AttemptError { error: err.into(), retryable: true })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let retryable = status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error();
+            return Err(AttemptError { error: eyre!("relay responded {status}"), retryable });
+        }
+        let body = response
+            .bytes().await
+            .map_err(|err| AttemptError { error: err.into(), retryable: true })?;
+        let response = serde_json::from_slice::<RpcResponse<R>>(&body)
+            .map_err(|err| AttemptError { error: err.into(), retryable: false })?;
+        match (response.result, response.error) {
+            (_, Some(error)) =>
+                Err(AttemptError {
+                    error: eyre!("relay error {}: {}", error.code, error.message),
+                    retryable: error.code == INTERNAL_ERROR_CODE,
+                }),
+            (Some(result), None) => Ok(result),
+            (None, None) =>
+                Err(AttemptError {
+                    error: eyre!("relay response without result"),
+                    retryable: false,
+                }),
+        }
+    }
+
+    /// `<address>:<signature>` of the EIP-191 signature of the hex-encoded body hash.
+    fn sign(&self, body: &[u8]) -> Result<String> {
+        let hash = hex::encode_prefixed(keccak256(body));
+        let signature = self.signer.sign_message_sync(hash.as_bytes())?;
+        Ok(format!("{}:{}", self.signer.address(), hex::encode_prefixed(signature.as_bytes())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use alloy_primitives::{ bytes, Bytes, U64 };
+    use alloy_signer_local::PrivateKeySigner;
+
+    use super::{ mock::MockRelay, submit, Relay, RelayClient, SendBundleParams };
+
+    fn txs() -> Vec<Bytes> {
+        vec![bytes!("02f86b0180"), bytes!("02f86b0101")]
+    }
+
+    fn client(relays: Vec<Relay>) -> RelayClient {
+        RelayClient::new(relays, PrivateKeySigner::random()).with_retries(
+            2,
+            Duration::from_millis(1)
+        )
+    }
+
+    fn bundle(block_number: u64) -> SendBundleParams {
+        SendBundleParams { txs: txs(), block_number: U64::from(block_number) }
+    }
+
+    #[tokio::test]
+    async fn sends_bundles_to_every_relay_and_target_block() {
+        let single = MockRelay::spawn().await.unwrap();
+        let double = MockRelay::spawn().await.unwrap();
+        let client = client(
+            vec![single.relay(), Relay { url: double.url().to_string(), target_blocks: 2 }]
+        );
+
+        let submissions = client.send_bundle(100, &txs()).await;
+
+        assert_eq!(submissions.len(), 3);
+        assert!(submissions.iter().all(|submission| submission.result.is_ok()));
+        assert_eq!(single.bundles(), vec![bundle(100)]);
+        let mut bundles = double.bundles();
+        bundles.sort_by_key(|bundle| bundle.block_number);
+        assert_eq!(bundles, vec![bundle(100), bundle(101)]);
+
+        single.stop().await;
+        double.stop().await;
+    }
+
+    #[tokio::test]
+    async fn retries_internal_errors() {
+        let relay = MockRelay::spawn().await.unwrap();
+        let client = client(vec![relay.relay()]);
+
+        relay.fail_next(2);
+        let submissions = client.send_bundle(100, &txs()).await;
+        assert!(submissions[0].result.is_ok());
+        assert_eq!(relay.bundles(), vec![bundle(100)]);
+
+        // one failure more than the retries
+        relay.fail_next(3);
+        let submissions = client.send_bundle(101, &txs()).await;
+        assert!(submissions[0].result.is_err());
+        assert_eq!(relay.bundles(), vec![bundle(100)]);
+
+        relay.stop().await;
+    }
+
+    #[tokio::test]
+    async fn submits_in_the_background() {
+        let relay = MockRelay::spawn().await.unwrap();
+        let client = client(vec![relay.relay()]);
+        relay.fail_next(1);
+
+        submit(&client, 100, txs());
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while relay.bundles().is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }).await.unwrap();
+        assert_eq!(relay.bundles(), vec![bundle(100)]);
+
+        relay.stop().await;
+    }
+
+    #[tokio::test]
+    async fn simulates_bundles() {
+        let relay = MockRelay::spawn().await.unwrap();
+        let client = client(vec![relay.relay()]);
+
+        let response = client.call_bundle(&relay.relay(), 101, 100, &txs()).await.unwrap();
+
+        let calls = relay.calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].txs, txs());
+        assert_eq!(calls[0].block_number, U64::from(101));
+        assert_eq!(calls[0].state_block_number, U64::from(100));
+        assert_eq!(response.total_gas_used, 0);
+
+        relay.stop().await;
+    }
+}
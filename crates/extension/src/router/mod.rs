@@ -0,0 +1,420 @@
+pub mod uniswap_v2;
+pub mod uniswap_v3;
+pub mod universal;
+
+use std::collections::HashMap;
+
+use alloy_primitives::{ address, b256, keccak256, Address, B256, U256 };
+
+use crate::strategy::path_finding::types::Hop;
+
+/// Amounts of a decoded swap, in units of its first input and last output token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapAmount {
+    ExactIn {
+        amount_in: U256,
+        min_amount_out: U256,
+    },
+    ExactOut {
+        amount_out: U256,
+        max_amount_in: U256,
+    },
+}
+
+/// A swap decoded from router calldata: the hops it goes through, in swap order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedSwap {
+    pub hops: Vec<Hop>,
+    pub amount: SwapAmount,
+}
+
+impl DecodedSwap {
+    pub fn pools(&self) -> impl Iterator<Item = &Address> {
+        self.hops.iter().map(|hop| &hop.dex)
+    }
+}
+
+/// Where a factory deploys its pools: `CREATE2` from `address` with `init_code_hash`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolFactory {
+    pub address: Address,
+    pub init_code_hash: B256,
+}
+
+impl PoolFactory {
+    fn pool_address(&self, salt: B256) -> Address {
+        self.address.create2(salt, self.init_code_hash)
+    }
+}
+
+/// Uniswap V2 factory on Ethereum mainnet.
+pub const UNISWAP_V2_FACTORY: PoolFactory = PoolFactory {
+    address: address!("5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f"),
+    init_code_hash: b256!("96e8ac4277198ff8b6f785478aa9a39f403cb768dd02cbee326c3e7da348845f"),
+};
+
+/// Uniswap V3 factory on Ethereum mainnet.
+pub const UNISWAP_V3_FACTORY: PoolFactory = PoolFactory {
+    address: address!("1F98431c8aD98523631AE4a59f267346ea31F984"),
+    init_code_hash: b256!("e34f199b19b2b4f47f68442619d555527d244f78a3297ea89325f843f87b8b54"),
+};
+
+/// Calldata layout of a router.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouterKind {
+    /// `UniswapV2Router02`.
+    UniswapV2Router02,
+    /// Uniswap V3 `SwapRouter`.
+    SwapRouter,
+    /// Uniswap `SwapRouter02`, V3 swaps without deadline plus V2 swaps.
+    SwapRouter02,
+    /// Uniswap `UniversalRouter`.
+    UniversalRouter,
+}
+
+/// Decodes the swaps of router calls into hops, deriving pool addresses from the factories.
+#[derive(Debug, Clone)]
+pub struct RouterDecoder {
+    routers: HashMap<Address, RouterKind>,
+    v2_factory: PoolFactory,
+    v3_factory: PoolFactory,
+}
+
+impl Default for RouterDecoder {
+    /// Uniswap routers and factories on Ethereum mainnet.
+    fn default() -> Self {
+        Self::new(UNISWAP_V2_FACTORY, UNISWAP_V3_FACTORY)
+            .with_router(
+                address!("7a250d5630B4cF539739dF2C5dAcb4c659F2488D"),
+                RouterKind::UniswapV2Router02
+            )
+            .with_router(
+                address!("E592427A0AEce92De3Edee1F18E0157C05861564"),
+                RouterKind::SwapRouter
+            )
+            .with_router(
+                address!("68b3465833fb72A70ecDF485E0e4C7bD8665Fc45"),
+                RouterKind::SwapRouter02
+            )
+            .with_router(
+                address!("3fC91A3afd70395Cd496C647d5a8C14D5a0bc4E0"),
+                RouterKind::UniversalRouter
+            )
+    }
+}
+
+impl RouterDecoder {
+    /// A decoder without routers, swaps resolve their pools through `v2_factory` and `v3_factory`.
+    pub fn new(v2_factory: PoolFactory, v3_factory: PoolFactory) -> Self {
+        Self { routers: HashMap::new(), v2_factory, v3_factory }
+    }
+
+    pub fn with_router(mut self, address: Address, kind: RouterKind) -> Self {
+        self.routers.insert(address, kind);
+        self
+    }
+
+    pub fn is_router(&self, address: &Address) -> bool {
+        self.routers.contains_key(address)
+    }
+
+    /// Decodes the swaps of a call to `to` with `input` and `value`. Returns `None` when `to` is
+    /// not a known router or the call is not a swap, and an empty list for swaps of an unknown
+    /// shape (e.g. an empty path).
+    pub fn decode(&self, to: Address, input: &[u8], value: U256) -> Option<Vec<DecodedSwap>> {
+        match self.routers.get(&to)? {
+            RouterKind::UniswapV2Router02 => uniswap_v2::decode(&self.v2_factory, input, value),
+            RouterKind::SwapRouter => uniswap_v3::decode_swap_router(self, input),
+            RouterKind::SwapRouter02 => uniswap_v3::decode_swap_router02(self, input),
+            RouterKind::UniversalRouter => universal::decode(self, input),
+        }
+    }
+}
+
+/// Sorts a token pair the way the factories do.
+fn sort_tokens(a: Address, b: Address) -> (Address, Address) {
+    if a < b { (a, b) } else { (b, a) }
+}
+
+/// Uniswap V2 pair of `a` and `b`: salt `keccak256(abi.encodePacked(token0, token1))`.
+pub fn uniswap_v2_pair(factory: &PoolFactory, a: Address, b: Address) -> Address {
+    let (token0, token1) = sort_tokens(a, b);
+    let mut packed = [0u8; 40];
+    packed[..20].copy_from_slice(token0.as_slice());
+    packed[20..].copy_from_slice(token1.as_slice());
+    factory.pool_address(keccak256(packed))
+}
+
+/// Uniswap V3 pool of `a` and `b` with `fee`: salt `keccak256(abi.encode(token0, token1, fee))`.
+pub fn uniswap_v3_pool(factory: &PoolFactory, a: Address, b: Address, fee: u32) -> Address {
+    let (token0, token1) = sort_tokens(a, b);
+    let mut encoded = [0u8; 96];
+    encoded[12..32].copy_from_slice(token0.as_slice());
+    encoded[44..64].copy_from_slice(token1.as_slice());
+    encoded[92..96].copy_from_slice(&fee.to_be_bytes());
+    factory.pool_address(keccak256(encoded))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{ address, hex, Address, U256 };
+    use alloy_sol_types::SolCall;
+    use searcher_reth_repository::types::dex_type;
+
+    use crate::strategy::path_finding::types::Hop;
+
+    use super::{
+        universal::IUniversalRouter::execute_0Call,
+        uniswap_v2_pair,
+        uniswap_v3_pool,
+        DecodedSwap,
+        RouterDecoder,
+        SwapAmount,
+        UNISWAP_V2_FACTORY,
+        UNISWAP_V3_FACTORY,
+    };
+
+    const USDC: Address = address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48");
+    const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    const USDT: Address = address!("dAC17F958D2ee523a2206206994597C13D831ec7");
+    const DAI: Address = address!("6B175474E89094C44Da98b954EedeAC495271d0F");
+    const WBTC: Address = address!("2260FAC5E5542a773Aa44fBCfeDf7C193bc2C599");
+
+    const UNISWAP_V2_ROUTER: Address = address!("7a250d5630B4cF539739dF2C5dAcb4c659F2488D");
+    const SWAP_ROUTER: Address = address!("E592427A0AEce92De3Edee1F18E0157C05861564");
+    const UNIVERSAL_ROUTER: Address = address!("3fC91A3afd70395Cd496C647d5a8C14D5a0bc4E0");
+
+    /// `execute(commands, inputs, deadline)` with `WRAP_ETH`, `V3_SWAP_EXACT_OUT` over the path
+    /// `USDC 500 WETH 3000 WBTC`, `V2_SWAP_EXACT_IN` over `[USDC, WETH, USDT]` with the
+    /// allow-revert flag set, and `UNWRAP_WETH`.
+    fn universal_router_execute() -> Vec<u8> {
+        hex::decode(
+            concat!(
+                "3593564c",
+                "0000000000000000000000000000000000000000000000000000000000000060",
+                "00000000000000000000000000000000000000000000000000000000000000a0",
+                "000000000000000000000000000000000000000000000000000000006553f100",
+                "0000000000000000000000000000000000000000000000000000000000000004",
+                "0b01880c00000000000000000000000000000000000000000000000000000000",
+                "0000000000000000000000000000000000000000000000000000000000000004",
+                "0000000000000000000000000000000000000000000000000000000000000080",
+                "00000000000000000000000000000000000000000000000000000000000000e0",
+                "0000000000000000000000000000000000000000000000000000000000000220",
+                "0000000000000000000000000000000000000000000000000000000000000360",
+                "0000000000000000000000000000000000000000000000000000000000000040",
+                "0000000000000000000000000000000000000000000000000000000000000002",
+                "0000000000000000000000000000000000000000000000000de0b6b3a7640000",
+                "0000000000000000000000000000000000000000000000000000000000000120",
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "00000000000000000000000000000000000000000000000000000000004c4b40",
+                "0000000000000000000000000000000000000000000000000de0b6b3a7640000",
+                "00000000000000000000000000000000000000000000000000000000000000a0",
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "0000000000000000000000000000000000000000000000000000000000000042",
+                "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb480001f4c02aaa39b223fe8d0a",
+                "0e5c4f27ead9083c756cc2000bb82260fac5e5542a773aa44fbcfedf7c193bc2",
+                "c599000000000000000000000000000000000000000000000000000000000000",
+                "0000000000000000000000000000000000000000000000000000000000000120",
+                "0000000000000000000000000000000000000000000000000000000000000002",
+                "0000000000000000000000000000000000000000000000000000000077359400",
+                "000000000000000000000000000000000000000000000000016345785d8a0000",
+                "00000000000000000000000000000000000000000000000000000000000000a0",
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "0000000000000000000000000000000000000000000000000000000000000003",
+                "000000000000000000000000a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+                "000000000000000000000000c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+                "000000000000000000000000dac17f958d2ee523a2206206994597c13d831ec7",
+                "0000000000000000000000000000000000000000000000000000000000000040",
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "0000000000000000000000000000000000000000000000000000000000000000",
+            )
+        ).unwrap()
+    }
+
+    /// `SwapRouter.exactOutput` over the path `USDT 500 WETH 500 USDC`, buying 1000 USDT.
+    fn swap_router_exact_output() -> Vec<u8> {
+        hex::decode(
+            concat!(
+                "f28c0498",
+                "0000000000000000000000000000000000000000000000000000000000000020",
+                "00000000000000000000000000000000000000000000000000000000000000a0",
+                "0000000000000000000000001111111111111111111111111111111111111111",
+                "000000000000000000000000000000000000000000000000000000006553f100",
+                "000000000000000000000000000000000000000000000000000000003b9aca00",
+                "000000000000000000000000000000000000000000000000000000003c336080",
+                "0000000000000000000000000000000000000000000000000000000000000042",
+                "dac17f958d2ee523a2206206994597c13d831ec70001f4c02aaa39b223fe8d0a",
+                "0e5c4f27ead9083c756cc20001f4a0b86991c6218b36c1d19d4a2e9eb0ce3606",
+                "eb48000000000000000000000000000000000000000000000000000000000000",
+            )
+        ).unwrap()
+    }
+
+    /// `UniswapV2Router02.swapExactETHForTokens` over `[WETH, DAI]`.
+    fn swap_exact_eth_for_tokens() -> Vec<u8> {
+        hex::decode(
+            concat!(
+                "7ff36ab5",
+                "0000000000000000000000000000000000000000000000a2a15d09519be00000",
+                "0000000000000000000000000000000000000000000000000000000000000080",
+                "0000000000000000000000001111111111111111111111111111111111111111",
+                "000000000000000000000000000000000000000000000000000000006553f100",
+                "0000000000000000000000000000000000000000000000000000000000000002",
+                "000000000000000000000000c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+                "0000000000000000000000006b175474e89094c44da98b954eedeac495271d0f",
+            )
+        ).unwrap()
+    }
+
+    fn hop(dex_type: u8, dex: Address, src_token: Address, dst_token: Address) -> Hop {
+        Hop { dexType: dex_type, dex, srcToken: src_token, dstToken: dst_token }
+    }
+
+    #[test]
+    fn derives_mainnet_pool_addresses() {
+        let pairs = [
+            (USDC, WETH, address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc")),
+            (WETH, USDT, address!("0d4a11d5EEaaC28EC3F61d100daF4d40471f1852")),
+            (DAI, WETH, address!("A478c2975Ab1Ea89e8196811F51A7B7Ade33eB11")),
+        ];
+        for (a, b, pair) in pairs {
+            assert_eq!(uniswap_v2_pair(&UNISWAP_V2_FACTORY, a, b), pair);
+            assert_eq!(uniswap_v2_pair(&UNISWAP_V2_FACTORY, b, a), pair);
+        }
+
+        let pools = [
+            (USDC, WETH, 500, address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640")),
+            (USDC, WETH, 3000, address!("8ad599c3A0ff1De082011EFDDc58f1908eb6e6D8")),
+            (WBTC, WETH, 3000, address!("CBCdF9626bC03E24f779434178A73a0B4bad62eD")),
+            (WETH, USDT, 500, address!("11b815efB8f581194ae79006d24E0d814B7697F6")),
+        ];
+        for (a, b, fee, pool) in pools {
+            assert_eq!(uniswap_v3_pool(&UNISWAP_V3_FACTORY, a, b, fee), pool);
+            assert_eq!(uniswap_v3_pool(&UNISWAP_V3_FACTORY, b, a, fee), pool);
+        }
+    }
+
+    #[test]
+    fn decodes_universal_router_swap_commands() {
+        let swaps = RouterDecoder::default()
+            .decode(UNIVERSAL_ROUTER, &universal_router_execute(), U256::ZERO)
+            .unwrap();
+
+        // the wrap and unwrap commands are skipped, the exact output path is in swap order
+        assert_eq!(swaps, vec![
+            DecodedSwap {
+                hops: vec![
+                    hop(
+                        dex_type::UNISWAP_V3,
+                        address!("CBCdF9626bC03E24f779434178A73a0B4bad62eD"),
+                        WBTC,
+                        WETH
+                    ),
+                    hop(
+                        dex_type::UNISWAP_V3,
+                        address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"),
+                        WETH,
+                        USDC
+                    )
+                ],
+                amount: SwapAmount::ExactOut {
+                    amount_out: U256::from(5_000_000u64),
+                    max_amount_in: U256::from(10u64.pow(18)),
+                },
+            },
+            DecodedSwap {
+                hops: vec![
+                    hop(
+                        dex_type::UNISWAP_V2,
+                        address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"),
+                        USDC,
+                        WETH
+                    ),
+                    hop(
+                        dex_type::UNISWAP_V2,
+                        address!("0d4a11d5EEaaC28EC3F61d100daF4d40471f1852"),
+                        WETH,
+                        USDT
+                    )
+                ],
+                amount: SwapAmount::ExactIn {
+                    amount_in: U256::from(2_000_000_000u64),
+                    min_amount_out: U256::from(10u64.pow(17)),
+                },
+            }
+        ]);
+    }
+
+    #[test]
+    fn rejects_universal_router_commands_it_cannot_decode() {
+        let decode = |commands: [u8; 4]| {
+            let mut call = execute_0Call::abi_decode(&universal_router_execute()).unwrap();
+            call.commands = commands.to_vec().into();
+            RouterDecoder::default().decode(UNIVERSAL_ROUTER, &call.abi_encode(), U256::ZERO)
+        };
+
+        assert_eq!(decode([0x0b, 0x01, 0x88, 0x0c]).map(|swaps| swaps.len()), Some(2));
+        // a V4 swap or a sub-plan in place of the wrap, with or without the allow-revert flag
+        assert!(decode([0x10, 0x01, 0x88, 0x0c]).is_none());
+        assert!(decode([0xa1, 0x01, 0x88, 0x0c]).is_none());
+        // a swap command whose input doesn't decode
+        assert!(decode([0x0b, 0x08, 0x88, 0x0c]).is_none());
+    }
+
+    #[test]
+    fn reverses_exact_output_paths() {
+        let swaps = RouterDecoder::default()
+            .decode(SWAP_ROUTER, &swap_router_exact_output(), U256::ZERO)
+            .unwrap();
+
+        assert_eq!(swaps, vec![DecodedSwap {
+            hops: vec![
+                hop(
+                    dex_type::UNISWAP_V3,
+                    address!("88e6A0c2dDD26FEEb64F039a2c41296FcB3f5640"),
+                    USDC,
+                    WETH
+                ),
+                hop(
+                    dex_type::UNISWAP_V3,
+                    address!("11b815efB8f581194ae79006d24E0d814B7697F6"),
+                    WETH,
+                    USDT
+                )
+            ],
+            amount: SwapAmount::ExactOut {
+                amount_out: U256::from(1_000_000_000u64),
+                max_amount_in: U256::from(1_010_000_000u64),
+            },
+        }]);
+    }
+
+    #[test]
+    fn decodes_eth_input_from_value() {
+        let value = U256::from(10u64.pow(18));
+        let swaps = RouterDecoder::default()
+            .decode(UNISWAP_V2_ROUTER, &swap_exact_eth_for_tokens(), value)
+            .unwrap();
+
+        assert_eq!(swaps, vec![DecodedSwap {
+            hops: vec![
+                hop(
+                    dex_type::UNISWAP_V2,
+                    address!("A478c2975Ab1Ea89e8196811F51A7B7Ade33eB11"),
+                    WETH,
+                    DAI
+                )
+            ],
+            amount: SwapAmount::ExactIn {
+                amount_in: value,
+                min_amount_out: U256::from(3000u64) * U256::from(10u64.pow(18)),
+            },
+        }]);
+    }
+
+    #[test]
+    fn ignores_unknown_routers() {
+        let input = swap_exact_eth_for_tokens();
+        assert!(RouterDecoder::default().decode(WETH, &input, U256::ZERO).is_none());
+    }
+}
@@ -0,0 +1,93 @@
+use alloy_primitives::{ Address, U256 };
+use alloy_sol_types::{ sol, SolInterface };
+use searcher_reth_repository::types::dex_type;
+
+use crate::strategy::path_finding::types::Hop;
+
+use super::{ uniswap_v2_pair, DecodedSwap, PoolFactory, SwapAmount };
+
+sol! {
+    interface IUniswapV2Router02 {
+        function swapExactTokensForTokens(
+            uint256 amountIn,
+            uint256 amountOutMin,
+            address[] path,
+            address to,
+            uint256 deadline
+        ) external returns (uint256[] amounts);
+        function swapExactETHForTokens(
+            uint256 amountOutMin,
+            address[] path,
+            address to,
+            uint256 deadline
+        ) external payable returns (uint256[] amounts);
+        function swapExactTokensForETH(
+            uint256 amountIn,
+            uint256 amountOutMin,
+            address[] path,
+            address to,
+            uint256 deadline
+        ) external returns (uint256[] amounts);
+        function swapExactTokensForTokensSupportingFeeOnTransferTokens(
+            uint256 amountIn,
+            uint256 amountOutMin,
+            address[] path,
+            address to,
+            uint256 deadline
+        ) external;
+        function swapExactETHForTokensSupportingFeeOnTransferTokens(
+            uint256 amountOutMin,
+            address[] path,
+            address to,
+            uint256 deadline
+        ) external payable;
+        function swapExactTokensForETHSupportingFeeOnTransferTokens(
+            uint256 amountIn,
+            uint256 amountOutMin,
+            address[] path,
+            address to,
+            uint256 deadline
+        ) external;
+    }
+}
+
+/// Decodes the `swapExact*` calls of `UniswapV2Router02`, ETH inputs are the call `value`.
+pub fn decode(factory: &PoolFactory, input: &[u8], value: U256) -> Option<Vec<DecodedSwap>> {
+    use IUniswapV2Router02::IUniswapV2Router02Calls as Calls;
+
+    let (path, amount_in, min_amount_out) = match Calls::abi_decode(input).ok()? {
+        Calls::swapExactTokensForTokens(call) => (call.path, call.amountIn, call.amountOutMin),
+        Calls::swapExactETHForTokens(call) => (call.path, value, call.amountOutMin),
+        Calls::swapExactTokensForETH(call) => (call.path, call.amountIn, call.amountOutMin),
+        Calls::swapExactTokensForTokensSupportingFeeOnTransferTokens(call) =>
+            (call.path, call.amountIn, call.amountOutMin),
+        Calls::swapExactETHForTokensSupportingFeeOnTransferTokens(call) =>
+            (call.path, value, call.amountOutMin),
+        Calls::swapExactTokensForETHSupportingFeeOnTransferTokens(call) =>
+            (call.path, call.amountIn, call.amountOutMin),
+    };
+    let amount = SwapAmount::ExactIn { amount_in, min_amount_out };
+    Some(swap(factory, &path, amount).into_iter().collect())
+}
+
+/// Swap through the pairs of a token `path`, which is in swap order for exact input and exact
+/// output alike. `None` for paths of less than two tokens.
+pub(crate) fn swap(
+    factory: &PoolFactory,
+    path: &[Address],
+    amount: SwapAmount
+) -> Option<DecodedSwap> {
+    if path.len() < 2 {
+        return None;
+    }
+    let hops = path
+        .windows(2)
+        .map(|pair| Hop {
+            dexType: dex_type::UNISWAP_V2,
+            dex: uniswap_v2_pair(factory, pair[0], pair[1]),
+            srcToken: pair[0],
+            dstToken: pair[1],
+        })
+        .collect();
+    Some(DecodedSwap { hops, amount })
+}
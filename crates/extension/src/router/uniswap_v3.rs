@@ -0,0 +1,301 @@
+use alloy_primitives::{ aliases::U24, Address, Bytes };
+use alloy_sol_types::SolInterface;
+use searcher_reth_repository::types::dex_type;
+
+use crate::strategy::path_finding::types::Hop;
+
+use super::{ uniswap_v2, uniswap_v3_pool, DecodedSwap, PoolFactory, RouterDecoder, SwapAmount };
+
+/// Length of an address in an encoded path.
+const ADDRESS_LENGTH: usize = 20;
+/// Length of a fee and the next address in an encoded path.
+const HOP_LENGTH: usize = 3 + ADDRESS_LENGTH;
+
+// the params structs of both routers share their names, so each router has its own module
+mod swap_router {
+    alloy_sol_types::sol! {
+        interface ISwapRouter {
+            struct ExactInputSingleParams {
+                address tokenIn;
+                address tokenOut;
+                uint24 fee;
+                address recipient;
+                uint256 deadline;
+                uint256 amountIn;
+                uint256 amountOutMinimum;
+                uint160 sqrtPriceLimitX96;
+            }
+
+            struct ExactInputParams {
+                bytes path;
+                address recipient;
+                uint256 deadline;
+                uint256 amountIn;
+                uint256 amountOutMinimum;
+            }
+
+            struct ExactOutputSingleParams {
+                address tokenIn;
+                address tokenOut;
+                uint24 fee;
+                address recipient;
+                uint256 deadline;
+                uint256 amountOut;
+                uint256 amountInMaximum;
+                uint160 sqrtPriceLimitX96;
+            }
+
+            struct ExactOutputParams {
+                bytes path;
+                address recipient;
+                uint256 deadline;
+                uint256 amountOut;
+                uint256 amountInMaximum;
+            }
+
+            function exactInputSingle(ExactInputSingleParams params)
+                external payable returns (uint256 amountOut);
+            function exactInput(ExactInputParams params)
+                external payable returns (uint256 amountOut);
+            function exactOutputSingle(ExactOutputSingleParams params)
+                external payable returns (uint256 amountIn);
+            function exactOutput(ExactOutputParams params)
+                external payable returns (uint256 amountIn);
+            function multicall(bytes[] data) external payable returns (bytes[] results);
+        }
+    }
+}
+
+mod swap_router02 {
+    alloy_sol_types::sol! {
+        interface ISwapRouter02 {
+            struct ExactInputSingleParams {
+                address tokenIn;
+                address tokenOut;
+                uint24 fee;
+                address recipient;
+                uint256 amountIn;
+                uint256 amountOutMinimum;
+                uint160 sqrtPriceLimitX96;
+            }
+
+            struct ExactInputParams {
+                bytes path;
+                address recipient;
+                uint256 amountIn;
+                uint256 amountOutMinimum;
+            }
+
+            struct ExactOutputSingleParams {
+                address tokenIn;
+                address tokenOut;
+                uint24 fee;
+                address recipient;
+                uint256 amountOut;
+                uint256 amountInMaximum;
+                uint160 sqrtPriceLimitX96;
+            }
+
+            struct ExactOutputParams {
+                bytes path;
+                address recipient;
+                uint256 amountOut;
+                uint256 amountInMaximum;
+            }
+
+            function exactInputSingle(ExactInputSingleParams params)
+                external payable returns (uint256 amountOut);
+            function exactInput(ExactInputParams params)
+                external payable returns (uint256 amountOut);
+            function exactOutputSingle(ExactOutputSingleParams params)
+                external payable returns (uint256 amountIn);
+            function exactOutput(ExactOutputParams params)
+                external payable returns (uint256 amountIn);
+            function swapExactTokensForTokens(
+                uint256 amountIn,
+                uint256 amountOutMin,
+                address[] path,
+                address to
+            ) external payable returns (uint256 amountOut);
+            function swapTokensForExactTokens(
+                uint256 amountOut,
+                uint256 amountInMax,
+                address[] path,
+                address to
+            ) external payable returns (uint256 amountIn);
+            function multicall(bytes[] data) external payable returns (bytes[] results);
+            function multicall(uint256 deadline, bytes[] data)
+                external payable returns (bytes[] results);
+        }
+    }
+}
+
+/// Decodes the `exactInput*`/`exactOutput*` calls of the Uniswap V3 `SwapRouter`, also inside
+/// `multicall`.
+pub fn decode_swap_router(decoder: &RouterDecoder, input: &[u8]) -> Option<Vec<DecodedSwap>> {
+    use swap_router::ISwapRouter::ISwapRouterCalls as Calls;
+
+    let factory = &decoder.v3_factory;
+    let swap = match Calls::abi_decode(input).ok()? {
+        Calls::exactInputSingle(call) => {
+            let params = call.params;
+            single(factory, params.tokenIn, params.tokenOut, params.fee, SwapAmount::ExactIn {
+                amount_in: params.amountIn,
+                min_amount_out: params.amountOutMinimum,
+            })
+        }
+        Calls::exactInput(call) => {
+            let params = call.params;
+            path_swap(factory, &params.path, SwapAmount::ExactIn {
+                amount_in: params.amountIn,
+                min_amount_out: params.amountOutMinimum,
+            })
+        }
+        Calls::exactOutputSingle(call) => {
+            let params = call.params;
+            single(factory, params.tokenIn, params.tokenOut, params.fee, SwapAmount::ExactOut {
+                amount_out: params.amountOut,
+                max_amount_in: params.amountInMaximum,
+            })
+        }
+        Calls::exactOutput(call) => {
+            let params = call.params;
+            path_swap(factory, &params.path, SwapAmount::ExactOut {
+                amount_out: params.amountOut,
+                max_amount_in: params.amountInMaximum,
+            })
+        }
+        Calls::multicall(call) => {
+            return Some(
+                call.data
+                    .iter()
+                    .filter_map(|data| decode_swap_router(decoder, data))
+                    .flatten()
+                    .collect()
+            );
+        }
+    };
+    Some(swap.into_iter().collect())
+}
+
+/// Decodes the V3 `exactInput*`/`exactOutput*` and the V2 swap calls of Uniswap `SwapRouter02`,
+/// also inside `multicall`.
+pub fn decode_swap_router02(decoder: &RouterDecoder, input: &[u8]) -> Option<Vec<DecodedSwap>> {
+    use swap_router02::ISwapRouter02::ISwapRouter02Calls as Calls;
+
+    let factory = &decoder.v3_factory;
+    let swap = match Calls::abi_decode(input).ok()? {
+        Calls::exactInputSingle(call) => {
+            let params = call.params;
+            single(factory, params.tokenIn, params.tokenOut, params.fee, SwapAmount::ExactIn {
+                amount_in: params.amountIn,
+                min_amount_out: params.amountOutMinimum,
+            })
+        }
+        Calls::exactInput(call) => {
+            let params = call.params;
+            path_swap(factory, &params.path, SwapAmount::ExactIn {
+                amount_in: params.amountIn,
+                min_amount_out: params.amountOutMinimum,
+            })
+        }
+        Calls::exactOutputSingle(call) => {
+            let params = call.params;
+            single(factory, params.tokenIn, params.tokenOut, params.fee, SwapAmount::ExactOut {
+                amount_out: params.amountOut,
+                max_amount_in: params.amountInMaximum,
+            })
+        }
+        Calls::exactOutput(call) => {
+            let params = call.params;
+            path_swap(factory, &params.path, SwapAmount::ExactOut {
+                amount_out: params.amountOut,
+                max_amount_in: params.amountInMaximum,
+            })
+        }
+        Calls::swapExactTokensForTokens(call) =>
+            uniswap_v2::swap(&decoder.v2_factory, &call.path, SwapAmount::ExactIn {
+                amount_in: call.amountIn,
+                min_amount_out: call.amountOutMin,
+            }),
+        Calls::swapTokensForExactTokens(call) =>
+            uniswap_v2::swap(&decoder.v2_factory, &call.path, SwapAmount::ExactOut {
+                amount_out: call.amountOut,
+                max_amount_in: call.amountInMax,
+            }),
+        Calls::multicall_0(call) => {
+            return Some(multicall(decoder, &call.data));
+        }
+        Calls::multicall_1(call) => {
+            return Some(multicall(decoder, &call.data));
+        }
+    };
+    Some(swap.into_iter().collect())
+}
+
+fn multicall(decoder: &RouterDecoder, data: &[Bytes]) -> Vec<DecodedSwap> {
+    data.iter()
+        .filter_map(|data| decode_swap_router02(decoder, data))
+        .flatten()
+        .collect()
+}
+
+/// Swap through the single pool of `token_in`, `token_out` and `fee`.
+fn single(
+    factory: &PoolFactory,
+    token_in: Address,
+    token_out: Address,
+    fee: U24,
+    amount: SwapAmount
+) -> Option<DecodedSwap> {
+    let hop = Hop {
+        dexType: dex_type::UNISWAP_V3,
+        dex: uniswap_v3_pool(factory, token_in, token_out, fee.to()),
+        srcToken: token_in,
+        dstToken: token_out,
+    };
+    Some(DecodedSwap { hops: vec![hop], amount })
+}
+
+/// Swap through an encoded path, which starts from the output token for exact output swaps.
+pub(crate) fn path_swap(
+    factory: &PoolFactory,
+    path: &[u8],
+    amount: SwapAmount
+) -> Option<DecodedSwap> {
+    let mut hops = path_hops(factory, path)?;
+    if let SwapAmount::ExactOut { .. } = amount {
+        hops = hops
+            .into_iter()
+            .rev()
+            .map(|hop| Hop { srcToken: hop.dstToken, dstToken: hop.srcToken, ..hop })
+            .collect();
+    }
+    Some(DecodedSwap { hops, amount })
+}
+
+/// Hops of an encoded path `token (fee token)+`, in path order.
+fn path_hops(factory: &PoolFactory, path: &[u8]) -> Option<Vec<Hop>> {
+    let hop_count = path.len().checked_sub(ADDRESS_LENGTH)? / HOP_LENGTH;
+    if hop_count == 0 || path.len() != ADDRESS_LENGTH + hop_count * HOP_LENGTH {
+        return None;
+    }
+    let hops = (0..hop_count)
+        .map(|index| {
+            let offset = index * HOP_LENGTH;
+            let src_token = Address::from_slice(&path[offset..offset + ADDRESS_LENGTH]);
+            let fee = &path[offset + ADDRESS_LENGTH..offset + HOP_LENGTH];
+            let fee = u32::from_be_bytes([0, fee[0], fee[1], fee[2]]);
+            let dst_token = Address::from_slice(
+                &path[offset + HOP_LENGTH..offset + HOP_LENGTH + ADDRESS_LENGTH]
+            );
+            Hop {
+                dexType: dex_type::UNISWAP_V3,
+                dex: uniswap_v3_pool(factory, src_token, dst_token, fee),
+                srcToken: src_token,
+                dstToken: dst_token,
+            }
+        })
+        .collect();
+    Some(hops)
+}
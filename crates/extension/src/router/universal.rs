@@ -0,0 +1,121 @@
+use alloy_sol_types::{ sol, SolInterface, SolValue };
+
+use super::{ uniswap_v2, uniswap_v3, DecodedSwap, RouterDecoder, SwapAmount };
+
+sol! {
+    interface IUniversalRouter {
+        function execute(bytes commands, bytes[] inputs, uint256 deadline) external payable;
+        function execute(bytes commands, bytes[] inputs) external payable;
+    }
+
+    // inputs of the swap commands
+    struct V3SwapExactIn {
+        address recipient;
+        uint256 amountIn;
+        uint256 amountOutMin;
+        bytes path;
+        bool payerIsUser;
+    }
+
+    struct V3SwapExactOut {
+        address recipient;
+        uint256 amountOut;
+        uint256 amountInMax;
+        bytes path;
+        bool payerIsUser;
+    }
+
+    struct V2SwapExactIn {
+        address recipient;
+        uint256 amountIn;
+        uint256 amountOutMin;
+        address[] path;
+        bool payerIsUser;
+    }
+
+    struct V2SwapExactOut {
+        address recipient;
+        uint256 amountOut;
+        uint256 amountInMax;
+        address[] path;
+        bool payerIsUser;
+    }
+}
+
+/// Command types of the Universal Router, in the low bits of each command byte.
+pub mod command {
+    pub const V3_SWAP_EXACT_IN: u8 = 0x00;
+    pub const V3_SWAP_EXACT_OUT: u8 = 0x01;
+    pub const PERMIT2_TRANSFER_FROM: u8 = 0x02;
+    pub const PERMIT2_PERMIT_BATCH: u8 = 0x03;
+    pub const SWEEP: u8 = 0x04;
+    pub const TRANSFER: u8 = 0x05;
+    pub const PAY_PORTION: u8 = 0x06;
+    pub const V2_SWAP_EXACT_IN: u8 = 0x08;
+    pub const V2_SWAP_EXACT_OUT: u8 = 0x09;
+    pub const PERMIT2_PERMIT: u8 = 0x0a;
+    pub const WRAP_ETH: u8 = 0x0b;
+    pub const UNWRAP_WETH: u8 = 0x0c;
+    /// Masks out the allow-revert flag of a command byte.
+    pub const TYPE_MASK: u8 = 0x3f;
+}
+
+/// Decodes the V2 and V3 swap commands of a Universal Router `execute` call. Wraps, transfers
+/// and permits don't swap through pools and are skipped. Returns `None` if any other command is
+/// present (V4 swaps, sub-plans, ...): it may touch pools the decoded swaps don't show.
+pub fn decode(decoder: &RouterDecoder, input: &[u8]) -> Option<Vec<DecodedSwap>> {
+    use IUniversalRouter::IUniversalRouterCalls as Calls;
+
+    let (commands, inputs) = match Calls::abi_decode(input).ok()? {
+        Calls::execute_0(call) => (call.commands, call.inputs),
+        Calls::execute_1(call) => (call.commands, call.inputs),
+    };
+    // the router reverts on a length mismatch
+    if commands.len() != inputs.len() {
+        return None;
+    }
+    let mut swaps = Vec::new();
+    for (command, input) in commands.iter().zip(&inputs) {
+        let swap = match command & command::TYPE_MASK {
+            command::V3_SWAP_EXACT_IN => {
+                let params = V3SwapExactIn::abi_decode_params(input).ok()?;
+                uniswap_v3::path_swap(&decoder.v3_factory, &params.path, SwapAmount::ExactIn {
+                    amount_in: params.amountIn,
+                    min_amount_out: params.amountOutMin,
+                })
+            }
+            command::V3_SWAP_EXACT_OUT => {
+                let params = V3SwapExactOut::abi_decode_params(input).ok()?;
+                uniswap_v3::path_swap(&decoder.v3_factory, &params.path, SwapAmount::ExactOut {
+                    amount_out: params.amountOut,
+                    max_amount_in: params.amountInMax,
+                })
+            }
+            command::V2_SWAP_EXACT_IN => {
+                let params = V2SwapExactIn::abi_decode_params(input).ok()?;
+                uniswap_v2::swap(&decoder.v2_factory, &params.path, SwapAmount::ExactIn {
+                    amount_in: params.amountIn,
+                    min_amount_out: params.amountOutMin,
+                })
+            }
+            command::V2_SWAP_EXACT_OUT => {
+                let params = V2SwapExactOut::abi_decode_params(input).ok()?;
+                uniswap_v2::swap(&decoder.v2_factory, &params.path, SwapAmount::ExactOut {
+                    amount_out: params.amountOut,
+                    max_amount_in: params.amountInMax,
+                })
+            }
+            command::PERMIT2_TRANSFER_FROM |
+            command::PERMIT2_PERMIT_BATCH |
+            command::SWEEP |
+            command::TRANSFER |
+            command::PAY_PORTION |
+            command::PERMIT2_PERMIT |
+            command::WRAP_ETH |
+            command::UNWRAP_WETH => None,
+            _ => return None,
+        };
+        swaps.extend(swap);
+    }
+    Some(swaps)
+}
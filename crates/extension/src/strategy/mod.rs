@@ -1 +1,14 @@
+pub mod negative_cycle;
 pub mod path_finding;
+
+use clap::ValueEnum;
+
+/// How candidate routes are found, selected at startup.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SearchStrategy {
+    /// Simulate every enumerated cycle (`get_candidates`).
+    #[default]
+    Enumeration,
+    /// Detect negative cycles of `-ln(rate)` with Bellman-Ford, then simulate only those.
+    NegativeCycle,
+}
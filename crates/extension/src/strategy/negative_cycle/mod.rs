@@ -0,0 +1,376 @@
+use std::{ collections::{ HashMap, HashSet }, sync::Arc };
+
+use alloy_primitives::Address;
+use eyre::Error;
+use reth_provider::StateProvider;
+
+use crate::amm;
+
+use super::path_finding::{
+    budget::SearchBudget,
+    candidate::MAX_HOPS,
+    strategy::Strategy,
+    types::{ Hop, RoutePath, Selection },
+    PathFinder,
+};
+
+/// Relaxations smaller than this are float noise, not arbitrage.
+const EPSILON: f64 = 1e-12;
+
+/// Directed swap through one pool, weighted `-ln(rate after fee)`.
+struct Edge {
+    from: usize,
+    to: usize,
+    hop: Hop,
+    weight: f64,
+}
+
+/// Token graph of the current pool states.
+struct RateGraph {
+    tokens: Vec<Address>,
+    edges: Vec<Edge>,
+}
+
+impl RateGraph {
+    fn new() -> Self {
+        Self { tokens: Vec::new(), edges: Vec::new() }
+    }
+
+    fn add_edge(&mut self, index: &mut HashMap<Address, usize>, hop: Hop, weight: f64) {
+        let mut node = |token: Address| {
+            *index.entry(token).or_insert_with(|| {
+                self.tokens.push(token);
+                self.tokens.len() - 1
+            })
+        };
+        let (from, to) = (node(hop.srcToken), node(hop.dstToken));
+        self.edges.push(Edge { from, to, hop, weight });
+    }
+
+    /// Bellman-Ford from `source`. Every edge still relaxing after `|V| - 1` rounds leads to a
+    /// negative cycle, returned as the edge indices in swap order.
+    fn negative_cycles(&self, source: usize) -> Vec<Vec<usize>> {
+        let n = self.tokens.len();
+        let mut dist = vec![f64::INFINITY; n];
+        let mut pred: Vec<Option<usize>> = vec![None; n];
+        dist[source] = 0.0;
+
+        for _ in 1..n {
+            let mut changed = false;
+            for (i, edge) in self.edges.iter().enumerate() {
+                let candidate = dist[edge.from] + edge.weight;
+                if candidate < dist[edge.to] - EPSILON {
+                    dist[edge.to] = candidate;
+                    pred[edge.to] = Some(i);
+                    changed = true;
+                }
+            }
+            if !changed {
+                return Vec::new();
+            }
+        }
+
+        let mut cycles = Vec::new();
+        for (i, edge) in self.edges.iter().enumerate() {
+            if dist[edge.from] + edge.weight >= dist[edge.to] - EPSILON {
+                continue;
+            }
+            let mut pred = pred.clone();
+            pred[edge.to] = Some(i);
+            if let Some(cycle) = self.trace_cycle(&pred, edge.to) {
+                cycles.push(cycle);
+            }
+        }
+        cycles
+    }
+
+    /// Walks predecessors `|V|` times to land on the cycle, then collects it.
+    fn trace_cycle(&self, pred: &[Option<usize>], start: usize) -> Option<Vec<usize>> {
+        let mut node = start;
+        for _ in 0..self.tokens.len() {
+            node = self.edges[pred[node]?].from;
+        }
+
+        let mut cycle = Vec::new();
+        let mut current = node;
+        loop {
+            let edge = pred[current]?;
+            cycle.push(edge);
+            current = self.edges[edge].from;
+            if current == node || cycle.len() > self.tokens.len() {
+                break;
+            }
+        }
+        cycle.reverse();
+        Some(cycle)
+    }
+}
+
+/// Finds candidates as negative cycles of the rate graph, then confirms them with a [`PathFinder`].
+pub struct NegativeCycleFinder<'a, SP>
+    where SP: StateProvider
+{
+    finder: PathFinder<'a, SP>,
+    /// Every indexed pool in both directions, see [`get_hops`].
+    ///
+    /// [`get_hops`]: super::path_finding::candidate::get_hops
+    hops: Arc<Vec<Hop>>,
+    /// Tokens of [`Priority::Beginning`], the sources of the search and the start of every cycle.
+    ///
+    /// [`Priority::Beginning`]: searcher_reth_repository::types::Priority::Beginning
+    beginning_tokens: Arc<HashSet<Address>>,
+}
+
+impl<'a, SP> NegativeCycleFinder<'a, SP>
+    where SP: StateProvider
+{
+    pub fn new(
+        finder: PathFinder<'a, SP>,
+        hops: Arc<Vec<Hop>>,
+        beginning_tokens: Arc<HashSet<Address>>
+    ) -> Self {
+        Self { finder, hops, beginning_tokens }
+    }
+
+    /// Builds the rate graph from the distinct hops of the indexed pools, not only those of the
+    /// (capped) candidates. Hops without native quoting can't be weighted and are left out.
+    fn rate_graph(&self) -> RateGraph {
+        let mut simulator = self.finder.simulator();
+        let mut graph = RateGraph::new();
+        let mut index = HashMap::new();
+        let mut seen = HashSet::new();
+
+        for hop in self.hops.iter() {
+            if !seen.insert((hop.dex, hop.srcToken, hop.dstToken)) {
+                continue;
+            }
+            if let Ok(Some(log_rate)) = amm::log_spot_rate(&mut simulator, hop) {
+                graph.add_edge(&mut index, hop.clone(), -log_rate);
+            }
+        }
+        graph
+    }
+}
+
+impl<'a, SP> Strategy
+    for NegativeCycleFinder<'a, SP>
+    where SP: StateProvider
+{
+    // Main logic:
+    // 1. Weight every distinct hop of the indexed pools with `-ln(spot rate after fee)` from the
+    //    current pool states.
+    // 2. Run Bellman-Ford from every configured beginning token, a negative cycle is a loop whose
+    //    rates multiply to more than 1.
+    // 3. Keep cycles through a beginning token, rotated to start from it, with no repeated pool
+    //    and at most MAX_HOPS hops.
+    // 4. Confirm them with the EVM simulation of the PathFinder.
+    //
+    // The enumerated candidates are not needed, the cycles are found in the whole rate graph.
+    fn filter_candidates(
+        &mut self,
+        _candidates: Vec<RoutePath>,
+        max_profit: u64,
+        min_profit: u64
+    ) -> Result<Selection, Error> {
+        let graph = self.rate_graph();
+        let (cycles, interrupted) = find_cycles(
+            &graph,
+            &self.beginning_tokens,
+            self.finder.budget()
+        );
+        let mut selection = self.finder.filter_candidates(cycles, max_profit, min_profit)?;
+        selection.interrupted |= interrupted;
+        Ok(selection)
+    }
+}
+
+/// Negative cycles of `graph` through the `beginning_tokens`, starting from one of them, without
+/// repeated pools and of at most [`MAX_HOPS`] hops. Also whether `budget` ran out first.
+fn find_cycles(
+    graph: &RateGraph,
+    beginning_tokens: &HashSet<Address>,
+    budget: &SearchBudget
+) -> (Vec<RoutePath>, bool) {
+    let mut seen = HashSet::new();
+    let mut cycles = Vec::new();
+    for (source, token) in graph.tokens.iter().enumerate() {
+        if !beginning_tokens.contains(token) {
+            continue;
+        }
+        // stop looking for cycles once the budget is exhausted
+        if budget.is_exhausted() {
+            return (cycles, true);
+        }
+        for cycle in graph.negative_cycles(source) {
+            let Some(start) = cycle
+                .iter()
+                .position(|edge| beginning_tokens.contains(&graph.edges[*edge].hop.srcToken))
+            else {
+                continue;
+            };
+            let mut hops = cycle
+                .iter()
+                .map(|edge| graph.edges[*edge].hop.clone())
+                .collect::<Vec<_>>();
+            hops.rotate_left(start);
+
+            let pools = hops
+                .iter()
+                .map(|hop| hop.dex)
+                .collect::<HashSet<_>>();
+            if hops.len() < 2 || hops.len() > MAX_HOPS || pools.len() != hops.len() {
+                continue;
+            }
+
+            let mut key = cycle.clone();
+            key.sort_unstable();
+            if seen.insert(key) {
+                cycles.push(RoutePath { hops });
+            }
+        }
+    }
+    (cycles, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{ HashMap, HashSet };
+
+    use alloy_primitives::Address;
+
+    use super::{ find_cycles, Hop, RateGraph, SearchBudget };
+
+    const A: Address = Address::with_last_byte(1);
+    const B: Address = Address::with_last_byte(2);
+    const C: Address = Address::with_last_byte(3);
+    const D: Address = Address::with_last_byte(4);
+
+    fn hop(pool: u8, src_token: Address, dst_token: Address) -> Hop {
+        Hop {
+            dexType: 0,
+            dex: Address::with_last_byte(0xa0 + pool),
+            srcToken: src_token,
+            dstToken: dst_token,
+        }
+    }
+
+    fn graph(rates: &[(u8, Address, Address, f64)]) -> RateGraph {
+        let mut graph = RateGraph::new();
+        let mut index = HashMap::new();
+        for (pool, src_token, dst_token, rate) in rates {
+            graph.add_edge(&mut index, hop(*pool, *src_token, *dst_token), -rate.ln());
+        }
+        graph
+    }
+
+    // A -> B -> C -> A multiplies to 1.2, the reverse direction to 0.768
+    fn arbitrage() -> Vec<(u8, Address, Address, f64)> {
+        vec![
+            (0, A, B, 2.0),
+            (0, B, A, 0.49),
+            (1, B, C, 2.0),
+            (1, C, B, 0.49),
+            (2, C, A, 0.3),
+            (2, A, C, 3.2)
+        ]
+    }
+
+    fn tokens(tokens: &[Address]) -> HashSet<Address> {
+        tokens.iter().copied().collect()
+    }
+
+    #[test]
+    fn finds_the_profitable_direction() {
+        let (cycles, interrupted) = find_cycles(
+            &graph(&arbitrage()),
+            &tokens(&[A]),
+            &SearchBudget::default()
+        );
+
+        assert!(!interrupted);
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].hops, vec![hop(0, A, B), hop(1, B, C), hop(2, C, A)]);
+    }
+
+    #[test]
+    fn finds_nothing_without_arbitrage() {
+        // A -> B -> C -> A multiplies to 0.96, the reverse direction to 0.936
+        let graph = graph(
+            &[
+                (0, A, B, 2.0),
+                (0, B, A, 0.49),
+                (1, B, C, 2.0),
+                (1, C, B, 0.49),
+                (2, C, A, 0.24),
+                (2, A, C, 3.9),
+            ]
+        );
+
+        assert!(graph.negative_cycles(0).is_empty());
+        assert!(find_cycles(&graph, &tokens(&[A, B, C]), &SearchBudget::default()).0.is_empty());
+    }
+
+    #[test]
+    fn starts_cycles_from_a_beginning_token() {
+        let (cycles, _) = find_cycles(
+            &graph(&arbitrage()),
+            &tokens(&[B]),
+            &SearchBudget::default()
+        );
+
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].hops, vec![hop(1, B, C), hop(2, C, A), hop(0, A, B)]);
+    }
+
+    #[test]
+    fn reports_a_cycle_found_from_several_sources_once() {
+        let (cycles, _) = find_cycles(
+            &graph(&arbitrage()),
+            &tokens(&[A, B, C]),
+            &SearchBudget::default()
+        );
+
+        assert_eq!(cycles.len(), 1);
+    }
+
+    #[test]
+    fn skips_cycles_without_a_beginning_token() {
+        let mut rates = arbitrage();
+        rates.extend([(3, D, A, 1.0), (3, A, D, 0.99)]);
+        let graph = graph(&rates);
+
+        // the cycle is reachable from D but doesn't go through it
+        assert!(find_cycles(&graph, &tokens(&[D]), &SearchBudget::default()).0.is_empty());
+        assert!(find_cycles(&graph, &HashSet::new(), &SearchBudget::default()).0.is_empty());
+    }
+
+    #[test]
+    fn skips_cycles_repeating_a_pool() {
+        // both directions of one pool multiply to more than 1
+        let graph = graph(&[(0, A, B, 2.0), (0, B, A, 0.6)]);
+
+        assert!(!graph.negative_cycles(0).is_empty());
+        assert!(find_cycles(&graph, &tokens(&[A]), &SearchBudget::default()).0.is_empty());
+    }
+
+    #[test]
+    fn stops_on_an_exhausted_budget() {
+        let budget = SearchBudget::default();
+        budget.cancel();
+
+        let (cycles, interrupted) = find_cycles(&graph(&arbitrage()), &tokens(&[A]), &budget);
+
+        assert!(cycles.is_empty());
+        assert!(interrupted);
+    }
+
+    #[test]
+    fn traces_the_cycle_behind_a_predecessor_chain() {
+        // edges: 0 A -> B, 1 B -> C, 2 C -> A, 3 D -> A
+        let graph = graph(&[(0, A, B, 1.0), (1, B, C, 1.0), (2, C, A, 1.0), (3, D, A, 1.0)]);
+        let pred = vec![Some(2), Some(0), Some(1), None];
+
+        assert_eq!(graph.trace_cycle(&pred, 1), Some(vec![0, 1, 2]));
+        assert_eq!(graph.trace_cycle(&pred, 3), None);
+    }
+}
@@ -0,0 +1,228 @@
+use std::{ collections::HashMap, fmt::Debug, sync::{ Arc, Mutex } };
+
+use alloy_primitives::{ B256, U256 };
+use clap::ValueEnum;
+
+/// Denominator of the bid shares (basis points).
+pub const BID_SHARE_PRECISION: u64 = 10_000;
+
+/// Picks the bid paid to the block builder for a route, out of its profit.
+///
+/// Bids are paid as priority fee on top of the configured one, so they are subtracted from the
+/// net profit before it is checked against the profit thresholds.
+pub trait BiddingPolicy: Debug + Send + Sync {
+    /// Bid in wei for the route `route_id` whose profit after gas is worth `margin` wei. Bids over
+    /// `margin` are capped to it.
+    fn bid(&self, route_id: B256, margin: U256) -> U256;
+}
+
+/// How the bid of a route is picked, selected at startup.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum BidPolicyKind {
+    /// A fixed share of the profit.
+    #[default]
+    Fixed,
+    /// A fixed share of the profit, up to a cap in wei.
+    Capped,
+    /// A share of the profit that grows as the inclusion rate of the route drops.
+    Competition,
+}
+
+/// Bids `share_bps` of the profit, bids nothing by default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FixedShare {
+    pub share_bps: u64,
+}
+
+impl FixedShare {
+    pub fn new(share_bps: u64) -> Self {
+        Self { share_bps: share_bps.min(BID_SHARE_PRECISION) }
+    }
+}
+
+impl BiddingPolicy for FixedShare {
+    fn bid(&self, _route_id: B256, margin: U256) -> U256 {
+        share_of(margin, self.share_bps)
+    }
+}
+
+/// Bids `share_bps` of the profit, never more than `cap` wei.
+#[derive(Debug, Clone, Copy)]
+pub struct CappedShare {
+    pub share: FixedShare,
+    pub cap: U256,
+}
+
+impl CappedShare {
+    pub fn new(share_bps: u64, cap: U256) -> Self {
+        Self { share: FixedShare::new(share_bps), cap }
+    }
+}
+
+impl BiddingPolicy for CappedShare {
+    fn bid(&self, route_id: B256, margin: U256) -> U256 {
+        self.share.bid(route_id, margin).min(self.cap)
+    }
+}
+
+/// Bids between `min_share_bps` and `max_share_bps` of the profit depending on how often the
+/// bundles of the route were included: a route that always lands bids the min share, a route
+/// that never does bids the max share. Routes with fewer than `min_samples` submissions bid
+/// halfway.
+#[derive(Debug, Clone)]
+pub struct CompetitionAdjusted {
+    pub min_share_bps: u64,
+    pub max_share_bps: u64,
+    pub min_samples: u64,
+    pub history: Arc<InclusionHistory>,
+}
+
+impl CompetitionAdjusted {
+    pub fn new(
+        min_share_bps: u64,
+        max_share_bps: u64,
+        min_samples: u64,
+        history: Arc<InclusionHistory>
+    ) -> Self {
+        let max_share_bps = max_share_bps.min(BID_SHARE_PRECISION);
+        Self {
+            min_share_bps: min_share_bps.min(max_share_bps),
+            max_share_bps,
+            min_samples: min_samples.max(1),
+            history,
+        }
+    }
+}
+
+impl BiddingPolicy for CompetitionAdjusted {
+    fn bid(&self, route_id: B256, margin: U256) -> U256 {
+        let spread = self.max_share_bps - self.min_share_bps;
+        let stats = self.history.stats(route_id);
+        let share_bps = if stats.submitted < self.min_samples {
+            self.min_share_bps + spread / 2
+        } else {
+            let missed = stats.submitted.saturating_sub(stats.included);
+            self.min_share_bps + spread * missed / stats.submitted
+        };
+        share_of(margin, share_bps)
+    }
+}
+
+fn share_of(amount: U256, share_bps: u64) -> U256 {
+    amount * U256::from(share_bps) / U256::from(BID_SHARE_PRECISION)
+}
+
+/// Settled submissions of the bundles of a route.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RouteInclusion {
+    pub submitted: u64,
+    /// Submissions included without reverting.
+    pub included: u64,
+}
+
+/// Inclusion rate of our own bundles per route, fed by the [`InclusionTracker`].
+///
+/// [`InclusionTracker`]: crate::tracker::InclusionTracker
+#[derive(Debug, Default)]
+pub struct InclusionHistory {
+    routes: Mutex<HashMap<B256, RouteInclusion>>,
+}
+
+impl InclusionHistory {
+    pub fn stats(&self, route_id: B256) -> RouteInclusion {
+        self.routes.lock().unwrap().get(&route_id).copied().unwrap_or_default()
+    }
+
+    /// Records a settled submission of `route_id`.
+    pub fn record(&self, route_id: B256, included: bool) {
+        let mut routes = self.routes.lock().unwrap();
+        let route = routes.entry(route_id).or_default();
+        route.submitted += 1;
+        route.included += u64::from(included);
+    }
+
+    /// Removes a submission of `route_id` recorded for a block that was reorged out.
+    pub fn retract(&self, route_id: B256, included: bool) {
+        if let Some(route) = self.routes.lock().unwrap().get_mut(&route_id) {
+            route.submitted = route.submitted.saturating_sub(1);
+            route.included = route.included.saturating_sub(u64::from(included));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use alloy_primitives::{ B256, U256 };
+
+    use super::{ BiddingPolicy, CompetitionAdjusted, InclusionHistory };
+
+    const ROUTE: B256 = B256::with_last_byte(1);
+
+    /// Bids between 10% and 90% of the margin, adjusted after 4 submissions.
+    fn policy() -> CompetitionAdjusted {
+        CompetitionAdjusted::new(1000, 9000, 4, Arc::new(InclusionHistory::default()))
+    }
+
+    fn record(policy: &CompetitionAdjusted, included: u64, missed: u64) {
+        for _ in 0..included {
+            policy.history.record(ROUTE, true);
+        }
+        for _ in 0..missed {
+            policy.history.record(ROUTE, false);
+        }
+    }
+
+    fn bid(policy: &CompetitionAdjusted) -> U256 {
+        policy.bid(ROUTE, U256::from(10_000))
+    }
+
+    #[test]
+    fn bids_halfway_until_enough_samples() {
+        let policy = policy();
+        assert_eq!(bid(&policy), U256::from(5000));
+
+        record(&policy, 0, 3);
+        assert_eq!(bid(&policy), U256::from(5000));
+    }
+
+    #[test]
+    fn interpolates_the_share_with_the_missed_submissions() {
+        let cases = [(4, 0, 1000), (3, 1, 3000), (2, 2, 5000), (1, 3, 7000), (0, 4, 9000)];
+        for (included, missed, share_bps) in cases {
+            let policy = policy();
+            record(&policy, included, missed);
+            assert_eq!(bid(&policy), U256::from(share_bps), "{included} of {}", included + missed);
+        }
+
+        // the share rounds down between the steps of the samples
+        let policy = policy();
+        record(&policy, 2, 1);
+        record(&policy, 0, 3);
+        assert_eq!(bid(&policy), U256::from(1000 + 8000 * 4 / 6));
+    }
+
+    #[test]
+    fn forgets_retracted_submissions() {
+        let policy = policy();
+        record(&policy, 0, 4);
+        assert_eq!(bid(&policy), U256::from(9000));
+
+        policy.history.retract(ROUTE, false);
+        assert_eq!(bid(&policy), U256::from(5000));
+    }
+
+    #[test]
+    fn clamps_the_shares_and_samples() {
+        let history = Arc::new(InclusionHistory::default());
+        let policy = CompetitionAdjusted::new(12_000, 20_000, 0, history.clone());
+        assert_eq!((policy.min_share_bps, policy.max_share_bps), (10_000, 10_000));
+        assert_eq!(policy.min_samples, 1);
+
+        // a min share over the max one bids the max share whatever the inclusion rate
+        let policy = CompetitionAdjusted::new(8000, 6000, 1, history);
+        record(&policy, 1, 0);
+        assert_eq!(bid(&policy), U256::from(6000));
+    }
+}
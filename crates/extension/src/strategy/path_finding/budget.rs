@@ -0,0 +1,63 @@
+use std::{
+    sync::{ atomic::{ AtomicBool, Ordering }, Arc },
+    time::{ Duration, Instant },
+};
+
+/// Bounds a search in time: it is exhausted once its deadline passes or it is cancelled.
+///
+/// Clones share the cancellation, so a clone kept outside of the search can cancel it.
+#[derive(Debug, Clone, Default)]
+pub struct SearchBudget {
+    deadline: Option<Instant>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl SearchBudget {
+    /// A budget expiring `time` from now.
+    pub fn new(time: Duration) -> Self {
+        Self { deadline: Instant::now().checked_add(time), cancelled: Arc::default() }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed) ||
+            self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ thread, time::Duration };
+
+    use super::SearchBudget;
+
+    #[test]
+    fn default_budget_never_runs_out() {
+        assert!(!SearchBudget::default().is_exhausted());
+        // a deadline past the end of time is no deadline
+        assert!(!SearchBudget::new(Duration::MAX).is_exhausted());
+    }
+
+    #[test]
+    fn runs_out_at_its_deadline() {
+        assert!(SearchBudget::new(Duration::ZERO).is_exhausted());
+
+        let budget = SearchBudget::new(Duration::from_millis(20));
+        assert!(!budget.is_exhausted());
+        thread::sleep(Duration::from_millis(30));
+        assert!(budget.is_exhausted());
+    }
+
+    #[test]
+    fn clones_share_the_cancellation() {
+        let budget = SearchBudget::new(Duration::from_secs(3600));
+        let search = budget.clone();
+        assert!(!search.is_exhausted());
+
+        thread::spawn(move || budget.cancel()).join().unwrap();
+        assert!(search.is_exhausted());
+    }
+}
@@ -1,85 +1,336 @@
+use std::collections::{ HashMap, HashSet };
+
 use alloy_primitives::Address;
 
+use eyre::{ eyre, Result };
 use itertools::{ Either, Itertools };
-use searcher_reth_repository::types::Priority;
+use searcher_reth_repository::types::{ Pool, Priority };
+
+use super::types::{ Hop, RoutePath };
+
+/// Smallest cycle: A -> B -> A.
+pub const MIN_HOPS: usize = 2;
+/// Longest cycle the enumerator is allowed to produce.
+pub const MAX_HOPS: usize = 6;
+
+/// Bounds of the cycle enumeration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CandidateLimits {
+    pub max_hops: usize,
+    pub max_candidates: usize,
+}
 
-use super::types::{Hop, RoutePath};
+impl CandidateLimits {
+    pub fn new(max_hops: usize, max_candidates: usize) -> Result<Self> {
+        if !(MIN_HOPS..=MAX_HOPS).contains(&max_hops) {
+            return Err(eyre!("max hops must be within {MIN_HOPS}..={MAX_HOPS}, got {max_hops}"));
+        }
+        Ok(Self { max_hops, max_candidates })
+    }
+}
+
+impl Default for CandidateLimits {
+    fn default() -> Self {
+        Self { max_hops: 3, max_candidates: 100_000 }
+    }
+}
+
+/// Token graph built from real pool edges: token -> pools trading it.
+struct TokenGraph<'a> {
+    edges: HashMap<Address, Vec<&'a Pool>>,
+}
+
+impl<'a> TokenGraph<'a> {
+    /// Only pools whose both tokens are registered become edges.
+    fn new(pools: &'a [Pool], tokens: &HashSet<Address>) -> Self {
+        let mut edges: HashMap<Address, Vec<&'a Pool>> = HashMap::new();
+        for pool in pools {
+            if pool.token0 == pool.token1 ||
+                !tokens.contains(&pool.token0) ||
+                !tokens.contains(&pool.token1)
+            {
+                continue;
+            }
+            edges.entry(pool.token0).or_default().push(pool);
+            edges.entry(pool.token1).or_default().push(pool);
+        }
+        Self { edges }
+    }
+
+    /// Pools trading `token`, paired with the token received for it.
+    fn neighbours(&self, token: &Address) -> impl Iterator<Item = (&'a Pool, Address)> {
+        self.edges
+            .get(token)
+            .into_iter()
+            .flatten()
+            .filter_map(move |pool| pool.other_token(token).map(|other| (*pool, other)))
+    }
+
+    /// Every edge of the graph, once per swap direction, by source token and then pool order.
+    fn hops(&self) -> Vec<Hop> {
+        self.edges
+            .keys()
+            .sorted()
+            .flat_map(|token| {
+                self.neighbours(token).map(move |(pool, other)| hop(pool, *token, other))
+            })
+            .collect()
+    }
+}
+
+/// Depth-first enumeration of the cycles of exactly `hops` hops from one start token.
+struct CycleEnumerator<'g, 'a> {
+    graph: &'g TokenGraph<'a>,
+    intermediates: &'g HashSet<Address>,
+    start_token: Address,
+    hops: usize,
+    max_candidates: usize,
+    path: Vec<Hop>,
+    visited_tokens: HashSet<Address>,
+    used_pools: HashSet<Address>,
+}
+
+impl CycleEnumerator<'_, '_> {
+    fn walk(&mut self, token: Address, route_paths: &mut Vec<RoutePath>) {
+        let graph = self.graph;
+        for (pool, next_token) in graph.neighbours(&token) {
+            if route_paths.len() >= self.max_candidates {
+                return;
+            }
+            // pruning: no repeated pool
+            if self.used_pools.contains(&pool.address) {
+                continue;
+            }
+
+            let last_hop = self.path.len() + 1 == self.hops;
+            if last_hop {
+                if next_token == self.start_token {
+                    let mut hops = self.path.clone();
+                    hops.push(hop(pool, token, next_token));
+                    route_paths.push(RoutePath { hops });
+                }
+                continue;
+            }
+
+            // pruning: no repeated intermediate token, never close the cycle early
+            if
+                next_token == self.start_token ||
+                !self.intermediates.contains(&next_token) ||
+                self.visited_tokens.contains(&next_token)
+            {
+                continue;
+            }
+
+            self.path.push(hop(pool, token, next_token));
+            self.visited_tokens.insert(next_token);
+            self.used_pools.insert(pool.address);
+            self.walk(next_token, route_paths);
+            self.used_pools.remove(&pool.address);
+            self.visited_tokens.remove(&next_token);
+            self.path.pop();
+        }
+    }
+}
+
+fn hop(pool: &Pool, src_token: Address, dst_token: Address) -> Hop {
+    Hop { dexType: pool.dex_type, dex: pool.address, srcToken: src_token, dstToken: dst_token }
+}
+
+/// Both swap directions of every pool between registered tokens: the whole token graph that
+/// [`get_candidates`] walks, regardless of the candidate limits.
+pub fn get_hops(pools: &[Pool], tokens: &[(Address, Priority)]) -> Vec<Hop> {
+    let all_tokens = tokens
+        .iter()
+        .map(|(addr, _)| *addr)
+        .collect::<HashSet<_>>();
+    TokenGraph::new(pools, &all_tokens).hops()
+}
+
+/// Tokens of [`Priority::Beginning`], where every searched route starts and ends.
+pub fn get_beginning_tokens(tokens: &[(Address, Priority)]) -> HashSet<Address> {
+    tokens
+        .iter()
+        .filter(|(_, p)| *p == Priority::Beginning)
+        .map(|(addr, _)| *addr)
+        .collect()
+}
 
 // A -> B -> A
 // A -> B -> C -> A
+// ...
+// A -> B -> ... -> A (up to `limits.max_hops`)
+//
+// Only cycles made of existing pools are emitted, in both traversal directions. Shorter cycles are
+// enumerated first, so `limits.max_candidates` drops the longest ones.
 pub fn get_candidates(
-    dexs: Vec<(Address, u8)>,
-    tokens: Vec<(Address, Priority)>
+    pools: Vec<Pool>,
+    tokens: Vec<(Address, Priority)>,
+    limits: CandidateLimits
 ) -> Vec<RoutePath> {
     let mut route_paths = Vec::new();
 
-    let (beginning_tokens, other_tokens): (Vec<Address>, Vec<Address>) = tokens
+    let (beginning_tokens, other_tokens): (Vec<Address>, HashSet<Address>) = tokens
         .iter()
         .partition_map(|(addr, p)| {
             if *p == Priority::Beginning { Either::Left(*addr) } else { Either::Right(*addr) }
         });
+    let all_tokens = tokens
+        .iter()
+        .map(|(addr, _)| *addr)
+        .collect::<HashSet<_>>();
+    let graph = TokenGraph::new(&pools, &all_tokens);
 
-    // Case 1: A -> B -> A (2-hop paths)
-    for start_token in &beginning_tokens {
-        for inter_token in &other_tokens {
-            for dex_hops in dexs.iter().permutations(2) {
-                let hops = vec![
-                    // A -> B
-                    Hop {
-                        dexType: dex_hops[0].1,
-                        dex: dex_hops[0].0,
-                        srcToken: *start_token,
-                        dstToken: *inter_token,
-                    },
-                    // B -> A
-                    Hop {
-                        dexType: dex_hops[1].1,
-                        dex: dex_hops[1].0,
-                        srcToken: *inter_token,
-                        dstToken: *start_token,
-                    }
-                ];
-                route_paths.push(RoutePath {
-                    hops,
-                });
-            }
+    for hops in MIN_HOPS..=limits.max_hops.min(MAX_HOPS) {
+        for start_token in &beginning_tokens {
+            let mut enumerator = CycleEnumerator {
+                graph: &graph,
+                intermediates: &other_tokens,
+                start_token: *start_token,
+                hops,
+                max_candidates: limits.max_candidates,
+                path: Vec::with_capacity(hops),
+                visited_tokens: HashSet::new(),
+                used_pools: HashSet::new(),
+            };
+            enumerator.walk(*start_token, &mut route_paths);
         }
     }
 
-    // Case 2: A -> B -> C -> A (3-hop paths)
-    for start_token in &beginning_tokens {
-        for inter_token_pair in other_tokens.iter().combinations(2) {
-            for dex_hops in dexs.iter().permutations(3) {
-                let hops = vec![
-                    // A -> B
-                    Hop {
-                        dexType: dex_hops[0].1,
-                        dex: dex_hops[0].0,
-                        srcToken: *start_token,
-                        dstToken: *inter_token_pair[0],
-                    },
-                    // B -> C
-                    Hop {
-                        dexType: dex_hops[1].1,
-                        dex: dex_hops[1].0,
-                        srcToken: *inter_token_pair[0],
-                        dstToken: *inter_token_pair[1],
-                    },
-                    // C -> A
-                    Hop {
-                        dexType: dex_hops[2].1,
-                        dex: dex_hops[2].0,
-                        srcToken: *inter_token_pair[1],
-                        dstToken: *start_token,
-                    }
-                ];
-
-                route_paths.push(RoutePath {
-                    hops,
-                });
-            }
+    route_paths
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use alloy_primitives::Address;
+    use searcher_reth_repository::types::{ dex_type, Pool, Priority };
+
+    use super::{ get_candidates, get_hops, CandidateLimits, RoutePath };
+
+    const USDC: Address = Address::with_last_byte(1);
+    const USDT: Address = Address::with_last_byte(2);
+    const WETH: Address = Address::with_last_byte(3);
+    const DAI: Address = Address::with_last_byte(4);
+    const UNKNOWN: Address = Address::with_last_byte(5);
+
+    fn pool(address: u8, token0: Address, token1: Address) -> Pool {
+        let address = Address::with_last_byte(address);
+        Pool { address, dex_type: dex_type::UNISWAP_V2, token0, token1 }
+    }
+
+    fn tokens() -> Vec<(Address, Priority)> {
+        vec![
+            (USDC, Priority::Beginning),
+            (USDT, Priority::Beginning),
+            (WETH, Priority::High),
+            (DAI, Priority::Medium)
+        ]
+    }
+
+    /// Two USDC/WETH pools and a USDC -> WETH -> DAI -> USDC triangle, a USDC/USDT pool that no
+    /// cycle can cross and a pool of an unregistered token.
+    fn pools() -> Vec<Pool> {
+        vec![
+            pool(0x11, USDC, WETH),
+            pool(0x12, WETH, USDC),
+            pool(0x13, WETH, DAI),
+            pool(0x14, DAI, USDC),
+            pool(0x15, USDC, USDT),
+            pool(0x16, WETH, UNKNOWN)
+        ]
+    }
+
+    fn candidates(max_hops: usize, max_candidates: usize) -> Vec<RoutePath> {
+        let limits = CandidateLimits::new(max_hops, max_candidates).unwrap();
+        get_candidates(pools(), tokens(), limits)
+    }
+
+    /// The pools of a route, as their last address byte.
+    fn pools_of(route_path: &RoutePath) -> Vec<u8> {
+        route_path.hops
+            .iter()
+            .map(|hop| hop.dex.0[19])
+            .collect()
+    }
+
+    #[test]
+    fn enumerates_cycles_through_distinct_pools_in_both_directions() {
+        let route_paths = candidates(2, usize::MAX);
+        assert_eq!(route_paths.iter().map(pools_of).collect::<Vec<_>>(), vec![
+            vec![0x11, 0x12],
+            vec![0x12, 0x11]
+        ]);
+        for route_path in &route_paths {
+            let tokens = route_path.hops
+                .iter()
+                .map(|hop| (hop.srcToken, hop.dstToken))
+                .collect::<Vec<_>>();
+            assert_eq!(tokens, vec![(USDC, WETH), (WETH, USDC)]);
         }
     }
 
-    route_paths
+    #[test]
+    fn enumerates_shorter_cycles_first() {
+        let route_paths = candidates(3, usize::MAX);
+        assert_eq!(route_paths.iter().map(pools_of).collect::<Vec<_>>(), vec![
+            vec![0x11, 0x12],
+            vec![0x12, 0x11],
+            vec![0x11, 0x13, 0x14],
+            vec![0x12, 0x13, 0x14],
+            vec![0x14, 0x13, 0x11],
+            vec![0x14, 0x13, 0x12]
+        ]);
+    }
+
+    #[test]
+    fn never_repeats_a_pool_or_an_intermediate_token() {
+        let route_paths = candidates(6, usize::MAX);
+        assert!(!route_paths.is_empty());
+        for route_path in &route_paths {
+            let hops = &route_path.hops;
+            let start_token = hops[0].srcToken;
+            assert_eq!(hops[hops.len() - 1].dstToken, start_token);
+            assert!(hops.windows(2).all(|pair| pair[0].dstToken == pair[1].srcToken));
+
+            let pools = hops
+                .iter()
+                .map(|hop| hop.dex)
+                .collect::<HashSet<_>>();
+            assert_eq!(pools.len(), hops.len(), "{route_path:?}");
+            let intermediates = hops[..hops.len() - 1]
+                .iter()
+                .map(|hop| hop.dstToken)
+                .collect::<Vec<_>>();
+            assert_eq!(intermediates.iter().collect::<HashSet<_>>().len(), intermediates.len());
+            assert!(
+                intermediates.iter().all(|token| *token == WETH || *token == DAI),
+                "{route_path:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn truncates_to_max_candidates() {
+        let all = candidates(3, usize::MAX);
+        assert_eq!(candidates(3, 3), all[..3]);
+        assert!(candidates(3, 0).is_empty());
+    }
+
+    #[test]
+    fn hops_cover_both_directions_in_a_stable_order() {
+        let hops = get_hops(&pools(), &tokens());
+        // every pool between registered tokens, both ways
+        assert_eq!(hops.len(), 10);
+        assert!(hops.windows(2).all(|pair| pair[0].srcToken <= pair[1].srcToken));
+        assert!(!hops.iter().any(|hop| hop.srcToken == UNKNOWN || hop.dstToken == UNKNOWN));
+        assert_eq!(
+            hops
+                .iter()
+                .filter(|hop| hop.srcToken == USDC)
+                .map(|hop| hop.dex.0[19])
+                .collect::<Vec<_>>(),
+            vec![0x11, 0x12, 0x14, 0x15]
+        );
+    }
 }
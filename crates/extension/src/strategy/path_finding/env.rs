@@ -0,0 +1,58 @@
+use alloy_consensus::BlockHeader;
+use reth_chainspec::{ EthChainSpec, EthereumHardforks };
+use reth_node_ethereum::evm::revm_spec_by_timestamp_and_block_number;
+use reth_revm::{
+    context::{ BlockEnv, CfgEnv },
+    primitives::{ hardfork::SpecId, Address },
+};
+
+use super::gas::next_base_fee;
+
+/// Block and chain environment the simulations run in.
+#[derive(Debug, Clone, Default)]
+pub struct SimulationEnv {
+    pub cfg: CfgEnv,
+    pub block: BlockEnv,
+}
+
+impl SimulationEnv {
+    /// Environment of the block following `header`: next number and timestamp, predicted base
+    /// fee and excess blob gas, and the hardfork active at that height. The coinbase is the
+    /// configured one, or `header`'s beneficiary if unset. Prevrandao and gas limit of the next
+    /// block are unknown and carried over from `header`.
+    pub fn next_block<C, H>(
+        chain_spec: &C,
+        header: &H,
+        block_time: u64,
+        coinbase: Option<Address>
+    ) -> Self
+        where C: EthChainSpec + EthereumHardforks, H: BlockHeader
+    {
+        let number = header.number() + 1;
+        let timestamp = header.timestamp() + block_time;
+        let spec = revm_spec_by_timestamp_and_block_number(chain_spec, timestamp, number);
+
+        let mut cfg = CfgEnv::default();
+        cfg.chain_id = chain_spec.chain().id();
+        cfg.spec = spec;
+
+        let mut block = BlockEnv {
+            number,
+            beneficiary: coinbase.unwrap_or_else(|| header.beneficiary()),
+            timestamp,
+            gas_limit: header.gas_limit(),
+            basefee: next_base_fee(header, chain_spec.base_fee_params_at_timestamp(timestamp)),
+            prevrandao: Some(header.mix_hash().unwrap_or_default()),
+            ..Default::default()
+        };
+        let excess_blob_gas = chain_spec
+            .blob_params_at_timestamp(timestamp)
+            .and_then(|blob_params| header.next_block_excess_blob_gas(blob_params));
+        if let Some(excess_blob_gas) = excess_blob_gas {
+            let is_prague = spec.is_enabled_in(SpecId::PRAGUE);
+            block.set_blob_excess_gas_and_price(excess_blob_gas, is_prague);
+        }
+
+        Self { cfg, block }
+    }
+}
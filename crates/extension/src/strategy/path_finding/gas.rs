@@ -0,0 +1,142 @@
+use std::{ fmt::Debug, sync::Arc };
+
+use alloy_consensus::BlockHeader;
+use alloy_eips::eip1559::BaseFeeParams;
+use alloy_primitives::{ Address, U256 };
+
+/// Gas every transaction pays before executing.
+pub const TX_BASE_GAS: u64 = 21_000;
+/// Calldata gas per zero byte.
+pub const ZERO_BYTE_GAS: u64 = 4;
+/// Calldata gas per non-zero byte (EIP-2028).
+pub const NON_ZERO_BYTE_GAS: u64 = 16;
+
+/// Converts native-token amounts (wei) into amounts of another token.
+pub trait PriceSource: Debug + Send + Sync {
+    /// Value of `amount` wei in `token`, `None` if `token` has no known price.
+    fn native_to_token(&self, token: Address, amount: U256) -> Option<U256>;
+
+    /// Value of `amount` of `token` in wei, `None` if `token` has no known price.
+    fn token_to_native(&self, token: Address, amount: U256) -> Option<U256>;
+}
+
+/// Gas price assumed for the next block and how to express gas costs in a start token.
+#[derive(Debug, Clone)]
+pub struct GasPricing {
+    /// Predicted base fee plus priority fee, in wei.
+    pub gas_price: U256,
+    /// `None` without a native token: gas can't be priced and profits are reported gross.
+    pub prices: Option<Arc<dyn PriceSource>>,
+}
+
+impl GasPricing {
+    pub fn new(gas_price: U256, prices: Arc<dyn PriceSource>) -> Self {
+        Self { gas_price, prices: Some(prices) }
+    }
+
+    /// Pricing without a native token: gas costs nothing and no bid can be valued.
+    pub fn gross(gas_price: U256) -> Self {
+        Self { gas_price, prices: None }
+    }
+
+    pub fn is_gross(&self) -> bool {
+        self.prices.is_none()
+    }
+
+    /// Cost of `gas_used` in `token`, zero when [gross](Self::gross), `None` if `token` can't be
+    /// priced.
+    pub fn gas_cost(&self, token: Address, gas_used: u64) -> Option<U256> {
+        match &self.prices {
+            Some(prices) => prices.native_to_token(token, U256::from(gas_used) * self.gas_price),
+            None => Some(U256::ZERO),
+        }
+    }
+
+    /// Value of `amount` of `token` in wei, to compare profits made in different tokens.
+    pub fn native_value(&self, token: Address, amount: U256) -> Option<U256> {
+        self.prices.as_ref()?.token_to_native(token, amount)
+    }
+
+    /// Value of `amount` wei in `token`.
+    pub fn token_value(&self, token: Address, amount: U256) -> Option<U256> {
+        self.prices.as_ref()?.native_to_token(token, amount)
+    }
+}
+
+impl Default for GasPricing {
+    fn default() -> Self {
+        Self::gross(U256::ZERO)
+    }
+}
+
+/// Gas a transaction pays on top of its execution: the base cost and its calldata.
+pub fn intrinsic_gas(input: &[u8]) -> u64 {
+    let zero_bytes = input
+        .iter()
+        .filter(|byte| **byte == 0)
+        .count() as u64;
+    let non_zero_bytes = (input.len() as u64) - zero_bytes;
+    TX_BASE_GAS + zero_bytes * ZERO_BYTE_GAS + non_zero_bytes * NON_ZERO_BYTE_GAS
+}
+
+/// EIP-1559 base fee of the block after `header`.
+pub fn next_base_fee<H: BlockHeader>(header: &H, params: BaseFeeParams) -> u64 {
+    header.next_block_base_fee(params).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_consensus::Header;
+    use alloy_eips::eip1559::BaseFeeParams;
+
+    use super::{ intrinsic_gas, next_base_fee, TX_BASE_GAS };
+
+    const GWEI: u64 = 1_000_000_000;
+
+    fn header(gas_used: u64) -> Header {
+        Header {
+            gas_limit: 30_000_000,
+            gas_used,
+            base_fee_per_gas: Some(GWEI),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn charges_calldata_per_byte() {
+        assert_eq!(intrinsic_gas(&[]), TX_BASE_GAS);
+        assert_eq!(intrinsic_gas(&[0, 0]), TX_BASE_GAS + 2 * 4);
+        assert_eq!(intrinsic_gas(&[1, 0xff]), TX_BASE_GAS + 2 * 16);
+        assert_eq!(intrinsic_gas(&[0, 1, 0, 2, 3]), TX_BASE_GAS + 2 * 4 + 3 * 16);
+    }
+
+    #[test]
+    fn raises_the_base_fee_above_the_gas_target() {
+        let params = BaseFeeParams::ethereum();
+
+        // a full block raises the base fee by 1/8
+        assert_eq!(next_base_fee(&header(30_000_000), params), GWEI + GWEI / 8);
+        assert_eq!(next_base_fee(&header(22_500_000), params), GWEI + GWEI / 16);
+    }
+
+    #[test]
+    fn keeps_the_base_fee_at_the_gas_target() {
+        assert_eq!(next_base_fee(&header(15_000_000), BaseFeeParams::ethereum()), GWEI);
+    }
+
+    #[test]
+    fn lowers_the_base_fee_below_the_gas_target() {
+        let params = BaseFeeParams::ethereum();
+
+        // an empty block lowers the base fee by 1/8
+        assert_eq!(next_base_fee(&header(0), params), GWEI - GWEI / 8);
+        assert_eq!(next_base_fee(&header(7_500_000), params), GWEI - GWEI / 16);
+    }
+
+    #[test]
+    fn has_no_base_fee_before_london() {
+        let header = Header { base_fee_per_gas: None, ..header(15_000_000) };
+
+        assert_eq!(next_base_fee(&header, BaseFeeParams::ethereum()), 0);
+    }
+}
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+
+use alloy_primitives::Address;
+use searcher_reth_repository::types::dex_type;
+
+use crate::amm::balancer;
+
+use super::types::RoutePath;
+
+/// Maps every pool to the route paths that swap through it.
+///
+/// Balancer pools keep their balances in the Vault's storage, so their routes are also indexed
+/// under the Vault: a swap through any Balancer pool touches every Balancer route.
+#[derive(Debug, Clone, Default)]
+pub struct RouteIndex {
+    pools: HashMap<Address, Vec<usize>>,
+}
+
+impl RouteIndex {
+    pub fn new(route_paths: &[RoutePath]) -> Self {
+        let mut pools: HashMap<Address, Vec<usize>> = HashMap::new();
+        for (index, route_path) in route_paths.iter().enumerate() {
+            for hop in &route_path.hops {
+                let mut addresses = vec![hop.dex];
+                if hop.dexType == dex_type::BALANCER_WEIGHTED {
+                    addresses.push(balancer::VAULT);
+                }
+                for address in addresses {
+                    let routes = pools.entry(address).or_default();
+                    // a route may go through the same pool twice
+                    if routes.last() != Some(&index) {
+                        routes.push(index);
+                    }
+                }
+            }
+        }
+        Self { pools }
+    }
+
+    pub fn contains(&self, pool: &Address) -> bool {
+        self.pools.contains_key(pool)
+    }
+
+    /// Returns the sorted, deduplicated indices of the routes touching any of `pools`.
+    pub fn touched_routes<'a>(&self, pools: impl IntoIterator<Item = &'a Address>) -> Vec<usize> {
+        let mut routes = pools
+            .into_iter()
+            .filter_map(|pool| self.pools.get(pool))
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>();
+        routes.sort_unstable();
+        routes.dedup();
+        routes
+    }
+}
@@ -1,47 +1,117 @@
 pub mod strategy;
 pub mod types;
 pub mod candidate;
+pub mod simulator;
+pub mod index;
+pub mod sizing;
+pub mod gas;
+pub mod env;
+pub mod overlay;
+pub mod budget;
+pub mod bidding;
 
-use reth_provider::{ BlockHashReader, DBProvider, LatestStateProviderRef, StateCommitmentProvider };
+use std::{ collections::HashMap, sync::Arc };
+
+use alloy_primitives::{ Address, B256 };
+use reth_provider::StateProvider;
 use reth_revm::{
-    context::{ BlockEnv, CfgEnv, Evm, TxEnv },
     database::StateProviderDatabase,
-    db::CacheDB,
-    handler::{ instructions::EthInstructions, EthPrecompiles },
-    interpreter::interpreter::EthInterpreter,
-    state::{ AccountInfo, Bytecode },
-    Context,
-    MainBuilder,
-    MainContext,
+    db::BundleState,
+    state::Bytecode,
+    DatabaseRef,
 };
-use types::DEPLOYED_ADDRESS;
-
-type PathFinderCtx<'a, DB> = Context<
-    BlockEnv,
-    TxEnv,
-    CfgEnv,
-    CacheDB<StateProviderDatabase<LatestStateProviderRef<'a, DB>>>
->;
-
-pub struct PathFinder<'a, DB> where DB: DBProvider + BlockHashReader + StateCommitmentProvider {
-    evm: Evm<
-        PathFinderCtx<'a, DB>,
-        (),
-        EthInstructions<EthInterpreter, PathFinderCtx<'a, DB>>,
-        EthPrecompiles
-    >,
+use crate::amm::uniswap_v2::UniswapV2Fees;
+use bidding::{ BiddingPolicy, FixedShare };
+use budget::SearchBudget;
+use env::SimulationEnv;
+use gas::GasPricing;
+use overlay::BundleOverlay;
+use simulator::Simulator;
+
+/// Read-only state snapshot shared by every simulator of a [`PathFinder`].
+pub(crate) type PathFinderDB<'a, SP> = BundleOverlay<'a, StateProviderDatabase<SP>>;
+
+pub struct PathFinder<'a, SP> where SP: StateProvider {
+    db: PathFinderDB<'a, SP>,
+    contract: Bytecode,
+    threads: usize,
+    gas_pricing: GasPricing,
+    env: SimulationEnv,
+    budget: SearchBudget,
+    bidding: Arc<dyn BiddingPolicy>,
+    uniswap_v2_fees: Arc<UniswapV2Fees>,
 }
 
-impl<'a, DB> PathFinder<'a, DB> where DB: DBProvider + BlockHashReader + StateCommitmentProvider {
-    /// Creates a new instance of the PathFinder
-    pub fn new(provider: LatestStateProviderRef<'a, DB>, contract: Bytecode) -> Self {
-        let mut db = CacheDB::new(StateProviderDatabase::new(provider));
-        db.insert_account_info(DEPLOYED_ADDRESS, AccountInfo {
-            code_hash: contract.hash_slow(),
-            code: Some(contract),
-            ..Default::default()
-        });
-        let evm = Context::mainnet().with_db(db).build_mainnet();
-        Self { evm }
+impl<'a, SP> PathFinder<'a, SP> where SP: StateProvider {
+    /// Creates a new instance of the PathFinder over `bundle` layered on the state of `provider`.
+    ///
+    /// `provider` must be the historical state the bundle was executed on, and `block_hashes`
+    /// the hashes of the blocks the bundle covers.
+    pub fn new(
+        provider: SP,
+        bundle: &'a BundleState,
+        block_hashes: HashMap<u64, B256>,
+        contract: Bytecode
+    ) -> Self {
+        Self {
+            db: BundleOverlay::new(StateProviderDatabase::new(provider), bundle, block_hashes),
+            contract,
+            threads: 1,
+            gas_pricing: GasPricing::default(),
+            env: SimulationEnv::default(),
+            budget: SearchBudget::default(),
+            bidding: Arc::new(FixedShare::default()),
+            uniswap_v2_fees: Arc::default(),
+        }
+    }
+
+    /// Sets the number of worker threads used to simulate candidates.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Sets the gas price and price source used to turn gross profits into net profits.
+    pub fn with_gas_pricing(mut self, gas_pricing: GasPricing) -> Self {
+        self.gas_pricing = gas_pricing;
+        self
+    }
+
+    /// Sets the block and chain environment of the simulations.
+    pub fn with_env(mut self, env: SimulationEnv) -> Self {
+        self.env = env;
+        self
+    }
+
+    /// Sets the budget of the search, an exhausted search returns the results found so far.
+    pub fn with_budget(mut self, budget: SearchBudget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Sets the policy picking the bid of every route out of its profit, no bid by default.
+    pub fn with_bidding(mut self, bidding: Arc<dyn BiddingPolicy>) -> Self {
+        self.bidding = bidding;
+        self
+    }
+
+    /// Sets the swap fees of the Uniswap V2 forks, quoted at the Uniswap V2 fee otherwise.
+    pub fn with_uniswap_v2_fees(mut self, uniswap_v2_fees: Arc<UniswapV2Fees>) -> Self {
+        self.uniswap_v2_fees = uniswap_v2_fees;
+        self
+    }
+
+    pub(crate) fn budget(&self) -> &SearchBudget {
+        &self.budget
+    }
+
+    /// Nonce of `address` in the state the routes are simulated on.
+    pub fn nonce(&self, address: Address) -> eyre::Result<u64> {
+        Ok(self.db.basic_ref(address)?.map(|account| account.nonce).unwrap_or_default())
+    }
+
+    /// Creates a simulator with its own `Evm` and `CacheDB` over the shared state snapshot.
+    pub(crate) fn simulator(&self) -> Simulator<'_, 'a, SP> {
+        Simulator::new(&self.db, self.contract.clone(), &self.env, &self.uniswap_v2_fees)
     }
 }
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+
+use alloy_primitives::{ Address, B256, U256 };
+use reth_revm::{ db::BundleState, state::{ AccountInfo, Bytecode }, DatabaseRef };
+
+/// Read-only view of a [`BundleState`] layered on top of the state it was executed on.
+///
+/// Accounts, storage and bytecode touched by the bundle are read from it, everything else from
+/// the underlying database. Blocks covered by the bundle resolve through `block_hashes`, since
+/// the underlying database only knows blocks up to the one the bundle starts from.
+#[derive(Debug)]
+pub struct BundleOverlay<'a, DB> {
+    db: DB,
+    bundle: &'a BundleState,
+    block_hashes: HashMap<u64, B256>,
+}
+
+impl<'a, DB> BundleOverlay<'a, DB> {
+    pub fn new(db: DB, bundle: &'a BundleState, block_hashes: HashMap<u64, B256>) -> Self {
+        Self { db, bundle, block_hashes }
+    }
+}
+
+impl<DB> DatabaseRef for BundleOverlay<'_, DB> where DB: DatabaseRef {
+    type Error = DB::Error;
+
+    fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+        match self.bundle.account(&address) {
+            // a destroyed account is present in the bundle without info
+            Some(account) => Ok(account.info.clone()),
+            None => self.db.basic_ref(address),
+        }
+    }
+
+    fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        match self.bundle.bytecode(&code_hash) {
+            Some(code) => Ok(code),
+            None => self.db.code_by_hash_ref(code_hash),
+        }
+    }
+
+    fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+        // `storage_slot` also answers for slots of accounts whose storage was wiped
+        match self.bundle.account(&address).and_then(|account| account.storage_slot(index)) {
+            Some(value) => Ok(value),
+            None => self.db.storage_ref(address, index),
+        }
+    }
+
+    fn block_hash_ref(&self, number: u64) -> Result<B256, Self::Error> {
+        match self.block_hashes.get(&number) {
+            Some(hash) => Ok(*hash),
+            None => self.db.block_hash_ref(number),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use alloy_primitives::{ address, Address, B256, U256 };
+    use reth_revm::{
+        db::{ BundleRetention, BundleState, CacheDB, EmptyDB, State },
+        state::{ Account, AccountInfo, EvmState, EvmStorageSlot },
+        Database,
+        DatabaseCommit,
+        DatabaseRef,
+    };
+
+    use super::BundleOverlay;
+
+    /// Changed in block 1, destroyed in block 2, re-created in block 3.
+    const RECREATED: Address = address!("0000000000000000000000000000000000000a0a");
+    /// Changed in block 1 only.
+    const CHANGED: Address = address!("0000000000000000000000000000000000000b0b");
+    /// Destroyed in block 2.
+    const DESTROYED: Address = address!("0000000000000000000000000000000000000c0c");
+    /// Never touched by the chain.
+    const UNTOUCHED: Address = address!("0000000000000000000000000000000000000d0d");
+
+    fn info(nonce: u64, balance: u64) -> AccountInfo {
+        AccountInfo { nonce, balance: U256::from(balance), ..Default::default() }
+    }
+
+    /// State at the fork point: every account with a couple of slots.
+    fn fork_state() -> CacheDB<EmptyDB> {
+        let mut db = CacheDB::new(EmptyDB::default());
+        for (address, nonce) in [(RECREATED, 1), (CHANGED, 2), (DESTROYED, 3), (UNTOUCHED, 4)] {
+            db.insert_account_info(address, info(nonce, 100));
+            db.insert_account_storage(address, U256::from(1), U256::from(10)).unwrap();
+            db.insert_account_storage(address, U256::from(2), U256::from(20)).unwrap();
+        }
+        db
+    }
+
+    /// An account of a block's changes with its changed slots, `(slot, original, present)`.
+    fn changed(info: AccountInfo, slots: &[(u64, u64, u64)]) -> Account {
+        let mut account = Account::from(info);
+        account.mark_touch();
+        for (slot, original, present) in slots {
+            account.storage.insert(
+                U256::from(*slot),
+                EvmStorageSlot::new_changed(U256::from(*original), U256::from(*present))
+            );
+        }
+        account
+    }
+
+    /// Executes three blocks of changes over `fork`, as the committed chain's bundle.
+    fn committed_bundle(fork: &CacheDB<EmptyDB>) -> BundleState {
+        let mut state = State::builder().with_database_ref(fork).with_bundle_update().build();
+        for address in [RECREATED, CHANGED, DESTROYED] {
+            state.basic(address).unwrap();
+            state.storage(address, U256::from(1)).unwrap();
+            state.storage(address, U256::from(2)).unwrap();
+        }
+
+        // block 1: storage changes
+        state.commit(
+            EvmState::from_iter([
+                (RECREATED, changed(info(1, 100), &[(1, 10, 11)])),
+                (CHANGED, changed(info(2, 90), &[(1, 10, 12)])),
+            ])
+        );
+        state.merge_transitions(BundleRetention::Reverts);
+
+        // block 2: self-destructs
+        let destroyed = |info| {
+            let mut account = changed(info, &[]);
+            account.mark_selfdestruct();
+            account
+        };
+        state.commit(
+            EvmState::from_iter([
+                (RECREATED, destroyed(info(1, 100))),
+                (DESTROYED, destroyed(info(3, 100))),
+            ])
+        );
+        state.merge_transitions(BundleRetention::Reverts);
+
+        // block 3: re-creation with a single slot
+        state.basic(RECREATED).unwrap();
+        let mut recreated = changed(info(0, 5), &[(2, 0, 7)]);
+        recreated.mark_created();
+        state.commit(EvmState::from_iter([(RECREATED, recreated)]));
+        state.merge_transitions(BundleRetention::Reverts);
+
+        state.take_bundle()
+    }
+
+    #[test]
+    fn reads_the_committed_chain_over_the_fork_state() {
+        let fork = fork_state();
+        let bundle = committed_bundle(&fork);
+        let block_hashes = (101..=103)
+            .map(|number| (number, B256::with_last_byte(number as u8)))
+            .collect::<HashMap<_, _>>();
+        let overlay = BundleOverlay::new(&fork, &bundle, block_hashes);
+
+        let slot = |address, slot: u64| overlay.storage_ref(address, U256::from(slot)).unwrap();
+        let nonce = |address| overlay.basic_ref(address).unwrap().map(|info| info.nonce);
+
+        // re-created: only the slots written since the re-creation survive
+        assert_eq!(nonce(RECREATED), Some(0));
+        assert_eq!(slot(RECREATED, 1), U256::ZERO);
+        assert_eq!(slot(RECREATED, 2), U256::from(7));
+
+        // changed: changed slots from the bundle, the others from the fork state
+        let balance = overlay.basic_ref(CHANGED).unwrap().map(|info| info.balance);
+        assert_eq!(balance, Some(U256::from(90)));
+        assert_eq!(slot(CHANGED, 1), U256::from(12));
+        assert_eq!(slot(CHANGED, 2), U256::from(20));
+
+        // destroyed: no account and wiped storage, not the fork state's
+        assert_eq!(nonce(DESTROYED), None);
+        assert_eq!(slot(DESTROYED, 1), U256::ZERO);
+        assert_eq!(slot(DESTROYED, 2), U256::ZERO);
+
+        // untouched: the fork state
+        assert_eq!(nonce(UNTOUCHED), Some(4));
+        assert_eq!(slot(UNTOUCHED, 1), U256::from(10));
+
+        // blocks of the chain from its hashes, older ones from the fork state
+        assert_eq!(overlay.block_hash_ref(102).unwrap(), B256::with_last_byte(102));
+        assert_eq!(overlay.block_hash_ref(100).unwrap(), fork.block_hash_ref(100).unwrap());
+    }
+}
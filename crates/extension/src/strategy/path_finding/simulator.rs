@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+use alloy_primitives::{ Address, Bytes, U256 };
+use alloy_sol_types::SolValue;
+use eyre::eyre;
+use reth_provider::StateProvider;
+use reth_revm::{
+    context::{ result::ExecutionResult, BlockEnv, CfgEnv, Evm, TxEnv },
+    context_interface::{ Block, ContextTr },
+    db::CacheDB,
+    handler::{ instructions::EthInstructions, EthPrecompiles, EvmTr },
+    interpreter::interpreter::EthInterpreter,
+    state::{ AccountInfo, Bytecode },
+    Context,
+    Database,
+    MainBuilder,
+    MainContext,
+    SystemCallEvm,
+};
+
+use crate::amm::{ self, uniswap_v2::UniswapV2Fees, PoolSource, PoolState };
+
+use super::{
+    budget::SearchBudget,
+    env::SimulationEnv,
+    gas::intrinsic_gas,
+    sizing::{
+        golden_section_search,
+        EVM_ITERATIONS,
+        MAX_RESERVE_SHARE_DIVISOR,
+        NATIVE_ITERATIONS,
+    },
+    types::{ Hop, RejectReason, RoutePath, SwapResult, DEPLOYED_ADDRESS },
+    PathFinderDB,
+};
+
+type SimulatorCtx<'s, 'a, SP> = Context<BlockEnv, TxEnv, CfgEnv, CacheDB<&'s PathFinderDB<'a, SP>>>;
+
+/// A single simulation worker: its own `Evm` and `CacheDB` over a shared read-only snapshot.
+pub struct Simulator<'s, 'a, SP> where SP: StateProvider {
+    evm: Evm<
+        SimulatorCtx<'s, 'a, SP>,
+        (),
+        EthInstructions<EthInterpreter, SimulatorCtx<'s, 'a, SP>>,
+        EthPrecompiles
+    >,
+    /// Pool states loaded for native quoting, valid for the block of the snapshot.
+    pools: HashMap<Address, Option<PoolState>>,
+    uniswap_v2_fees: &'s UniswapV2Fees,
+}
+
+impl<'s, 'a, SP> Simulator<'s, 'a, SP>
+    where SP: StateProvider
+{
+    pub(crate) fn new(
+        db: &'s PathFinderDB<'a, SP>,
+        contract: Bytecode,
+        env: &SimulationEnv,
+        uniswap_v2_fees: &'s UniswapV2Fees
+    ) -> Self {
+        let mut db = CacheDB::new(db);
+        db.insert_account_info(DEPLOYED_ADDRESS, AccountInfo {
+            code_hash: contract.hash_slow(),
+            code: Some(contract),
+            ..Default::default()
+        });
+        let evm = Context::mainnet()
+            .with_db(db)
+            .with_cfg(env.cfg.clone())
+            .with_block(env.block.clone())
+            .build_mainnet();
+        Self { evm, pools: HashMap::new(), uniswap_v2_fees }
+    }
+
+    /// Natively estimates the ppm profit ratio of a route from the pool states in the `CacheDB`.
+    /// `None` if a hop has no native quoting (or its state can't be read).
+    pub fn estimate_profit_ratio(&mut self, route_path: &RoutePath) -> Option<u64> {
+        amm::estimate_profit_ratio(self, route_path).ok().flatten()
+    }
+
+    /// Searches the profit-maximizing input of a route, up to a share of its first pool's reserve.
+    /// Probes are quoted natively when every hop supports it, otherwise simulated in the EVM.
+    /// An exhausted `budget` cuts the search short.
+    ///
+    /// Returns (amount in, expected amount out), `None` if the first pool can't be sized natively
+    /// or no size is profitable.
+    pub fn optimal_amount_in(
+        &mut self,
+        route_path: &RoutePath,
+        budget: &SearchBudget
+    ) -> Option<(U256, U256)> {
+        let first_hop = route_path.hops.first()?;
+        let first_pool = self.pool(first_hop).ok().flatten()?;
+        let reserve = first_pool.reserve_of(self, first_hop.srcToken).ok().flatten()?;
+        let high = reserve / U256::from(MAX_RESERVE_SHARE_DIVISOR);
+
+        let native = route_path.hops
+            .iter()
+            .all(|hop| matches!(amm::native_pool(self, hop), Ok(Some(_))));
+        if native {
+            golden_section_search(U256::from(1), high, NATIVE_ITERATIONS, budget, |amount_in| {
+                amm::quote_route(self, route_path, amount_in).ok().flatten()
+            })
+        } else {
+            golden_section_search(U256::from(1), high, EVM_ITERATIONS, budget, |amount_in| {
+                self.simulate(route_path, amount_in).ok().map(|(result, _)| result.amountOut)
+            })
+        }
+    }
+
+    /// Runs the searcher contract for a single route path without committing state.
+    /// An `amount_in` of zero lets the contract size the trade.
+    ///
+    /// Returns the swap result with the gas a transaction executing it uses: a system call
+    /// doesn't charge the intrinsic and calldata gas, which are added to its execution gas for
+    /// the calldata sized with the returned `amountIn`, the one [`Executor`] signs.
+    ///
+    /// [`Executor`]: crate::executor::Executor
+    pub fn simulate(
+        &mut self,
+        route_path: &RoutePath,
+        amount_in: U256
+    ) -> Result<(SwapResult, u64), RejectReason> {
+        let calldata = route_path.sized(amount_in).abi_encode();
+        let result = self.evm
+            .transact_system_call(calldata.into(), DEPLOYED_ADDRESS)
+            .map_err(|err| RejectReason::Evm(err.to_string()))?;
+
+        match result.result {
+            ExecutionResult::Success { output, gas_used, .. } =>
+                SwapResult::abi_decode(output.data())
+                    .map(|result| {
+                        let calldata = route_path.sized(result.amountIn).abi_encode();
+                        (result, gas_used + intrinsic_gas(&calldata))
+                    })
+                    .map_err(|err| RejectReason::InvalidOutput(err.to_string())),
+            ExecutionResult::Revert { output, .. } => Err(RejectReason::Reverted(output)),
+            ExecutionResult::Halt { reason, .. } =>
+                Err(RejectReason::Halted(format!("{reason:?}"))),
+        }
+    }
+}
+
+impl<'s, 'a, SP> PoolSource
+    for Simulator<'s, 'a, SP>
+    where SP: StateProvider
+{
+    fn storage(&mut self, address: Address, slot: U256) -> eyre::Result<U256> {
+        Ok(self.evm.ctx().db().storage(address, slot)?)
+    }
+
+    fn call(&mut self, address: Address, input: Bytes) -> eyre::Result<Bytes> {
+        let result = self.evm.transact_system_call(input, address)?;
+        match result.result {
+            ExecutionResult::Success { output, .. } => Ok(output.into_data()),
+            result => Err(eyre!("call to {address} failed: {result:?}")),
+        }
+    }
+
+    fn timestamp(&mut self) -> u64 {
+        self.evm.ctx().block().timestamp()
+    }
+
+    fn uniswap_v2_fee_bps(&mut self, factory: Address) -> u64 {
+        self.uniswap_v2_fees.fee_bps(factory)
+    }
+
+    fn pool(&mut self, hop: &Hop) -> eyre::Result<Option<PoolState>> {
+        if let Some(pool) = self.pools.get(&hop.dex) {
+            return Ok(pool.clone());
+        }
+        let pool = PoolState::load(self, hop)?;
+        self.pools.insert(hop.dex, pool.clone());
+        Ok(pool)
+    }
+}
@@ -0,0 +1,173 @@
+use alloy_primitives::U256;
+
+use super::budget::SearchBudget;
+
+/// Golden-section iterations when every probe is quoted natively.
+pub const NATIVE_ITERATIONS: usize = 64;
+/// Golden-section iterations when every probe is an EVM simulation.
+pub const EVM_ITERATIONS: usize = 16;
+/// Upper bound of the search, as a fraction of the first pool's input reserve.
+pub const MAX_RESERVE_SHARE_DIVISOR: u64 = 2;
+
+/// `1 - 1 / phi`, in ppm.
+const GOLDEN_SECTION_PPM: u64 = 381_966;
+const PPM: u64 = 1_000_000;
+
+/// A probe of the search: amount in and quoted amount out (`None` if the swap failed).
+#[derive(Debug, Clone, Copy)]
+struct Probe {
+    amount_in: U256,
+    amount_out: Option<U256>,
+}
+
+impl Probe {
+    /// Compares `out - in` without going through signed arithmetic.
+    fn beats(&self, other: &Probe) -> bool {
+        match (self.amount_out, other.amount_out) {
+            (Some(out), Some(other_out)) =>
+                out.saturating_add(other.amount_in) > other_out.saturating_add(self.amount_in),
+            (Some(_), None) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Golden-section search of the profit-maximizing input in `[low, high]`, assuming the profit
+/// `quote(x) - x` of a cycle is unimodal in `x` (which holds for AMM curves).
+///
+/// The search stops early once `budget` is exhausted, with the best probe so far.
+///
+/// Returns the best (amount in, amount out), `None` if no probe made a profit.
+pub fn golden_section_search<F>(
+    low: U256,
+    high: U256,
+    iterations: usize,
+    budget: &SearchBudget,
+    mut quote: F
+) -> Option<(U256, U256)>
+    where F: FnMut(U256) -> Option<U256>
+{
+    if high <= low {
+        return None;
+    }
+    let step = |low: U256, high: U256| {
+        ((high - low) * U256::from(GOLDEN_SECTION_PPM)) / U256::from(PPM)
+    };
+    let mut probe = |amount_in: U256| Probe { amount_in, amount_out: quote(amount_in) };
+
+    let (mut low, mut high) = (low, high);
+    let mut left = probe(low + step(low, high));
+    let mut right = probe(high - step(low, high));
+
+    for _ in 0..iterations {
+        if high - low <= U256::from(2) || budget.is_exhausted() {
+            break;
+        }
+        if left.beats(&right) {
+            high = right.amount_in;
+            right = left;
+            left = probe(low + step(low, high));
+        } else {
+            low = left.amount_in;
+            left = right;
+            right = probe(high - step(low, high));
+        }
+    }
+
+    let best = if left.beats(&right) { left } else { right };
+    best.amount_out
+        .filter(|amount_out| *amount_out > best.amount_in)
+        .map(|amount_out| (best.amount_in, amount_out))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::U256;
+
+    use crate::strategy::path_finding::budget::SearchBudget;
+
+    use super::{ golden_section_search, EVM_ITERATIONS, NATIVE_ITERATIONS };
+
+    /// Quote of a cycle whose profit peaks at `best`, `peak` over the input.
+    fn peaked(best: u64, peak: u64) -> impl Fn(U256) -> Option<U256> {
+        move |amount_in| {
+            let distance = amount_in.abs_diff(U256::from(best));
+            let profit = U256::from(peak).saturating_sub(distance * distance);
+            Some(amount_in + profit)
+        }
+    }
+
+    #[test]
+    fn finds_the_peak_of_a_unimodal_profit() {
+        let budget = SearchBudget::default();
+        let high = U256::from(1_000_000);
+        let (amount_in, amount_out) = golden_section_search(
+            U256::from(1),
+            high,
+            NATIVE_ITERATIONS,
+            &budget,
+            peaked(123_456, 1_000_000_000_000)
+        ).unwrap();
+        assert!(amount_in.abs_diff(U256::from(123_456)) <= U256::from(2), "{amount_in}");
+        assert!(amount_out - amount_in >= U256::from(1_000_000_000_000u64 - 4));
+
+        // fewer iterations get close to the peak, by a share of the range
+        let (amount_in, _) = golden_section_search(
+            U256::from(1),
+            high,
+            EVM_ITERATIONS,
+            &budget,
+            peaked(123_456, 1_000_000_000_000)
+        ).unwrap();
+        assert!(amount_in.abs_diff(U256::from(123_456)) <= U256::from(1_000), "{amount_in}");
+    }
+
+    #[test]
+    fn finds_a_peak_at_the_bounds() {
+        let budget = SearchBudget::default();
+        let search = |best: u64| {
+            golden_section_search(
+                U256::from(1),
+                U256::from(10_000),
+                NATIVE_ITERATIONS,
+                &budget,
+                peaked(best, 1_000_000_000)
+            )
+                .unwrap()
+                .0
+        };
+        assert!(search(1) <= U256::from(3));
+        assert!(search(10_000) >= U256::from(9_998));
+    }
+
+    #[test]
+    fn finds_nothing_without_a_profit() {
+        let budget = SearchBudget::default();
+        let search = |quote: &dyn Fn(U256) -> Option<U256>| {
+            golden_section_search(U256::from(1), U256::from(1_000_000), 64, &budget, quote)
+        };
+        // flat: every amount comes back as is
+        assert_eq!(search(&Some), None);
+        assert_eq!(search(&|amount_in| amount_in.checked_sub(U256::from(1))), None);
+        assert_eq!(search(&|_| None), None);
+    }
+
+    #[test]
+    fn searches_tiny_ranges() {
+        let budget = SearchBudget::default();
+        let double = |amount_in: U256| Some(amount_in * U256::from(2));
+        for high in [2, 3, 4, 10] {
+            let (amount_in, amount_out) = golden_section_search(
+                U256::from(1),
+                U256::from(high),
+                NATIVE_ITERATIONS,
+                &budget,
+                double
+            ).unwrap();
+            assert!(amount_in >= U256::from(1) && amount_in <= U256::from(high));
+            assert_eq!(amount_out, amount_in * U256::from(2));
+        }
+        assert_eq!(golden_section_search(U256::from(5), U256::from(5), 64, &budget, double), None);
+        assert_eq!(golden_section_search(U256::from(5), U256::from(1), 64, &budget, double), None);
+    }
+}
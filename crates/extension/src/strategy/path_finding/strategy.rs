@@ -1,10 +1,14 @@
-use alloy_sol_types::SolValue;
-use eyre::Error;
+use std::{ cmp::Reverse, sync::atomic::{ AtomicBool, AtomicUsize, Ordering }, thread };
 
-use reth_provider::{ BlockHashReader, DBProvider, StateCommitmentProvider };
-use reth_revm::SystemCallEvm;
+use alloy_primitives::{ Address, U256 };
+use eyre::{ eyre, Error };
 
-use crate::strategy::path_finding::types::{ Profit, DEPLOYED_ADDRESS };
+use reth_provider::StateProvider;
+
+use crate::{
+    amm,
+    strategy::path_finding::types::{ ProfitablePath, RejectReason, RejectedPath, Selection },
+};
 
 use super::{ types::RoutePath, PathFinder };
 
@@ -14,54 +18,394 @@ pub trait Strategy {
         candidates: Vec<RoutePath>,
         max_profit: u64,
         min_profit: u64
-    ) -> Result<Vec<RoutePath>, Error>;
+    ) -> Result<Selection, Error>;
 }
 
-impl<'a, DB> Strategy
-    for PathFinder<'a, DB>
-    where DB: DBProvider + BlockHashReader + StateCommitmentProvider
-{
-    // DFS-based search for arbitrage paths with dynamic pruning.
-    // Keeps only the most profitable path for each start token.
-    // Get the top 10 paths over min_profit and
+/// Outcome of a single candidate, tagged with its index in the candidate list.
+enum Outcome {
+    Selected(ProfitablePath),
+    Rejected(RejectedPath),
+}
 
+impl<'a, SP> Strategy
+    for PathFinder<'a, SP>
+    where SP: StateProvider
+{
+    // Selection pass over the candidates of the current block.
+    //
     // Main logic:
-    // 1. For each token, start a DFS to explore swap paths up to a given hop limit.
-    // 2. Simulate swap outcomes and calculate cumulative profit ratio.
-    // 3. If a newly found path has higher profit than the previously best one for the same start token, replace it.
-    // 4. Discard any path with lower profit than the current best.
-
-    // Path pruning rules:
-    // - Avoid cycles (do not revisit same token in a path).
-    // - Stop expanding a path if:
-    //     - The cumulative profit is below a defined threshold.
-    //     - The current profit is less than or equal to the best known for the same start token.
-    // - Store only the most profitable path per start token.
+    // 1. Natively estimate every route on the pool states, drop it if it can't pass min_profit,
+    //    and order the rest by estimated profit ratio so the most promising ones go first.
+    // 2. Search the profit-maximizing input amount of the route (natively when possible).
+    // 3. Simulate the sized route path against the searcher contract (state is never committed,
+    //    so every simulation starts from the same block state).
+    // 4. Subtract the gas cost at the next block's gas price, expressed in the start token, and
+    //    the bid picked by the bidding policy, and convert the net profit into a ppm ratio
+    //    comparable with max_profit / min_profit.
+    // 5. If a path beats max_profit, stop searching and return it together with the paths found
+    //    before it.
+    // 6. Otherwise keep every path over min_profit, ranked by profit in the native token.
+    //
+    // Paths that revert or return undecodable output are reported in `Selection::rejected`.
+    //
+    // The candidates are dealt round-robin to the workers, for the estimates and then, ordered,
+    // for the simulations, so the most promising ones are simulated first by every worker. Every
+    // outcome keeps the index of its candidate, so merging the workers gives exactly the result of
+    // a sequential pass. When the budget is exhausted the workers stop and the paths found so far
+    // are returned.
     fn filter_candidates(
         &mut self,
         route_paths: Vec<RoutePath>,
         max_profit: u64,
         min_profit: u64
+    ) -> Result<Selection, Error> {
+        let finder = &*self;
+        let route_paths = finder.rank_candidates(route_paths, max_profit, min_profit)?;
+        // index of the first path beating max_profit, workers past it can stop early
+        let cutoff = AtomicUsize::new(usize::MAX);
+        let interrupted = AtomicBool::new(false);
+        let workers = finder.threads.min(route_paths.len()).max(1);
+
+        let mut outcomes = finder.run_workers(workers, |worker| {
+            finder.simulate_worker(
+                worker,
+                workers,
+                &route_paths,
+                max_profit,
+                min_profit,
+                &cutoff,
+                &interrupted
+            )
+        })?;
+
+        let cutoff = cutoff.into_inner();
+        outcomes.retain(|(index, _)| *index <= cutoff);
+        outcomes.sort_by_key(|(index, _)| *index);
+
+        let mut selection = Selection {
+            interrupted: interrupted.into_inner(),
+            ..Default::default()
+        };
+        for (_, outcome) in outcomes {
+            match outcome {
+                Outcome::Selected(path) => selection.optimal_paths.push(path),
+                Outcome::Rejected(path) => selection.rejected.push(path),
+            }
+        }
+        // rank by profit valued in the native token so different start tokens compare, unpriced
+        // paths last. The sort is stable and keeps candidate order between equal profits.
+        let gas_pricing = &self.gas_pricing;
+        selection.optimal_paths.sort_by_cached_key(|path| {
+            let start_token = path.route_path.hops.first().map(|hop| hop.srcToken);
+            let value = start_token.and_then(|token| gas_pricing.native_value(token, path.profit));
+            Reverse((value, path.profit))
+        });
+        Ok(selection)
+    }
+}
+
+impl<'a, SP> PathFinder<'a, SP> where SP: StateProvider {
+    /// Runs `work` for every worker, on scoped threads if there's more than one, and concatenates
+    /// their results.
+    fn run_workers<T, F>(&self, workers: usize, work: F) -> Result<Vec<T>, Error>
+        where T: Send, F: Fn(usize) -> Vec<T> + Sync
+    {
+        if workers == 1 {
+            return Ok(work(0));
+        }
+        thread::scope(|scope| {
+            let handles = (0..workers)
+                .map(|worker| {
+                    let work = &work;
+                    scope.spawn(move || work(worker))
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().map_err(|_| eyre!("simulation worker panicked")))
+                .collect::<Result<Vec<_>, _>>()
+                .map(|chunks| chunks.into_iter().flatten().collect::<Vec<_>>())
+        })
+    }
+
+    /// Drops the candidates whose native estimate can't pass the thresholds and orders the rest
+    /// by estimated profit ratio, highest first. Candidates without native quoting can't be
+    /// estimated and come last, in their original order, as do the candidates left unestimated
+    /// once the budget is exhausted (the simulations then stop at once).
+    fn rank_candidates(
+        &self,
+        route_paths: Vec<RoutePath>,
+        max_profit: u64,
+        min_profit: u64
     ) -> Result<Vec<RoutePath>, Error> {
-        let mut optimal_paths = Vec::<RoutePath>::new();
-        // TODO: use parallel core
-        // get native token price. ex. BERA/USDC
-        while let Some(route_path) = route_paths.iter().next() {
-            let result = self.evm
-                .transact_system_call(route_path.abi_encode().into(), DEPLOYED_ADDRESS)
-                .unwrap();
-            // amount
-            let Profit { amount } = Profit::abi_decode(result.result.output().unwrap())?;
-
-            let net_profit = amount;
-            // if max profit / 1e6 < net_profit,  push optimal_paths.push(route_path) and break
-            // else if min profit / 1e6 < net_profit, push optimal_paths.push(route_path);
-            // else continue
-            todo!();
+        let workers = self.threads.min(route_paths.len()).max(1);
+        let estimated = self.run_workers(workers, |worker| {
+            let mut simulator = self.simulator();
+            (worker..route_paths.len())
+                .step_by(workers)
+                .map_while(|index| {
+                    (!self.budget.is_exhausted()).then(|| {
+                        (index, simulator.estimate_profit_ratio(&route_paths[index]))
+                    })
+                })
+                .collect()
+        })?;
+        let mut estimates = vec![None; route_paths.len()];
+        for (index, estimate) in estimated {
+            estimates[index] = estimate;
+        }
+
+        let mut ranked = route_paths
+            .into_iter()
+            .zip(estimates)
+            .filter_map(|(route_path, estimate)| {
+                match estimate {
+                    Some(estimate) if estimate <= min_profit.min(max_profit) => None,
+                    _ => Some((estimate, route_path)),
+                }
+            })
+            .collect::<Vec<_>>();
+        ranked.sort_by_key(|(estimate, _)| Reverse(*estimate));
+        Ok(
+            ranked
+                .into_iter()
+                .map(|(_, route_path)| route_path)
+                .collect()
+        )
+    }
+
+    /// Simulates every `stride`-th candidate starting at `worker` on a fresh simulator, until the
+    /// cutoff is passed or the budget is exhausted.
+    fn simulate_worker(
+        &self,
+        worker: usize,
+        stride: usize,
+        route_paths: &[RoutePath],
+        max_profit: u64,
+        min_profit: u64,
+        cutoff: &AtomicUsize,
+        interrupted: &AtomicBool
+    ) -> Vec<(usize, Outcome)> {
+        let mut simulator = self.simulator();
+        let mut outcomes = Vec::new();
+
+        for index in (worker..route_paths.len()).step_by(stride) {
+            if index > cutoff.load(Ordering::Relaxed) {
+                break;
+            }
+            if self.budget.is_exhausted() {
+                interrupted.store(true, Ordering::Relaxed);
+                break;
+            }
+            let route_path = &route_paths[index];
+
+            let amount_in = simulator
+                .optimal_amount_in(route_path, &self.budget)
+                .map(|(amount_in, _)| amount_in)
+                .unwrap_or_default();
+            let (result, gas_used) = match simulator.simulate(route_path, amount_in) {
+                Ok(simulated) => simulated,
+                Err(reason) => {
+                    outcomes.push((
+                        index,
+                        Outcome::Rejected(RejectedPath { route_path: route_path.clone(), reason }),
+                    ));
+                    continue;
+                }
+            };
+
+            let (amount_in, amount_out) = (result.amountIn, result.amountOut);
+            let start_token = route_path.hops.first().map(|hop| hop.srcToken).unwrap_or_default();
+            let Some(gas_cost) = self.gas_pricing.gas_cost(start_token, gas_used) else {
+                outcomes.push((
+                    index,
+                    Outcome::Rejected(RejectedPath {
+                        route_path: route_path.clone(),
+                        reason: RejectReason::Unpriced(start_token),
+                    }),
+                ));
+                continue;
+            };
+            let margin = amount_out.saturating_sub(amount_in).saturating_sub(gas_cost);
+            let Some((bid, bid_per_gas)) = self.bid(route_path, start_token, margin, gas_used)
+            else {
+                outcomes.push((
+                    index,
+                    Outcome::Rejected(RejectedPath {
+                        route_path: route_path.clone(),
+                        reason: RejectReason::Unpriced(start_token),
+                    }),
+                ));
+                continue;
+            };
+            let net_profit = margin - bid;
+            let ratio = amm::ratio_of(amount_in, amount_in + net_profit);
+            let beats_max = ratio > max_profit;
+            if beats_max || ratio > min_profit {
+                outcomes.push((
+                    index,
+                    Outcome::Selected(ProfitablePath {
+                        route_path: route_path.clone(),
+                        amount_in,
+                        amount_out,
+                        gas_used,
+                        gas_price: self.gas_pricing.gas_price,
+                        gas_cost,
+                        bid,
+                        bid_per_gas,
+                        profit: net_profit,
+                        profit_ratio: ratio,
+                    }),
+                ));
+            }
+            if beats_max {
+                cutoff.fetch_min(index, Ordering::Relaxed);
+                break;
+            }
         }
 
-        // find paths of min ~ max with using "Incremental Evaluation"
-        // stateless transition in evm
-        Ok(optimal_paths)
+        outcomes
+    }
+
+    /// Bid of the bidding policy for `route_path` leaving `margin` of `token` after gas, in
+    /// `token` and as priority fee per gas over `gas_used`. The bid is rounded down to whole wei
+    /// per gas, so that it is exactly what the route pays. `None` if `token` can't be priced.
+    fn bid(
+        &self,
+        route_path: &RoutePath,
+        token: Address,
+        margin: U256,
+        gas_used: u64
+    ) -> Option<(U256, u128)> {
+        if margin.is_zero() || gas_used == 0 || self.gas_pricing.is_gross() {
+            return Some((U256::ZERO, 0));
+        }
+        let margin_value = self.gas_pricing.native_value(token, margin)?;
+        let bid_value = self.bidding.bid(route_path.id(), margin_value).min(margin_value);
+        let bid_per_gas = bid_value / U256::from(gas_used);
+        if bid_per_gas.is_zero() {
+            return Some((U256::ZERO, 0));
+        }
+        let bid = self.gas_pricing.token_value(token, bid_per_gas * U256::from(gas_used))?;
+        Some((bid.min(margin), bid_per_gas.saturating_to()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ collections::HashMap, time::Duration };
+
+    use alloy_primitives::{ hex, Address, B256, U256 };
+    use reth_provider::test_utils::MockEthProvider;
+    use reth_revm::{ db::BundleState, state::Bytecode };
+
+    use crate::strategy::path_finding::{
+        budget::SearchBudget,
+        types::{ Hop, RoutePath },
+        PathFinder,
+    };
+
+    use super::Strategy;
+
+    const TOKEN_A: Address = Address::with_last_byte(0xaa);
+    const TOKEN_B: Address = Address::with_last_byte(0xbb);
+
+    /// Searcher contract returning `SwapResult(1e6, 1e6 + (keccak256(calldata) >> 240))`, a
+    /// profit ratio in ppm unique to every route.
+    fn searcher_code() -> Bytecode {
+        Bytecode::new_raw(
+            hex::decode(
+                concat!(
+                    "36600060003736600020", // hash = keccak256(calldata)
+                    "60f01c620f424001", // amountOut = 1e6 + (hash >> 240)
+                    "602052", // mstore(0x20, amountOut)
+                    "620f4240600052", // mstore(0, amountIn = 1e6)
+                    "60406000f3" // return (amountIn, amountOut)
+                )
+            )
+                .unwrap()
+                .into()
+        )
+    }
+
+    /// Round trips A -> B -> A over pools without native quoting, so every route is simulated.
+    fn candidates(count: u8) -> Vec<RoutePath> {
+        (1..=count)
+            .map(|index| RoutePath {
+                hops: vec![
+                    Hop {
+                        dexType: u8::MAX,
+                        dex: Address::with_last_byte(index),
+                        srcToken: TOKEN_A,
+                        dstToken: TOKEN_B,
+                    },
+                    Hop {
+                        dexType: u8::MAX,
+                        dex: Address::with_last_byte(index),
+                        srcToken: TOKEN_B,
+                        dstToken: TOKEN_A,
+                    }
+                ],
+            })
+            .collect()
+    }
+
+    /// (route id, amount in, amount out, profit) of the selected paths, in ranked order.
+    fn select(threads: usize, max_profit: u64, min_profit: u64) -> Vec<(B256, U256, U256, U256)> {
+        let bundle = BundleState::default();
+        let mut path_finder = PathFinder::new(
+            MockEthProvider::default(),
+            &bundle,
+            HashMap::new(),
+            searcher_code()
+        ).with_threads(threads);
+        let selection = path_finder
+            .filter_candidates(candidates(64), max_profit, min_profit)
+            .unwrap();
+        assert!(!selection.interrupted);
+        assert!(selection.rejected.is_empty());
+        selection.optimal_paths
+            .into_iter()
+            .map(|path| (path.route_path.id(), path.amount_in, path.amount_out, path.profit))
+            .collect()
+    }
+
+    #[test]
+    fn parallel_search_ranks_like_sequential() {
+        let sequential = select(1, u64::MAX, 10_000);
+        assert!(!sequential.is_empty());
+        assert!(sequential.windows(2).all(|pair| pair[0].3 >= pair[1].3));
+        for threads in [2, 3, 8] {
+            assert_eq!(select(threads, u64::MAX, 10_000), sequential, "{threads} threads");
+        }
+    }
+
+    #[test]
+    fn parallel_search_stops_at_the_same_cutoff() {
+        let all = select(1, u64::MAX, 0);
+        let sequential = select(1, 50_000, 0);
+        assert!(!sequential.is_empty() && sequential.len() < all.len());
+        for threads in [2, 3, 8] {
+            assert_eq!(select(threads, 50_000, 0), sequential, "{threads} threads");
+        }
+    }
+
+    #[test]
+    fn exhausted_search_stops_interrupted() {
+        let cancelled = SearchBudget::default();
+        cancelled.cancel();
+        for budget in [cancelled, SearchBudget::new(Duration::ZERO)] {
+            let bundle = BundleState::default();
+            let mut path_finder = PathFinder::new(
+                MockEthProvider::default(),
+                &bundle,
+                HashMap::new(),
+                searcher_code()
+            ).with_budget(budget);
+            let selection = path_finder.filter_candidates(candidates(64), u64::MAX, 0).unwrap();
+            assert!(selection.interrupted);
+            assert!(selection.optimal_paths.is_empty());
+        }
     }
 }
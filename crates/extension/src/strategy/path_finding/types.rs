@@ -1,9 +1,23 @@
-use alloy_primitives::{ address, Address };
-use alloy_sol_types::sol;
+use alloy_primitives::{ address, keccak256, Address, Bytes, B256, U256 };
+use alloy_sol_types::{ sol, SolValue };
+use searcher_reth_ipc::{ self as ipc, Opportunity };
 
 pub(crate) const DEPLOYED_ADDRESS: Address = address!("0000000000000000000000000000000000012345");
 
+/// Version of the searcher contract ABI that the bytecode passed with `--bytecode` (or through
+/// the RPC) must implement.
+///
+/// - `1`: called with an abi-encoded `RoutePath`, returned a `Profit { uint256 amount }`.
+/// - `2`: called with an abi-encoded [`SizedRoutePath`], returns a [`SwapResult`]. Version `1`
+///   contracts don't decode it, and every route is rejected as reverted or invalid output.
+pub const CONTRACT_ABI_VERSION: u32 = 2;
+
+/// Denominator of the profit ratios configured in `SearcherExtension` (parts per million).
+pub const PROFIT_RATIO_PRECISION: u64 = 1_000_000;
+
 sol! {
+    #![sol(all_derives)]
+
     struct Hop {
         uint8 dexType;
         address dex;
@@ -15,7 +29,105 @@ sol! {
         Hop[] hops;
     }
 
-    struct Profit {
-        uint256 amount;
+    // calldata of the searcher contract, `amountIn == 0` lets the contract size the trade
+    struct SizedRoutePath {
+        Hop[] hops;
+        uint256 amountIn;
+    }
+
+    // return data of the searcher contract, in units of the start token
+    struct SwapResult {
+        uint256 amountIn;
+        uint256 amountOut;
+    }
+}
+
+impl RoutePath {
+    pub fn sized(&self, amount_in: U256) -> SizedRoutePath {
+        SizedRoutePath { hops: self.hops.clone(), amountIn: amount_in }
+    }
+
+    /// Identifies the route across blocks and configuration versions: the hash of its hops.
+    pub fn id(&self) -> B256 {
+        keccak256(self.hops.abi_encode())
+    }
+}
+
+/// A simulated route whose profit cleared the min threshold.
+#[derive(Debug, Clone)]
+pub struct ProfitablePath {
+    pub route_path: RoutePath,
+    pub amount_in: U256,
+    pub amount_out: U256,
+    pub gas_used: u64,
+    /// Gas price assumed for the next block, in wei.
+    pub gas_price: U256,
+    /// Gas cost in units of the start token.
+    pub gas_cost: U256,
+    /// Bid paid to the block builder in units of the start token, see [`BiddingPolicy`].
+    ///
+    /// [`BiddingPolicy`]: super::bidding::BiddingPolicy
+    pub bid: U256,
+    /// The bid as priority fee per gas, paid on top of the configured priority fee.
+    pub bid_per_gas: u128,
+    /// `amount_out - amount_in - gas_cost - bid`.
+    pub profit: U256,
+    /// ppm ratio of the net profit over `amount_in`.
+    pub profit_ratio: u64,
+}
+
+/// Why a route was dropped from the selection.
+#[derive(Debug, Clone)]
+pub enum RejectReason {
+    /// The searcher contract reverted with the given output.
+    Reverted(Bytes),
+    /// The EVM halted (out of gas, invalid opcode, ...).
+    Halted(String),
+    /// The call succeeded but its output is not an abi-encoded `SwapResult`.
+    InvalidOutput(String),
+    /// The EVM could not execute the call at all (e.g. database error).
+    Evm(String),
+    /// Gas can't be expressed in the start token of the route.
+    Unpriced(Address),
+}
+
+#[derive(Debug, Clone)]
+pub struct RejectedPath {
+    pub route_path: RoutePath,
+    pub reason: RejectReason,
+}
+
+/// Result of a selection pass over a set of candidates.
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    /// Paths over the min threshold, ranked by profit (highest first).
+    pub optimal_paths: Vec<ProfitablePath>,
+    pub rejected: Vec<RejectedPath>,
+    /// The search budget ran out before every candidate was simulated.
+    pub interrupted: bool,
+}
+
+impl Selection {
+    /// The optimal paths as emitted on the output socket.
+    pub fn opportunities(&self) -> Vec<Opportunity> {
+        self.optimal_paths
+            .iter()
+            .map(|path| Opportunity {
+                hops: path.route_path.hops.iter().cloned().map(ipc::Hop::from).collect(),
+                amountIn: path.amount_in,
+                expectedAmountOut: path.amount_out,
+                gasUsed: U256::from(path.gas_used),
+                gasPrice: path.gas_price,
+                gasCost: path.gas_cost,
+                bid: path.bid,
+                netProfit: path.profit,
+            })
+            .collect()
+    }
+}
+
+impl From<Hop> for ipc::Hop {
+    fn from(hop: Hop) -> Self {
+        Self { dexType: hop.dexType, dex: hop.dex, srcToken: hop.srcToken, dstToken: hop.dstToken }
     }
 }
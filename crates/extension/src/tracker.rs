@@ -0,0 +1,338 @@
+use std::{ collections::{ BTreeMap, HashMap }, sync::{ Arc, Mutex } };
+
+use alloy_consensus::{ BlockHeader, Transaction, TxReceipt };
+use alloy_primitives::{ Address, Log, TxHash, B256, I256, U256 };
+use alloy_sol_types::{ sol, SolEvent };
+use reth_execution_types::Chain;
+use reth_primitives_traits::{ BlockBody, NodePrimitives, SignedTransaction };
+use searcher_reth_repository::types::{ Execution, ExecutionStatus };
+
+use crate::{
+    exex::RETRACTABLE_BLOCKS,
+    strategy::path_finding::{ bidding::InclusionHistory, types::ProfitablePath },
+};
+
+sol! {
+    event Transfer(address indexed from, address indexed to, uint256 value);
+}
+
+/// A signed transaction waiting for its outcome.
+#[derive(Debug, Clone)]
+struct Tracked {
+    route_id: B256,
+    token: Address,
+    dexes: Vec<Address>,
+    config_version: u64,
+    target_block: u64,
+    last_block: u64,
+    expected_profit: U256,
+}
+
+/// A transaction settled in a recent block, kept until the block can't be reorged out anymore.
+#[derive(Debug, Clone)]
+struct Settled {
+    tx_hash: TxHash,
+    tracked: Tracked,
+    included: bool,
+}
+
+/// Follows the transactions signed for found routes until they land or the blocks they target
+/// pass, and settles their outcome.
+///
+/// Included transactions are settled with the profit their receipt logs show, the balance change
+/// of the contract they called in the start token, and the gas they paid. Every outcome feeds
+/// the inclusion rate of its route. When the block of an outcome is reorged out, the outcome is
+/// marked reorged and the transaction is tracked again, for the new chain to settle it.
+#[derive(Debug, Default)]
+pub struct InclusionTracker {
+    history: Arc<InclusionHistory>,
+    pending: Mutex<HashMap<TxHash, Tracked>>,
+    /// Transactions settled in the last [`RETRACTABLE_BLOCKS`] blocks, by block number.
+    settled: Mutex<BTreeMap<u64, Vec<Settled>>>,
+}
+
+impl InclusionTracker {
+    pub fn new(history: Arc<InclusionHistory>) -> Self {
+        Self { history, pending: Mutex::default(), settled: Mutex::default() }
+    }
+
+    pub fn history(&self) -> &Arc<InclusionHistory> {
+        &self.history
+    }
+
+    /// Tracks `tx_hash`, executing `path`, submitted for the blocks `target_block..=last_block`.
+    pub fn track(
+        &self,
+        tx_hash: TxHash,
+        path: &ProfitablePath,
+        config_version: u64,
+        target_block: u64,
+        last_block: u64
+    ) {
+        let hops = &path.route_path.hops;
+        let tracked = Tracked {
+            route_id: path.route_path.id(),
+            token: hops.first().map(|hop| hop.srcToken).unwrap_or_default(),
+            dexes: hops.iter().map(|hop| hop.dex).collect(),
+            config_version,
+            target_block,
+            last_block,
+            expected_profit: path.profit,
+        };
+        self.pending.lock().unwrap().insert(tx_hash, tracked);
+    }
+
+    /// Settles the tracked transactions included in the blocks of `chain`, and the ones whose
+    /// target blocks passed without them.
+    pub fn settle<N: NodePrimitives>(&self, chain: &Chain<N>) -> Vec<Execution> {
+        let mut pending = self.pending.lock().unwrap();
+        let mut settled = self.settled.lock().unwrap();
+        let mut executions = Vec::new();
+
+        for (block, receipts) in chain.blocks_and_receipts() {
+            let header = block.header();
+            let mut cumulative_gas_used = 0;
+            for (tx, receipt) in block.body().transactions_iter().zip(receipts) {
+                let gas_used = receipt.cumulative_gas_used() - cumulative_gas_used;
+                cumulative_gas_used = receipt.cumulative_gas_used();
+                let Some(tracked) = pending.remove(tx.tx_hash()) else {
+                    continue;
+                };
+                let gas_price = tx.effective_gas_price(header.base_fee_per_gas());
+                let (status, realized_profit) = if receipt.status() {
+                    let contract = tx.to().unwrap_or_default();
+                    (
+                        ExecutionStatus::Included,
+                        token_delta(receipt.logs(), tracked.token, contract),
+                    )
+                } else {
+                    (ExecutionStatus::Reverted, I256::ZERO)
+                };
+                settled.entry(header.number()).or_default().push(Settled {
+                    tx_hash: *tx.tx_hash(),
+                    tracked: tracked.clone(),
+                    included: status == ExecutionStatus::Included,
+                });
+                executions.push(tracked.settle(
+                    *tx.tx_hash(),
+                    header.number(),
+                    header.timestamp(),
+                    status,
+                    realized_profit,
+                    U256::from(gas_used) * U256::from(gas_price)
+                ));
+            }
+
+            // the other bundles of a route share its nonce, they miss once one of them lands
+            let missed = pending
+                .iter()
+                .filter(|(_, tracked)| tracked.last_block <= header.number())
+                .map(|(tx_hash, _)| *tx_hash)
+                .collect::<Vec<_>>();
+            for tx_hash in missed {
+                let tracked = pending.remove(&tx_hash).expect("missed transaction is pending");
+                settled.entry(header.number()).or_default().push(Settled {
+                    tx_hash,
+                    tracked: tracked.clone(),
+                    included: false,
+                });
+                executions.push(tracked.settle(
+                    tx_hash,
+                    header.number(),
+                    header.timestamp(),
+                    ExecutionStatus::Missed,
+                    I256::ZERO,
+                    U256::ZERO
+                ));
+            }
+        }
+
+        let tip = chain.tip().header().number();
+        *settled = settled.split_off(&tip.saturating_sub(RETRACTABLE_BLOCKS));
+
+        for execution in &executions {
+            self.history.record(execution.route_id, execution.status == ExecutionStatus::Included);
+        }
+        executions
+    }
+
+    /// Tracks the transactions settled in the blocks of the reverted `chain` again, and returns
+    /// their outcomes marked reorged. Their recorded inclusions are retracted, the chain that
+    /// replaces it settles them anew.
+    pub fn revert<N: NodePrimitives>(&self, chain: &Chain<N>) -> Vec<Execution> {
+        let mut pending = self.pending.lock().unwrap();
+        let mut settled = self.settled.lock().unwrap();
+        let mut executions = Vec::new();
+
+        for block in chain.blocks().values() {
+            let header = block.header();
+            let reorged = settled.remove(&header.number()).unwrap_or_default();
+            for Settled { tx_hash, tracked, included } in reorged {
+                self.history.retract(tracked.route_id, included);
+                executions.push(tracked.clone().settle(
+                    tx_hash,
+                    header.number(),
+                    header.timestamp(),
+                    ExecutionStatus::Reorged,
+                    I256::ZERO,
+                    U256::ZERO
+                ));
+                pending.insert(tx_hash, tracked);
+            }
+        }
+        executions
+    }
+}
+
+impl Tracked {
+    fn settle(
+        self,
+        tx_hash: TxHash,
+        block_number: u64,
+        timestamp: u64,
+        status: ExecutionStatus,
+        realized_profit: I256,
+        gas_cost: U256
+    ) -> Execution {
+        Execution {
+            tx_hash,
+            route_id: self.route_id,
+            token: self.token,
+            dexes: self.dexes,
+            config_version: self.config_version,
+            target_block: self.target_block,
+            block_number,
+            timestamp,
+            status,
+            expected_profit: self.expected_profit,
+            realized_profit,
+            gas_cost,
+        }
+    }
+}
+
+/// Balance change of `account` in `token` made by the ERC-20 transfers in `logs`.
+fn token_delta(logs: &[Log], token: Address, account: Address) -> I256 {
+    logs.iter()
+        .filter(|log| log.address == token)
+        .filter_map(|log| Transfer::decode_log_data(&log.data).ok())
+        .fold(I256::ZERO, |delta, transfer| {
+            let value = I256::from_raw(transfer.value);
+            match (transfer.from == account, transfer.to == account) {
+                (false, true) => delta.saturating_add(value),
+                (true, false) => delta.saturating_sub(value),
+                _ => delta,
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use alloy_primitives::{ address, TxHash, U256 };
+    use reth_execution_types::{ Chain, ExecutionOutcome };
+    use reth_primitives::{ Receipt, TxType };
+    use reth_primitives_traits::SignedTransaction;
+    use reth_testing_utils::generators::{ self, random_block, BlockParams, Rng };
+    use searcher_reth_repository::types::ExecutionStatus;
+
+    use crate::strategy::path_finding::{
+        bidding::{ InclusionHistory, RouteInclusion },
+        types::{ Hop, ProfitablePath, RoutePath },
+    };
+
+    use super::InclusionTracker;
+
+    fn path() -> ProfitablePath {
+        let hop = Hop {
+            dexType: 0,
+            dex: address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"),
+            srcToken: address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+            dstToken: address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"),
+        };
+        ProfitablePath {
+            route_path: RoutePath { hops: vec![hop] },
+            amount_in: U256::from(100),
+            amount_out: U256::from(110),
+            gas_used: 100_000,
+            gas_price: U256::from(1),
+            gas_cost: U256::from(1),
+            bid: U256::ZERO,
+            bid_per_gas: 0,
+            profit: U256::from(9),
+            profit_ratio: 90_000,
+        }
+    }
+
+    /// Block `number` with a single successful transaction when `with_tx`, and its hash.
+    fn chain(rng: &mut impl Rng, number: u64, with_tx: bool) -> (Chain, Option<TxHash>) {
+        let params = BlockParams { tx_count: Some(u8::from(with_tx)), ..Default::default() };
+        let block = random_block(rng, number, params).try_recover().unwrap();
+        let tx_hash = block.body().transactions.first().map(|tx| *tx.tx_hash());
+        let receipts = tx_hash
+            .iter()
+            .map(|_| Receipt {
+                tx_type: TxType::Eip1559,
+                success: true,
+                cumulative_gas_used: 21_000,
+                logs: Vec::new(),
+            })
+            .collect();
+        let outcome = ExecutionOutcome {
+            receipts: vec![receipts],
+            first_block: number,
+            ..Default::default()
+        };
+        (Chain::from_block(block, outcome, None), tx_hash)
+    }
+
+    #[test]
+    fn tracks_reorged_transactions_again() {
+        let mut rng = generators::rng();
+        let tracker = InclusionTracker::new(Arc::new(InclusionHistory::default()));
+        let route_id = path().route_path.id();
+        let (included, tx_hash) = chain(&mut rng, 10, true);
+        let tx_hash = tx_hash.unwrap();
+        tracker.track(tx_hash, &path(), 1, 10, 10);
+
+        let executions = tracker.settle(&included);
+        assert_eq!(executions.len(), 1);
+        assert_eq!(executions[0].tx_hash, tx_hash);
+        assert_eq!(executions[0].status, ExecutionStatus::Included);
+        assert_eq!(
+            tracker.history().stats(route_id),
+            RouteInclusion { submitted: 1, included: 1 }
+        );
+
+        // the block is reorged out: the outcome is marked and its inclusion retracted
+        let executions = tracker.revert(&included);
+        assert_eq!(executions.len(), 1);
+        assert_eq!(executions[0].tx_hash, tx_hash);
+        assert_eq!(executions[0].status, ExecutionStatus::Reorged);
+        assert_eq!(executions[0].block_number, 10);
+        assert_eq!(tracker.history().stats(route_id), RouteInclusion::default());
+
+        // the replacing block leaves it out, which is its last target block
+        let (replacing, _) = chain(&mut rng, 10, false);
+        let executions = tracker.settle(&replacing);
+        assert_eq!(executions.len(), 1);
+        assert_eq!(executions[0].tx_hash, tx_hash);
+        assert_eq!(executions[0].status, ExecutionStatus::Missed);
+        assert_eq!(
+            tracker.history().stats(route_id),
+            RouteInclusion { submitted: 1, included: 0 }
+        );
+
+        // reorging the replacing block tracks the missed transaction again, and the block it was
+        // included in settles it once more
+        assert_eq!(tracker.revert(&replacing).len(), 1);
+        let executions = tracker.settle(&included);
+        assert_eq!(executions.len(), 1);
+        assert_eq!(executions[0].status, ExecutionStatus::Included);
+        assert_eq!(
+            tracker.history().stats(route_id),
+            RouteInclusion { submitted: 1, included: 1 }
+        );
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::BTreeMap;
+
+use alloy_primitives::B256;
+
+use crate::{
+    Error,
+    Header,
+    Message,
+    MessageBody,
+    FORMAT_VERSION,
+    FRAME_HEADER_LEN,
+    MAGIC,
+};
+
+/// A decoded datagram: the header of its message and one chunk of the payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame<'a> {
+    pub kind: u8,
+    pub header: Header,
+    pub chunk_index: u16,
+    pub chunk_count: u16,
+    pub chunk: &'a [u8],
+}
+
+/// Decodes the frame in `datagram`.
+pub fn decode_frame(datagram: &[u8]) -> Result<Frame<'_>, Error> {
+    if datagram.len() < FRAME_HEADER_LEN {
+        return Err(Error::Truncated);
+    }
+    if datagram[0..2] != MAGIC {
+        return Err(Error::BadMagic);
+    }
+    if datagram[2] != FORMAT_VERSION {
+        return Err(Error::UnsupportedVersion(datagram[2]));
+    }
+    let u64_at = |offset: usize| {
+        u64::from_be_bytes(datagram[offset..offset + 8].try_into().expect("8 bytes"))
+    };
+    let u16_at = |offset: usize| {
+        u16::from_be_bytes(datagram[offset..offset + 2].try_into().expect("2 bytes"))
+    };
+    let chunk_len = u32::from_be_bytes(datagram[72..76].try_into().expect("4 bytes")) as usize;
+    let chunk = datagram[FRAME_HEADER_LEN..].get(..chunk_len).ok_or(Error::Truncated)?;
+    let (chunk_index, chunk_count) = (u16_at(68), u16_at(70));
+    if chunk_index >= chunk_count {
+        return Err(Error::BadChunk { index: chunk_index, count: chunk_count });
+    }
+
+    Ok(Frame {
+        kind: datagram[3],
+        header: Header {
+            chain_id: u64_at(4),
+            block_number: u64_at(12),
+            block_hash: B256::from_slice(&datagram[20..52]),
+            config_version: u64_at(52),
+            sequence: u64_at(60),
+        },
+        chunk_index,
+        chunk_count,
+        chunk,
+    })
+}
+
+/// Chunks received of a message.
+#[derive(Debug)]
+struct Partial {
+    kind: u8,
+    header: Header,
+    chunks: Vec<Option<Vec<u8>>>,
+    received: usize,
+}
+
+/// Reassembles the messages of a socket from its datagrams.
+///
+/// Chunks may arrive in any order. At most `max_partial` incomplete messages are kept, the
+/// oldest by sequence number is dropped when another one starts, so messages that lost a chunk
+/// don't pile up.
+#[derive(Debug)]
+pub struct Reassembler {
+    max_partial: usize,
+    partial: BTreeMap<u64, Partial>,
+}
+
+impl Default for Reassembler {
+    fn default() -> Self {
+        Self::new(16)
+    }
+}
+
+impl Reassembler {
+    pub fn new(max_partial: usize) -> Self {
+        Self { max_partial: max_partial.max(1), partial: BTreeMap::new() }
+    }
+
+    /// Adds the datagram, returns its message once every chunk of it was received.
+    pub fn push(&mut self, datagram: &[u8]) -> Result<Option<Message>, Error> {
+        let frame = decode_frame(datagram)?;
+        if frame.chunk_count == 1 {
+            let body = MessageBody::decode(frame.kind, frame.chunk)?;
+            return Ok(Some(Message { header: frame.header, body }));
+        }
+
+        let sequence = frame.header.sequence;
+        if !self.partial.contains_key(&sequence) && self.partial.len() >= self.max_partial {
+            self.partial.pop_first();
+        }
+        let partial = self.partial.entry(sequence).or_insert_with(|| Partial {
+            kind: frame.kind,
+            header: frame.header,
+            chunks: vec![None; frame.chunk_count as usize],
+            received: 0,
+        });
+        if partial.kind != frame.kind ||
+            partial.header != frame.header ||
+            partial.chunks.len() != frame.chunk_count as usize
+        {
+            self.partial.remove(&sequence);
+            return Err(Error::BadChunk { index: frame.chunk_index, count: frame.chunk_count });
+        }
+        let slot = &mut partial.chunks[frame.chunk_index as usize];
+        if slot.is_none() {
+            *slot = Some(frame.chunk.to_vec());
+            partial.received += 1;
+        }
+        if partial.received < partial.chunks.len() {
+            return Ok(None);
+        }
+
+        let partial = self.partial.remove(&sequence).expect("complete message is partial");
+        let payload = partial.chunks.into_iter().flatten().flatten().collect::<Vec<_>>();
+        let body = MessageBody::decode(partial.kind, &payload)?;
+        Ok(Some(Message { header: partial.header, body }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{ address, Bytes, B256, U256 };
+
+    use crate::{
+        message_kind,
+        Error,
+        Header,
+        Hop,
+        Message,
+        MessageBody,
+        Opportunity,
+        DEFAULT_MAX_DATAGRAM,
+        FRAME_HEADER_LEN,
+    };
+
+    use super::{ decode_frame, Reassembler };
+
+    /// Datagram size splitting [`bundle`] messages in four chunks.
+    const SMALL_DATAGRAM: usize = FRAME_HEADER_LEN + 128;
+
+    fn header(sequence: u64) -> Header {
+        Header {
+            chain_id: 1,
+            block_number: 100,
+            block_hash: B256::repeat_byte(0xab),
+            config_version: 3,
+            sequence,
+        }
+    }
+
+    fn opportunities(sequence: u64) -> Message {
+        let opportunity = Opportunity {
+            hops: vec![Hop {
+                dexType: 0,
+                dex: address!("B4e16d0168e52d35CaCD2c6185b44281Ec28C9Dc"),
+                srcToken: address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+                dstToken: address!("A0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48"),
+            }],
+            amountIn: U256::from(1_000),
+            expectedAmountOut: U256::from(1_100),
+            gasUsed: U256::from(150_000),
+            gasPrice: U256::from(10),
+            gasCost: U256::from(15),
+            bid: U256::from(5),
+            netProfit: U256::from(80),
+        };
+        Message { header: header(sequence), body: MessageBody::Opportunities(vec![opportunity]) }
+    }
+
+    /// A bundle of a single 300 bytes transaction.
+    fn bundle(sequence: u64) -> Message {
+        let body = MessageBody::bundle(Some(B256::repeat_byte(1)), vec![Bytes::from(vec![2; 300])]);
+        Message { header: header(sequence), body }
+    }
+
+    #[test]
+    fn decodes_single_frame_messages() {
+        let message = opportunities(7);
+        let frames = message.encode(DEFAULT_MAX_DATAGRAM).unwrap();
+        assert_eq!(frames.len(), 1);
+
+        let frame = decode_frame(&frames[0]).unwrap();
+        assert_eq!(frame.kind, message_kind::OPPORTUNITIES);
+        assert_eq!(frame.header, header(7));
+        assert_eq!((frame.chunk_index, frame.chunk_count), (0, 1));
+        assert_eq!(frame.chunk, message.body.encode());
+
+        assert_eq!(Reassembler::default().push(&frames[0]).unwrap(), Some(message));
+    }
+
+    #[test]
+    fn encodes_retractions_in_an_empty_frame() {
+        let message = Message { header: header(1), body: MessageBody::Retraction };
+        let frames = message.encode(DEFAULT_MAX_DATAGRAM).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].len(), FRAME_HEADER_LEN);
+
+        let frame = decode_frame(&frames[0]).unwrap();
+        assert_eq!(frame.kind, message_kind::RETRACTION);
+        assert!(frame.chunk.is_empty());
+        assert_eq!(Reassembler::default().push(&frames[0]).unwrap(), Some(message));
+    }
+
+    #[test]
+    fn reassembles_chunks_out_of_order() {
+        let message = bundle(3);
+        let frames = message.encode(SMALL_DATAGRAM).unwrap();
+        assert_eq!(frames.len(), 4);
+        assert!(frames.iter().all(|frame| frame.len() <= SMALL_DATAGRAM));
+
+        let mut reassembler = Reassembler::default();
+        for index in [2, 0, 3] {
+            assert_eq!(reassembler.push(&frames[index]).unwrap(), None);
+        }
+        // a repeated chunk doesn't count twice
+        assert_eq!(reassembler.push(&frames[0]).unwrap(), None);
+        assert_eq!(reassembler.push(&frames[1]).unwrap(), Some(message));
+    }
+
+    #[test]
+    fn rejects_malformed_frames() {
+        let frames = bundle(1).encode(SMALL_DATAGRAM).unwrap();
+        let mut reassembler = Reassembler::default();
+
+        let short_header = &frames[0][..FRAME_HEADER_LEN - 1];
+        assert_eq!(reassembler.push(short_header), Err(Error::Truncated));
+        let short_chunk = &frames[0][..frames[0].len() - 1];
+        assert_eq!(reassembler.push(short_chunk), Err(Error::Truncated));
+
+        let mut bad_magic = frames[0].clone();
+        bad_magic[0] = b'X';
+        assert_eq!(reassembler.push(&bad_magic), Err(Error::BadMagic));
+
+        let mut bad_version = frames[0].clone();
+        bad_version[2] = 0xff;
+        assert_eq!(reassembler.push(&bad_version), Err(Error::UnsupportedVersion(0xff)));
+
+        let mut bad_index = frames[0].clone();
+        bad_index[68..70].copy_from_slice(&4u16.to_be_bytes());
+        assert_eq!(reassembler.push(&bad_index), Err(Error::BadChunk { index: 4, count: 4 }));
+
+        // a chunk disagreeing with the other chunks of its message drops the message
+        let mut other_kind = frames[1].clone();
+        other_kind[3] = message_kind::BACKRUN;
+        assert_eq!(reassembler.push(&frames[0]).unwrap(), None);
+        assert_eq!(reassembler.push(&other_kind), Err(Error::BadChunk { index: 1, count: 4 }));
+        for frame in &frames[1..] {
+            assert_eq!(reassembler.push(frame).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn evicts_the_oldest_partial_message() {
+        let messages = [bundle(1), bundle(2), bundle(3)];
+        let frames = messages
+            .iter()
+            .map(|message| message.encode(SMALL_DATAGRAM).unwrap())
+            .collect::<Vec<_>>();
+        let mut reassembler = Reassembler::new(2);
+
+        for frames in &frames {
+            assert_eq!(reassembler.push(&frames[0]).unwrap(), None);
+        }
+        // the third message evicted the first one, whose other chunks start it over and evict
+        // the second one
+        for frame in &frames[0][1..] {
+            assert_eq!(reassembler.push(frame).unwrap(), None);
+        }
+        assert_eq!(reassembler.push(&frames[2][1]).unwrap(), None);
+        assert_eq!(reassembler.push(&frames[2][2]).unwrap(), None);
+        assert_eq!(reassembler.push(&frames[2][3]).unwrap(), Some(messages[2].clone()));
+        assert_eq!(reassembler.push(&frames[0][0]).unwrap(), Some(messages[0].clone()));
+        for frame in &frames[1][1..] {
+            assert_eq!(reassembler.push(frame).unwrap(), None);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Message format of the searcher-reth output socket.
+//!
+//! Every message is sent as one or more datagrams, each a frame: a fixed-size header followed by
+//! a chunk of the ABI encoded payload of the message. Integers are big-endian.
+//!
+//! | offset | size | field                                            |
+//! |--------|------|--------------------------------------------------|
+//! | 0      | 2    | magic, `"SR"`                                    |
+//! | 2      | 1    | format version, [`FORMAT_VERSION`]               |
+//! | 3      | 1    | message kind, see [`message_kind`]               |
+//! | 4      | 8    | chain id                                         |
+//! | 12     | 8    | block number                                     |
+//! | 20     | 32   | block hash, zero for bundles of upcoming blocks  |
+//! | 52     | 8    | config version                                   |
+//! | 60     | 8    | sequence number of the message                   |
+//! | 68     | 2    | chunk index                                      |
+//! | 70     | 2    | chunk count                                      |
+//! | 72     | 4    | chunk length                                     |
+//! | 76     | ..   | chunk                                            |
+//!
+//! Sequence numbers grow by one per message of a socket, a gap means messages were lost. A
+//! message larger than a datagram is split in chunks sharing its sequence number, which
+//! [`Reassembler`] puts back together:
+//!
+//! ```ignore
+//! let mut reassembler = Reassembler::default();
+//! let mut buf = vec![0; DEFAULT_MAX_DATAGRAM];
+//! loop {
+//!     let len = sock.recv(&mut buf)?;
+//!     if let Some(Message { header, body }) = reassembler.push(&buf[..len])? {
+//!         // ...
+//!     }
+//! }
+//! ```
+
+mod decoder;
+mod message;
+
+pub use decoder::{ decode_frame, Frame, Reassembler };
+pub use message::{ Backrun, Bundle, Hop, Message, MessageBody, Opportunity };
+
+use std::fmt;
+
+use alloy_primitives::B256;
+
+/// First bytes of every frame.
+pub const MAGIC: [u8; 2] = *b"SR";
+/// Version of the frame layout and payloads, bumped on every incompatible change.
+pub const FORMAT_VERSION: u8 = 1;
+/// Length of the frame header.
+pub const FRAME_HEADER_LEN: usize = 76;
+/// Datagram size the searcher uses by default.
+pub const DEFAULT_MAX_DATAGRAM: usize = 64 * 1024;
+
+/// Kind byte of every message.
+pub mod message_kind {
+    /// Opportunities found on top of a block, payload `Opportunity[]`.
+    pub const OPPORTUNITIES: u8 = 0;
+    /// The block left the canonical chain, its opportunities are void. Empty payload.
+    pub const RETRACTION: u8 = 1;
+    /// Opportunities behind a pending transaction, payload `Backrun`.
+    pub const BACKRUN: u8 = 2;
+    /// Signed transactions for the block, payload `Bundle`.
+    pub const BUNDLE: u8 = 3;
+}
+
+/// Fields shared by every message.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Header {
+    pub chain_id: u64,
+    pub block_number: u64,
+    pub block_hash: B256,
+    /// Version of the searcher configuration the message was produced with.
+    pub config_version: u64,
+    pub sequence: u64,
+}
+
+/// Errors of encoding and decoding frames.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The datagram is shorter than its header says.
+    Truncated,
+    BadMagic,
+    UnsupportedVersion(u8),
+    UnknownKind(u8),
+    /// The chunk index is not below the chunk count, or the chunks of a message disagree.
+    BadChunk { index: u16, count: u16 },
+    /// The payload needs more chunks than a frame can number.
+    TooLarge(usize),
+    /// The reassembled payload is not a valid ABI encoding of its kind.
+    Abi(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Truncated => f.write_str("truncated frame"),
+            Error::BadMagic => f.write_str("bad frame magic"),
+            Error::UnsupportedVersion(version) => write!(f, "unsupported format version {version}"),
+            Error::UnknownKind(kind) => write!(f, "unknown message kind {kind}"),
+            Error::BadChunk { index, count } => write!(f, "bad chunk {index} of {count}"),
+            Error::TooLarge(len) => write!(f, "payload of {len} bytes is too large"),
+            Error::Abi(err) => write!(f, "invalid payload: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
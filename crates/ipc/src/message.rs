@@ -0,0 +1,129 @@
+use alloy_primitives::{ Bytes, B256 };
+use alloy_sol_types::{ sol, SolValue };
+
+use crate::{ message_kind, Error, Header, FORMAT_VERSION, FRAME_HEADER_LEN, MAGIC };
+
+sol! {
+    #![sol(all_derives)]
+
+    struct Hop {
+        uint8 dexType;
+        address dex;
+        address srcToken;
+        address dstToken;
+    }
+
+    // amounts are in units of the start token of the route, gas price in wei
+    struct Opportunity {
+        Hop[] hops;
+        uint256 amountIn;
+        uint256 expectedAmountOut;
+        uint256 gasUsed;
+        uint256 gasPrice;
+        uint256 gasCost;
+        uint256 bid;
+        uint256 netProfit;
+    }
+
+    // opportunities to include right after `targetTx`
+    struct Backrun {
+        bytes32 targetTx;
+        Opportunity[] opportunities;
+    }
+
+    // signed transactions to include in order at the top of the block, `targetTx` is the
+    // backrun transaction leading the bundle, or zero
+    struct Bundle {
+        bytes32 targetTx;
+        bytes[] transactions;
+    }
+}
+
+/// Payload of a message, by kind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MessageBody {
+    Opportunities(Vec<Opportunity>),
+    Retraction,
+    Backrun(Backrun),
+    Bundle(Bundle),
+}
+
+impl MessageBody {
+    pub fn backrun(target_tx: B256, opportunities: Vec<Opportunity>) -> Self {
+        Self::Backrun(Backrun { targetTx: target_tx, opportunities })
+    }
+
+    pub fn bundle(target_tx: Option<B256>, transactions: Vec<Bytes>) -> Self {
+        Self::Bundle(Bundle { targetTx: target_tx.unwrap_or_default(), transactions })
+    }
+
+    /// Kind byte of the message, see [`message_kind`].
+    pub fn kind(&self) -> u8 {
+        match self {
+            Self::Opportunities(_) => message_kind::OPPORTUNITIES,
+            Self::Retraction => message_kind::RETRACTION,
+            Self::Backrun(_) => message_kind::BACKRUN,
+            Self::Bundle(_) => message_kind::BUNDLE,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            Self::Opportunities(opportunities) => opportunities.abi_encode(),
+            Self::Retraction => Vec::new(),
+            Self::Backrun(backrun) => backrun.abi_encode(),
+            Self::Bundle(bundle) => bundle.abi_encode(),
+        }
+    }
+
+    pub fn decode(kind: u8, payload: &[u8]) -> Result<Self, Error> {
+        let abi = |err: alloy_sol_types::Error| Error::Abi(err.to_string());
+        match kind {
+            message_kind::OPPORTUNITIES =>
+                Vec::<Opportunity>::abi_decode(payload).map(Self::Opportunities).map_err(abi),
+            message_kind::RETRACTION => Ok(Self::Retraction),
+            message_kind::BACKRUN => Backrun::abi_decode(payload).map(Self::Backrun).map_err(abi),
+            message_kind::BUNDLE => Bundle::abi_decode(payload).map(Self::Bundle).map_err(abi),
+            kind => Err(Error::UnknownKind(kind)),
+        }
+    }
+}
+
+/// A message of the output socket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub header: Header,
+    pub body: MessageBody,
+}
+
+impl Message {
+    /// Frames of the message, one per datagram of at most `max_datagram` bytes. An empty payload
+    /// still takes one frame.
+    pub fn encode(&self, max_datagram: usize) -> Result<Vec<Vec<u8>>, Error> {
+        let payload = self.body.encode();
+        let chunk_len = max_datagram.saturating_sub(FRAME_HEADER_LEN).max(1);
+        let count = payload.len().div_ceil(chunk_len).max(1);
+        let count = u16::try_from(count).map_err(|_| Error::TooLarge(payload.len()))?;
+
+        let chunks = payload.chunks(chunk_len).chain(payload.is_empty().then_some(&[][..]));
+        Ok(chunks
+            .enumerate()
+            .map(|(index, chunk)| {
+                let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + chunk.len());
+                frame.extend_from_slice(&MAGIC);
+                frame.push(FORMAT_VERSION);
+                frame.push(self.body.kind());
+                frame.extend_from_slice(&self.header.chain_id.to_be_bytes());
+                frame.extend_from_slice(&self.header.block_number.to_be_bytes());
+                frame.extend_from_slice(self.header.block_hash.as_slice());
+                frame.extend_from_slice(&self.header.config_version.to_be_bytes());
+                frame.extend_from_slice(&self.header.sequence.to_be_bytes());
+                frame.extend_from_slice(&(index as u16).to_be_bytes());
+                frame.extend_from_slice(&count.to_be_bytes());
+                frame.extend_from_slice(&(chunk.len() as u32).to_be_bytes());
+                frame.extend_from_slice(chunk);
+                frame
+            })
+            .collect())
+    }
+}
@@ -1,12 +1,20 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
+mod m20261018_000001_add_dex_tokens;
+mod m20261018_000002_create_execution;
+mod m20261018_000003_create_competitor_arbitrage;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20220101_000001_create_table::Migration)]
+        vec![
+            Box::new(m20220101_000001_create_table::Migration),
+            Box::new(m20261018_000001_add_dex_tokens::Migration),
+            Box::new(m20261018_000002_create_execution::Migration),
+            Box::new(m20261018_000003_create_competitor_arbitrage::Migration)
+        ]
     }
 }
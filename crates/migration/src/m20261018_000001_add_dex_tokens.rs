@@ -0,0 +1,44 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Dex {
+    Table,
+    Token0,
+    Token1,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite only supports one column per ALTER TABLE
+        manager.alter_table(
+            Table::alter()
+                .table(Dex::Table)
+                .add_column(ColumnDef::new(Dex::Token0).string().not_null().default(""))
+                .to_owned()
+        ).await?;
+
+        manager.alter_table(
+            Table::alter()
+                .table(Dex::Table)
+                .add_column(ColumnDef::new(Dex::Token1).string().not_null().default(""))
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.alter_table(
+            Table::alter().table(Dex::Table).drop_column(Dex::Token1).to_owned()
+        ).await?;
+        manager.alter_table(
+            Table::alter().table(Dex::Table).drop_column(Dex::Token0).to_owned()
+        ).await?;
+
+        Ok(())
+    }
+}
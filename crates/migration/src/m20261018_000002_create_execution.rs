@@ -0,0 +1,67 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum Execution {
+    Table,
+    ChainId,
+    TxHash,
+    RouteId,
+    Token,
+    Dexes,
+    ConfigVersion,
+    TargetBlock,
+    BlockNumber,
+    Timestamp,
+    Status,
+    ExpectedProfit,
+    RealizedProfit,
+    GasCost,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Create Execution table, amounts are decimal strings as they don't fit in an integer
+        manager.create_table(
+            Table::create()
+                .table(Execution::Table)
+                .if_not_exists()
+                .col(ColumnDef::new(Execution::ChainId).integer().not_null())
+                .col(ColumnDef::new(Execution::TxHash).string().not_null())
+                .col(ColumnDef::new(Execution::RouteId).string().not_null())
+                .col(ColumnDef::new(Execution::Token).string().not_null())
+                .col(ColumnDef::new(Execution::Dexes).string().not_null())
+                .col(ColumnDef::new(Execution::ConfigVersion).integer().not_null())
+                .col(ColumnDef::new(Execution::TargetBlock).integer().not_null())
+                .col(ColumnDef::new(Execution::BlockNumber).integer().not_null())
+                .col(ColumnDef::new(Execution::Timestamp).integer().not_null())
+                .col(ColumnDef::new(Execution::Status).string().not_null())
+                .col(ColumnDef::new(Execution::ExpectedProfit).string().not_null())
+                .col(ColumnDef::new(Execution::RealizedProfit).string().not_null())
+                .col(ColumnDef::new(Execution::GasCost).string().not_null())
+                .primary_key(Index::create().col(Execution::ChainId).col(Execution::TxHash))
+                .to_owned()
+        ).await?;
+
+        // PnL summaries select a time range
+        manager.create_index(
+            Index::create()
+                .name("idx_execution_timestamp")
+                .table(Execution::Table)
+                .col(Execution::ChainId)
+                .col(Execution::Timestamp)
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(Execution::Table).to_owned()).await?;
+
+        Ok(())
+    }
+}
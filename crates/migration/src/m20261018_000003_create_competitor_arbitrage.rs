@@ -0,0 +1,70 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[derive(DeriveIden)]
+enum CompetitorArbitrage {
+    Table,
+    ChainId,
+    TxHash,
+    CycleIndex,
+    BlockNumber,
+    Timestamp,
+    Sender,
+    Contract,
+    Token,
+    Pools,
+    Profit,
+    RouteId,
+    MatchKind,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Create CompetitorArbitrage table, one row per token cycle of a transaction
+        manager.create_table(
+            Table::create()
+                .table(CompetitorArbitrage::Table)
+                .if_not_exists()
+                .col(ColumnDef::new(CompetitorArbitrage::ChainId).integer().not_null())
+                .col(ColumnDef::new(CompetitorArbitrage::TxHash).string().not_null())
+                .col(ColumnDef::new(CompetitorArbitrage::CycleIndex).integer().not_null())
+                .col(ColumnDef::new(CompetitorArbitrage::BlockNumber).integer().not_null())
+                .col(ColumnDef::new(CompetitorArbitrage::Timestamp).integer().not_null())
+                .col(ColumnDef::new(CompetitorArbitrage::Sender).string().not_null())
+                .col(ColumnDef::new(CompetitorArbitrage::Contract).string().not_null())
+                .col(ColumnDef::new(CompetitorArbitrage::Token).string().not_null())
+                .col(ColumnDef::new(CompetitorArbitrage::Pools).string().not_null())
+                .col(ColumnDef::new(CompetitorArbitrage::Profit).string().not_null())
+                .col(ColumnDef::new(CompetitorArbitrage::RouteId).string().not_null())
+                .col(ColumnDef::new(CompetitorArbitrage::MatchKind).string().not_null())
+                .primary_key(
+                    Index::create()
+                        .col(CompetitorArbitrage::ChainId)
+                        .col(CompetitorArbitrage::TxHash)
+                        .col(CompetitorArbitrage::CycleIndex)
+                )
+                .to_owned()
+        ).await?;
+
+        // arbitrages are queried by block range
+        manager.create_index(
+            Index::create()
+                .name("idx_competitor_arbitrage_block_number")
+                .table(CompetitorArbitrage::Table)
+                .col(CompetitorArbitrage::ChainId)
+                .col(CompetitorArbitrage::BlockNumber)
+                .to_owned()
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(CompetitorArbitrage::Table).to_owned()).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,27 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "competitor_arbitrage")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub chain_id: i64,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub tx_hash: String,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub cycle_index: i64,
+    #[sea_orm(indexed)]
+    pub block_number: i64,
+    pub timestamp: i64,
+    pub sender: String,
+    pub contract: String,
+    pub token: String,
+    pub pools: String,
+    pub profit: String,
+    pub route_id: String,
+    pub match_kind: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
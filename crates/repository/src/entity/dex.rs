@@ -9,6 +9,8 @@ pub struct Model {
     pub address: String,
     #[sea_orm(primary_key, auto_increment = false)]
     pub dex_type: String,
+    pub token0: String,
+    pub token1: String,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
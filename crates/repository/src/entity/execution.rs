@@ -0,0 +1,27 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "execution")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub chain_id: i64,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub tx_hash: String,
+    pub route_id: String,
+    pub token: String,
+    pub dexes: String,
+    pub config_version: i64,
+    pub target_block: i64,
+    pub block_number: i64,
+    #[sea_orm(indexed)]
+    pub timestamp: i64,
+    pub status: String,
+    pub expected_profit: String,
+    pub realized_profit: String,
+    pub gas_cost: String,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,4 +1,6 @@
 pub mod contract;
 pub mod token;
 pub mod dex;
+pub mod execution;
+pub mod competitor_arbitrage;
 pub mod prelude;
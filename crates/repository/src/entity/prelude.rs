@@ -1,4 +1,6 @@
 
 pub use super::contract::Entity as Contract;
 pub use super::token::Entity as Token;
-pub use super::dex::Entity as Dex;
\ No newline at end of file
+pub use super::dex::Entity as Dex;
+pub use super::execution::Entity as Execution;
+pub use super::competitor_arbitrage::Entity as CompetitorArbitrage;
\ No newline at end of file
@@ -3,7 +3,7 @@ pub mod types;
 
 use eyre::Result;
 use reth_revm::primitives::Address;
-use sea_orm::{ QueryOrder, TransactionTrait };
+use sea_orm::{ sea_query::OnConflict, QueryOrder, TransactionTrait };
 use sea_orm::{
     DatabaseConnection,
     Database,
@@ -14,10 +14,19 @@ use sea_orm::{
     ColumnTrait,
 };
 use entity::prelude::*;
-use entity::{ token, dex, contract };
+use entity::{ token, dex, contract, execution, competitor_arbitrage };
 
 use migration::{ Migrator, MigratorTrait };
-use types::{DexType, Priority};
+use types::{
+    CompetitorArbitrage,
+    CompetitorMatch,
+    DexType,
+    Execution,
+    Pool,
+    PnlGrouping,
+    PnlSummary,
+    Priority,
+};
 
 pub struct SearcherRepository {
     conn: DatabaseConnection,
@@ -49,17 +58,23 @@ impl SearcherRepository {
         Ok(result)
     }
 
-    pub async fn get_all_dexs(&self, chain_id: u64) -> Result<Vec<(Address, DexType)>> {
+    pub async fn get_all_dexs(&self, chain_id: u64) -> Result<Vec<Pool>> {
         let dexs = Dex::find()
             .filter(dex::Column::ChainId.eq(chain_id as i64))
             .all(&self.conn).await?;
 
         let result = dexs
             .into_iter()
+            // pools registered before their token pair was known can't be routed
+            .filter(|dex| !dex.token0.is_empty() && !dex.token1.is_empty())
             .map(|dex| {
-                let addr: Address = dex.address.parse().unwrap();
                 let dex_type = dex.dex_type.parse::<i64>().unwrap();
-                (addr, dex_type as DexType)
+                Pool {
+                    address: dex.address.parse().unwrap(),
+                    dex_type: dex_type as DexType,
+                    token0: dex.token0.parse().unwrap(),
+                    token1: dex.token1.parse().unwrap(),
+                }
             })
             .collect();
 
@@ -71,7 +86,7 @@ impl SearcherRepository {
         chain_id: u64,
         new_tokens: &Option<Vec<(Address, i64)>>,
         deprecated_tokens: &Option<Vec<Address>>,
-        new_dexs: &Option<Vec<(DexType, Address)>>,
+        new_dexs: &Option<Vec<(DexType, Address, Address, Address)>>,
         deprecated_dexs: &Option<Vec<Address>>
     ) -> Result<()> {
         let txn = self.conn.begin().await?;
@@ -100,11 +115,13 @@ impl SearcherRepository {
         }
 
         if let Some(dexs) = new_dexs {
-            for (dex_type, address) in dexs {
+            for (dex_type, address, token0, token1) in dexs {
                 let dex = dex::ActiveModel {
                     chain_id: Set(chain_id as i64),
                     address: Set(address.to_string()),
                     dex_type: Set((*dex_type as i64).to_string()),
+                    token0: Set(token0.to_string()),
+                    token1: Set(token1.to_string()),
                 };
                 dex.insert(&txn).await?;
             }
@@ -145,4 +162,203 @@ impl SearcherRepository {
             .exec(&self.conn).await?;
         Ok(())
     }
+
+    /// Records settled executions, an execution settled again (after a reorg) is overwritten.
+    pub async fn insert_executions(&self, chain_id: u64, executions: &[Execution]) -> Result<()> {
+        let txn = self.conn.begin().await?;
+
+        for execution in executions {
+            let model = execution::ActiveModel {
+                chain_id: Set(chain_id as i64),
+                tx_hash: Set(execution.tx_hash.to_string()),
+                route_id: Set(execution.route_id.to_string()),
+                token: Set(execution.token.to_string()),
+                dexes: Set(
+                    execution.dexes
+                        .iter()
+                        .map(Address::to_string)
+                        .collect::<Vec<_>>()
+                        .join(",")
+                ),
+                config_version: Set(execution.config_version as i64),
+                target_block: Set(execution.target_block as i64),
+                block_number: Set(execution.block_number as i64),
+                timestamp: Set(execution.timestamp as i64),
+                status: Set(execution.status.to_string()),
+                expected_profit: Set(execution.expected_profit.to_string()),
+                realized_profit: Set(execution.realized_profit.to_string()),
+                gas_cost: Set(execution.gas_cost.to_string()),
+            };
+            execution::Entity::insert(model)
+                .on_conflict(
+                    OnConflict::columns([execution::Column::ChainId, execution::Column::TxHash])
+                        .update_columns([
+                            execution::Column::BlockNumber,
+                            execution::Column::Timestamp,
+                            execution::Column::Status,
+                            execution::Column::RealizedProfit,
+                            execution::Column::GasCost,
+                        ])
+                        .to_owned()
+                )
+                .exec(&txn).await?;
+        }
+
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Executions settled at or after `since` (unix seconds), oldest first.
+    pub async fn get_executions(
+        &self,
+        chain_id: u64,
+        since: Option<u64>
+    ) -> Result<Vec<Execution>> {
+        let executions = execution::Entity::find()
+            .filter(execution::Column::ChainId.eq(chain_id as i64))
+            .filter(execution::Column::Timestamp.gte(since.unwrap_or_default() as i64))
+            .order_by_asc(execution::Column::Timestamp)
+            .all(&self.conn).await?;
+
+        executions
+            .into_iter()
+            .map(|execution| {
+                Ok(Execution {
+                    tx_hash: execution.tx_hash.parse()?,
+                    route_id: execution.route_id.parse()?,
+                    token: execution.token.parse()?,
+                    dexes: execution.dexes
+                        .split(',')
+                        .filter(|dex| !dex.is_empty())
+                        .map(str::parse)
+                        .collect::<Result<_, _>>()?,
+                    config_version: execution.config_version as u64,
+                    target_block: execution.target_block as u64,
+                    block_number: execution.block_number as u64,
+                    timestamp: execution.timestamp as u64,
+                    status: execution.status.parse()?,
+                    expected_profit: execution.expected_profit.parse()?,
+                    realized_profit: execution.realized_profit.parse()?,
+                    gas_cost: execution.gas_cost.parse()?,
+                })
+            })
+            .collect()
+    }
+
+    /// Realized PnL of the executions settled at or after `since` (unix seconds).
+    pub async fn get_pnl_summary(
+        &self,
+        chain_id: u64,
+        grouping: PnlGrouping,
+        since: Option<u64>
+    ) -> Result<Vec<PnlSummary>> {
+        let executions = self.get_executions(chain_id, since).await?;
+        Ok(PnlSummary::summarize(grouping, &executions))
+    }
+
+    /// Records the arbitrages of other searchers, a transaction detected again (after a reorg)
+    /// is overwritten.
+    pub async fn insert_competitor_arbitrages(
+        &self,
+        chain_id: u64,
+        arbitrages: &[CompetitorArbitrage]
+    ) -> Result<()> {
+        let txn = self.conn.begin().await?;
+
+        for arbitrage in arbitrages {
+            let model = competitor_arbitrage::ActiveModel {
+                chain_id: Set(chain_id as i64),
+                tx_hash: Set(arbitrage.tx_hash.to_string()),
+                cycle_index: Set(arbitrage.cycle_index as i64),
+                block_number: Set(arbitrage.block_number as i64),
+                timestamp: Set(arbitrage.timestamp as i64),
+                sender: Set(arbitrage.sender.to_string()),
+                contract: Set(arbitrage.contract.to_string()),
+                token: Set(arbitrage.token.to_string()),
+                pools: Set(
+                    arbitrage.pools
+                        .iter()
+                        .map(Address::to_string)
+                        .collect::<Vec<_>>()
+                        .join(",")
+                ),
+                profit: Set(arbitrage.profit.to_string()),
+                route_id: Set(arbitrage.route_id.map(|id| id.to_string()).unwrap_or_default()),
+                match_kind: Set(arbitrage.match_kind.to_string()),
+            };
+            competitor_arbitrage::Entity::insert(model)
+                .on_conflict(
+                    OnConflict::columns([
+                        competitor_arbitrage::Column::ChainId,
+                        competitor_arbitrage::Column::TxHash,
+                        competitor_arbitrage::Column::CycleIndex,
+                    ])
+                        .update_columns([
+                            competitor_arbitrage::Column::BlockNumber,
+                            competitor_arbitrage::Column::Timestamp,
+                            competitor_arbitrage::Column::MatchKind,
+                        ])
+                        .to_owned()
+                )
+                .exec(&txn).await?;
+        }
+
+        txn.commit().await?;
+        Ok(())
+    }
+
+    /// Arbitrages of other searchers in the blocks `from_block..=to_block`, optionally only the
+    /// ones with `match_kind`, oldest first.
+    pub async fn get_competitor_arbitrages(
+        &self,
+        chain_id: u64,
+        from_block: Option<u64>,
+        to_block: Option<u64>,
+        match_kind: Option<CompetitorMatch>
+    ) -> Result<Vec<CompetitorArbitrage>> {
+        let mut query = competitor_arbitrage::Entity::find()
+            .filter(competitor_arbitrage::Column::ChainId.eq(chain_id as i64))
+            .filter(
+                competitor_arbitrage::Column::BlockNumber.gte(from_block.unwrap_or_default() as i64)
+            );
+        if let Some(to_block) = to_block {
+            query = query.filter(competitor_arbitrage::Column::BlockNumber.lte(to_block as i64));
+        }
+        if let Some(match_kind) = match_kind {
+            query = query.filter(
+                competitor_arbitrage::Column::MatchKind.eq(match_kind.to_string())
+            );
+        }
+        let arbitrages = query
+            .order_by_asc(competitor_arbitrage::Column::BlockNumber)
+            .order_by_asc(competitor_arbitrage::Column::TxHash)
+            .order_by_asc(competitor_arbitrage::Column::CycleIndex)
+            .all(&self.conn).await?;
+
+        arbitrages
+            .into_iter()
+            .map(|arbitrage| {
+                Ok(CompetitorArbitrage {
+                    tx_hash: arbitrage.tx_hash.parse()?,
+                    cycle_index: arbitrage.cycle_index as u64,
+                    block_number: arbitrage.block_number as u64,
+                    timestamp: arbitrage.timestamp as u64,
+                    sender: arbitrage.sender.parse()?,
+                    contract: arbitrage.contract.parse()?,
+                    token: arbitrage.token.parse()?,
+                    pools: arbitrage.pools
+                        .split(',')
+                        .filter(|pool| !pool.is_empty())
+                        .map(str::parse)
+                        .collect::<Result<_, _>>()?,
+                    profit: arbitrage.profit.parse()?,
+                    route_id: match arbitrage.route_id.as_str() {
+                        "" => None,
+                        route_id => Some(route_id.parse()?),
+                    },
+                    match_kind: arbitrage.match_kind.parse()?,
+                })
+            })
+            .collect()
+    }
 }
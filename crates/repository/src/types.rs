@@ -1,3 +1,8 @@
+use std::{ collections::BTreeMap, fmt, str::FromStr };
+
+use eyre::{ eyre, Error };
+use reth_revm::primitives::{ Address, B256, I256, U256 };
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Priority {
     Beginning, // USDC or USDT, beginning token
@@ -23,3 +28,262 @@ impl From<i64> for Priority {
 }
 
 pub type DexType = u8;
+
+/// Known values of `Hop.dexType`.
+pub mod dex_type {
+    use super::DexType;
+
+    /// Uniswap V2 pair (and forks with the same storage layout and a 0.3% fee).
+    pub const UNISWAP_V2: DexType = 0;
+    /// Uniswap V3 pool (concentrated liquidity).
+    pub const UNISWAP_V3: DexType = 1;
+    /// Curve StableSwap plain pool.
+    pub const CURVE_STABLESWAP: DexType = 2;
+    /// Balancer V2 weighted pool, quoted natively for weight ratios of 1, 2 and 4 only.
+    pub const BALANCER_WEIGHTED: DexType = 3;
+}
+
+/// A single pool (a `dex` row) and the token pair it trades.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pool {
+    pub address: Address,
+    pub dex_type: DexType,
+    pub token0: Address,
+    pub token1: Address,
+}
+
+impl Pool {
+    /// Returns the token received when swapping `token` through this pool.
+    pub fn other_token(&self, token: &Address) -> Option<Address> {
+        if *token == self.token0 {
+            Some(self.token1)
+        } else if *token == self.token1 {
+            Some(self.token0)
+        } else {
+            None
+        }
+    }
+}
+
+/// Outcome of a transaction signed for a found route.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExecutionStatus {
+    /// Included and succeeded.
+    Included,
+    /// Included and reverted, the gas was paid for nothing.
+    Reverted,
+    /// Not included in any of the blocks it targeted.
+    Missed,
+    /// Settled in a block that was reorged out, until the new chain settles it again.
+    Reorged,
+}
+
+impl fmt::Display for ExecutionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ExecutionStatus::Included => "included",
+            ExecutionStatus::Reverted => "reverted",
+            ExecutionStatus::Missed => "missed",
+            ExecutionStatus::Reorged => "reorged",
+        })
+    }
+}
+
+impl FromStr for ExecutionStatus {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "included" => Ok(ExecutionStatus::Included),
+            "reverted" => Ok(ExecutionStatus::Reverted),
+            "missed" => Ok(ExecutionStatus::Missed),
+            "reorged" => Ok(ExecutionStatus::Reorged),
+            _ => Err(eyre!("unknown execution status {s}")),
+        }
+    }
+}
+
+/// A settled transaction signed for a found route (an `execution` row).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Execution {
+    pub tx_hash: B256,
+    /// Hash of the hops of the route.
+    pub route_id: B256,
+    /// Start token of the route, the profits are in units of it.
+    pub token: Address,
+    /// Pools the route swaps through, in order.
+    pub dexes: Vec<Address>,
+    pub config_version: u64,
+    /// First block the transaction targeted.
+    pub target_block: u64,
+    /// Block the transaction was included in or, if missed, the block that settled it.
+    pub block_number: u64,
+    /// Timestamp of `block_number`.
+    pub timestamp: u64,
+    pub status: ExecutionStatus,
+    /// Net profit found by the simulation.
+    pub expected_profit: U256,
+    /// Balance change of the searcher contract in the start token, zero unless included.
+    pub realized_profit: I256,
+    /// Gas paid, in wei.
+    pub gas_cost: U256,
+}
+
+/// How a competitor's arbitrage cycle relates to the routes we search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompetitorMatch {
+    /// A route of ours, or the same cycle from another token, that our search selected for the
+    /// block: the competitor won the race.
+    Selected,
+    /// A route of ours that our search didn't select for the block.
+    NotSelected,
+    /// The same cycle as a route of ours from another token, not selected for the block.
+    Rotation,
+    /// Every pool of the cycle is indexed but the cycle is not one of our routes (too many
+    /// hops, or cut by the candidate limit).
+    NotEnumerated,
+    /// The cycle swaps through pools we don't index.
+    UnindexedPool,
+}
+
+impl fmt::Display for CompetitorMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CompetitorMatch::Selected => "selected",
+            CompetitorMatch::NotSelected => "not_selected",
+            CompetitorMatch::Rotation => "rotation",
+            CompetitorMatch::NotEnumerated => "not_enumerated",
+            CompetitorMatch::UnindexedPool => "unindexed_pool",
+        })
+    }
+}
+
+impl FromStr for CompetitorMatch {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "selected" => Ok(CompetitorMatch::Selected),
+            "not_selected" => Ok(CompetitorMatch::NotSelected),
+            "rotation" => Ok(CompetitorMatch::Rotation),
+            "not_enumerated" => Ok(CompetitorMatch::NotEnumerated),
+            "unindexed_pool" => Ok(CompetitorMatch::UnindexedPool),
+            _ => Err(eyre!("unknown competitor match {s}")),
+        }
+    }
+}
+
+/// A token cycle captured by another searcher in a committed block (a `competitor_arbitrage`
+/// row).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompetitorArbitrage {
+    pub tx_hash: B256,
+    /// Position of the cycle among the cycles of the transaction.
+    pub cycle_index: u64,
+    pub block_number: u64,
+    pub timestamp: u64,
+    /// Signer of the transaction.
+    pub sender: Address,
+    /// Contract the transaction called.
+    pub contract: Address,
+    /// Start token of the cycle, the profit is in units of it.
+    pub token: Address,
+    /// Pools of the cycle, in swap order.
+    pub pools: Vec<Address>,
+    /// Amount out of the last swap minus amount into the first one.
+    pub profit: I256,
+    /// Our route with the same cycle, if any.
+    pub route_id: Option<B256>,
+    pub match_kind: CompetitorMatch,
+}
+
+/// How PnL summaries are grouped, every group is also split by start token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PnlGrouping {
+    Token,
+    /// Every pool of a route is credited with the whole PnL of the route.
+    Dex,
+    /// UTC day of the block timestamp.
+    Day,
+}
+
+/// Realized PnL of the executions of a group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PnlSummary {
+    /// Token, dex address or `YYYY-MM-DD` day of the group.
+    pub key: String,
+    pub token: Address,
+    pub included: u64,
+    pub reverted: u64,
+    pub missed: u64,
+    pub reorged: u64,
+    /// Sum of the realized profits, in units of `token`.
+    pub realized_profit: I256,
+    /// Sum of the gas paid, in wei.
+    pub gas_cost: U256,
+}
+
+impl PnlSummary {
+    fn new(key: String, token: Address) -> Self {
+        Self {
+            key,
+            token,
+            included: 0,
+            reverted: 0,
+            missed: 0,
+            reorged: 0,
+            realized_profit: I256::ZERO,
+            gas_cost: U256::ZERO,
+        }
+    }
+
+    /// Adds `execution` to the summary.
+    pub(crate) fn add(&mut self, execution: &Execution) {
+        match execution.status {
+            ExecutionStatus::Included => self.included += 1,
+            ExecutionStatus::Reverted => self.reverted += 1,
+            ExecutionStatus::Missed => self.missed += 1,
+            ExecutionStatus::Reorged => self.reorged += 1,
+        }
+        self.realized_profit = self.realized_profit.saturating_add(execution.realized_profit);
+        self.gas_cost = self.gas_cost.saturating_add(execution.gas_cost);
+    }
+
+    /// Summaries of `executions` grouped by `grouping` and start token, ordered by key.
+    pub fn summarize<'a>(
+        grouping: PnlGrouping,
+        executions: impl IntoIterator<Item = &'a Execution>
+    ) -> Vec<Self> {
+        let mut summaries = BTreeMap::<(String, Address), PnlSummary>::new();
+        for execution in executions {
+            let keys = match grouping {
+                PnlGrouping::Token => vec![execution.token.to_string()],
+                PnlGrouping::Dex => execution.dexes.iter().map(Address::to_string).collect(),
+                PnlGrouping::Day => vec![utc_day(execution.timestamp)],
+            };
+            for key in keys {
+                summaries
+                    .entry((key.clone(), execution.token))
+                    .or_insert_with(|| PnlSummary::new(key, execution.token))
+                    .add(execution);
+            }
+        }
+        summaries.into_values().collect()
+    }
+}
+
+/// `YYYY-MM-DD` of the UTC day of a unix timestamp.
+fn utc_day(timestamp: u64) -> String {
+    // days to civil date, see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = (timestamp / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
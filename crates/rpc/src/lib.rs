@@ -1,12 +1,20 @@
 use std::sync::Arc;
 
-use jsonrpsee::{ core::{ async_trait, RpcResult }, proc_macros::rpc, tracing::info };
-use reth_revm::primitives::Address;
+use jsonrpsee::{
+    core::{ async_trait, RpcResult },
+    proc_macros::rpc,
+    tracing::info,
+    types::{ error::{ INTERNAL_ERROR_CODE, INVALID_PARAMS_CODE }, ErrorObjectOwned },
+};
+use reth_revm::primitives::{ Address, B256, I256, U256 };
 use searcher_reth_extension::{
-    strategy::path_finding::candidate::get_candidates,
+    strategy::path_finding::candidate::{ get_beginning_tokens, get_candidates, get_hops },
     SearcherExtension,
 };
-use searcher_reth_repository::{types::DexType, SearcherRepository};
+use searcher_reth_repository::{
+    types::{ CompetitorArbitrage, CompetitorMatch, DexType, PnlGrouping, PnlSummary },
+    SearcherRepository,
+};
 use serde::{ Deserialize, Serialize };
 use tokio::sync::RwLock;
 
@@ -28,10 +36,155 @@ pub struct UpdateProfitRateParameters {
 pub struct UpdateRoutePathParameters {
     pub new_tokens: Option<Vec<(Address, i64)>>,
     pub deprecated_tokens: Option<Vec<Address>>,
-    pub new_dexs: Option<Vec<(DexType, Address)>>,
+    /// (dex type, pool, token0, token1)
+    pub new_dexs: Option<Vec<(DexType, Address, Address, Address)>>,
     pub deprecated_dexs: Option<Vec<Address>>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCandidateLimitsParameters {
+    pub max_hops: Option<usize>,
+    pub max_candidates: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum PnlGroupBy {
+    Token,
+    Dex,
+    Day,
+}
+
+impl From<PnlGroupBy> for PnlGrouping {
+    fn from(group_by: PnlGroupBy) -> Self {
+        match group_by {
+            PnlGroupBy::Token => PnlGrouping::Token,
+            PnlGroupBy::Dex => PnlGrouping::Dex,
+            PnlGroupBy::Day => PnlGrouping::Day,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PnlSummaryParameters {
+    pub group_by: PnlGroupBy,
+    /// Only executions settled at or after this unix timestamp
+    pub since: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PnlSummaryResponse {
+    /// Token, dex address or YYYY-MM-DD day
+    pub key: String,
+    /// Start token the profits are in
+    pub token: Address,
+    pub included: u64,
+    pub reverted: u64,
+    pub missed: u64,
+    /// Settled in blocks that were reorged out and not settled again yet
+    pub reorged: u64,
+    pub realized_profit: I256,
+    /// Gas paid, in wei
+    pub gas_cost: U256,
+}
+
+impl From<PnlSummary> for PnlSummaryResponse {
+    fn from(summary: PnlSummary) -> Self {
+        Self {
+            key: summary.key,
+            token: summary.token,
+            included: summary.included,
+            reverted: summary.reverted,
+            missed: summary.missed,
+            reorged: summary.reorged,
+            realized_profit: summary.realized_profit,
+            gas_cost: summary.gas_cost,
+        }
+    }
+}
+
+/// How a competitor's arbitrage relates to our routes, see [`CompetitorMatch`]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CompetitorMatchKind {
+    Selected,
+    NotSelected,
+    Rotation,
+    NotEnumerated,
+    UnindexedPool,
+}
+
+impl From<CompetitorMatchKind> for CompetitorMatch {
+    fn from(kind: CompetitorMatchKind) -> Self {
+        match kind {
+            CompetitorMatchKind::Selected => CompetitorMatch::Selected,
+            CompetitorMatchKind::NotSelected => CompetitorMatch::NotSelected,
+            CompetitorMatchKind::Rotation => CompetitorMatch::Rotation,
+            CompetitorMatchKind::NotEnumerated => CompetitorMatch::NotEnumerated,
+            CompetitorMatchKind::UnindexedPool => CompetitorMatch::UnindexedPool,
+        }
+    }
+}
+
+impl From<CompetitorMatch> for CompetitorMatchKind {
+    fn from(kind: CompetitorMatch) -> Self {
+        match kind {
+            CompetitorMatch::Selected => CompetitorMatchKind::Selected,
+            CompetitorMatch::NotSelected => CompetitorMatchKind::NotSelected,
+            CompetitorMatch::Rotation => CompetitorMatchKind::Rotation,
+            CompetitorMatch::NotEnumerated => CompetitorMatchKind::NotEnumerated,
+            CompetitorMatch::UnindexedPool => CompetitorMatchKind::UnindexedPool,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CompetitorArbitragesParameters {
+    pub from_block: Option<u64>,
+    pub to_block: Option<u64>,
+    pub match_kind: Option<CompetitorMatchKind>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CompetitorArbitrageResponse {
+    pub tx_hash: B256,
+    pub cycle_index: u64,
+    pub block_number: u64,
+    pub timestamp: u64,
+    pub sender: Address,
+    pub contract: Address,
+    /// Start token the profit is in
+    pub token: Address,
+    pub pools: Vec<Address>,
+    pub profit: I256,
+    /// Our route with the same cycle
+    pub route_id: Option<B256>,
+    pub match_kind: CompetitorMatchKind,
+}
+
+impl From<CompetitorArbitrage> for CompetitorArbitrageResponse {
+    fn from(arbitrage: CompetitorArbitrage) -> Self {
+        Self {
+            tx_hash: arbitrage.tx_hash,
+            cycle_index: arbitrage.cycle_index,
+            block_number: arbitrage.block_number,
+            timestamp: arbitrage.timestamp,
+            sender: arbitrage.sender,
+            contract: arbitrage.contract,
+            token: arbitrage.token,
+            pools: arbitrage.pools,
+            profit: arbitrage.profit,
+            route_id: arbitrage.route_id,
+            match_kind: arbitrage.match_kind.into(),
+        }
+    }
+}
+
 #[rpc(server, namespace = "searcher")]
 pub trait SearcherRpcApi {
     /// Set searcher contract
@@ -45,6 +198,24 @@ pub trait SearcherRpcApi {
     // Update config of dex and token in in-memory and storage
     #[method(name = "update_route_paths")]
     async fn update_route_paths(&self, params: UpdateRoutePathParameters) -> RpcResult<()>;
+
+    /// Set max hops and max number of candidates, and regenerate route paths
+    #[method(name = "update_candidate_limits")]
+    async fn update_candidate_limits(
+        &self,
+        params: UpdateCandidateLimitsParameters
+    ) -> RpcResult<()>;
+
+    /// Realized PnL of the signed transactions per token, dex or day
+    #[method(name = "pnl_summary")]
+    async fn pnl_summary(&self, params: PnlSummaryParameters) -> RpcResult<Vec<PnlSummaryResponse>>;
+
+    /// Arbitrages other searchers captured on indexed pools, and how they match our routes
+    #[method(name = "competitor_arbitrages")]
+    async fn competitor_arbitrages(
+        &self,
+        params: CompetitorArbitragesParameters
+    ) -> RpcResult<Vec<CompetitorArbitrageResponse>>;
 }
 
 pub struct SearcherRpc {
@@ -61,8 +232,11 @@ impl SearcherRpc {
     ) -> Self {
         let dexs = repo.get_all_dexs(chain_id).await.unwrap();
         let tokens = repo.get_all_tokens(chain_id).await.unwrap();
-        let route_paths = get_candidates(dexs, tokens);
-        extension.write().await.update_route_paths(route_paths);
+        let limits = extension.read().await.candidate_limits();
+        let pool_hops = get_hops(&dexs, &tokens);
+        let beginning_tokens = get_beginning_tokens(&tokens);
+        let route_paths = get_candidates(dexs, tokens, limits);
+        extension.write().await.update_route_paths(route_paths, pool_hops, beginning_tokens);
         Self { chain_id, extension, repo }
     }
 }
@@ -71,7 +245,9 @@ impl SearcherRpc {
 // dexs / tokens / simulate contract bytecode
 
 // case 1: dexs / tokens => update route paths
-// total number of paths: d*(d-1)*n + d*(d-1)*(d-2)*mC2 + d*(d-1)*(d-2)*(d-3)*mC3
+// each dex is a pool trading one token pair, so paths are the cycles of the token graph:
+// total number of paths: sum over beginning tokens of the 2..=max_hops cycles through it,
+// capped at max_candidates
 // case 2: simulate contract => update bytecode
 #[async_trait]
 impl SearcherRpcApiServer for SearcherRpc {
@@ -120,8 +296,11 @@ impl SearcherRpcApiServer for SearcherRpc {
 
             let updated_dexs = repo.get_all_dexs(chain_id).await.unwrap();
             let updated_tokens = repo.get_all_tokens(chain_id).await.unwrap();
-            let route_paths = get_candidates(updated_dexs, updated_tokens);
-            extension.write().await.update_route_paths(route_paths);
+            let limits = extension.read().await.candidate_limits();
+            let pool_hops = get_hops(&updated_dexs, &updated_tokens);
+            let beginning_tokens = get_beginning_tokens(&updated_tokens);
+            let route_paths = get_candidates(updated_dexs, updated_tokens, limits);
+            extension.write().await.update_route_paths(route_paths, pool_hops, beginning_tokens);
             info!(
                 target: "searcher_rpc",
                 new_tokens = ?params.new_tokens,
@@ -133,4 +312,60 @@ impl SearcherRpcApiServer for SearcherRpc {
 
         Ok(())
     }
+
+    async fn update_candidate_limits(
+        &self,
+        params: UpdateCandidateLimitsParameters
+    ) -> RpcResult<()> {
+        self.extension
+            .write().await
+            .update_candidate_limits(params.max_hops, params.max_candidates)
+            .map_err(|e| ErrorObjectOwned::owned(INVALID_PARAMS_CODE, e.to_string(), None::<()>))?;
+
+        let repo = self.repo.clone();
+        let extension = self.extension.clone();
+        let chain_id = self.chain_id;
+        let _ = tokio::task::spawn(async move {
+            let dexs = repo.get_all_dexs(chain_id).await.unwrap();
+            let tokens = repo.get_all_tokens(chain_id).await.unwrap();
+            let limits = extension.read().await.candidate_limits();
+            let pool_hops = get_hops(&dexs, &tokens);
+            let beginning_tokens = get_beginning_tokens(&tokens);
+            let route_paths = get_candidates(dexs, tokens, limits);
+            info!(
+                target: "searcher_rpc",
+                max_hops = limits.max_hops,
+                max_candidates = limits.max_candidates,
+                route_paths = route_paths.len()
+            );
+            extension.write().await.update_route_paths(route_paths, pool_hops, beginning_tokens);
+        }).await;
+
+        Ok(())
+    }
+
+    async fn pnl_summary(
+        &self,
+        params: PnlSummaryParameters
+    ) -> RpcResult<Vec<PnlSummaryResponse>> {
+        let summaries = self.repo
+            .get_pnl_summary(self.chain_id, params.group_by.into(), params.since).await
+            .map_err(|e| ErrorObjectOwned::owned(INTERNAL_ERROR_CODE, e.to_string(), None::<()>))?;
+        Ok(summaries.into_iter().map(PnlSummaryResponse::from).collect())
+    }
+
+    async fn competitor_arbitrages(
+        &self,
+        params: CompetitorArbitragesParameters
+    ) -> RpcResult<Vec<CompetitorArbitrageResponse>> {
+        let arbitrages = self.repo
+            .get_competitor_arbitrages(
+                self.chain_id,
+                params.from_block,
+                params.to_block,
+                params.match_kind.map(CompetitorMatch::from)
+            ).await
+            .map_err(|e| ErrorObjectOwned::owned(INTERNAL_ERROR_CODE, e.to_string(), None::<()>))?;
+        Ok(arbitrages.into_iter().map(CompetitorArbitrageResponse::from).collect())
+    }
 }